@@ -0,0 +1,165 @@
+//! Generates a square of chunks of the terrain world in memory and reports how much of
+//! every block ended up in them and at what heights, to check the ore and strata table of
+//! the terrain world against what it actually produces.
+//!
+//! Run from the `core` folder:
+//!
+//! ```sh
+//! cargo run --release --bin ore_report
+//! cargo run --release --bin ore_report -- 8 -10 4
+//! ```
+//!
+//! The arguments are the width of the square in chunks and the chunk it starts at.
+
+use std::{collections::BTreeMap, time::Instant};
+
+use nanoid::nanoid;
+use rayon::prelude::*;
+use voxelize::{BlockUtils, Chunk, ChunkOptions, Resources, VoxelAccess};
+use worlds_core::{registry::get_registry, worlds::setup_terrain_world};
+
+/// How many voxels of a block there are and where.
+#[derive(Clone, Copy)]
+struct BlockCount {
+    count: u64,
+    min_y: i32,
+    max_y: i32,
+    sum_y: i64,
+}
+
+impl BlockCount {
+    fn add(&mut self, vy: i32) {
+        self.count += 1;
+        self.min_y = self.min_y.min(vy);
+        self.max_y = self.max_y.max(vy);
+        self.sum_y += vy as i64;
+    }
+
+    fn merge(&mut self, other: &BlockCount) {
+        self.count += other.count;
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_y = self.max_y.max(other.max_y);
+        self.sum_y += other.sum_y;
+    }
+}
+
+impl Default for BlockCount {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            min_y: i32::MAX,
+            max_y: i32::MIN,
+            sum_y: 0,
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: ore_report [size] [start_x start_z]");
+    std::process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse::<i32>().unwrap_or_else(|_| usage()))
+        .collect::<Vec<_>>();
+
+    let size = args.first().cloned().unwrap_or(6);
+    let (start_x, start_z) = match args.len() {
+        0 | 1 => (-size / 2, -size / 2),
+        3 => (args[1], args[2]),
+        _ => usage(),
+    };
+
+    if size <= 0 {
+        usage();
+    }
+
+    let registry = get_registry();
+    let world = setup_terrain_world(&registry);
+    let config = world.config().to_owned();
+    let stages = world.pipeline().stages.to_owned();
+    let options = ChunkOptions {
+        size: config.chunk_size,
+        max_height: config.max_height,
+        sub_chunks: config.sub_chunks,
+    };
+
+    let coords = (start_x..start_x + size)
+        .flat_map(|cx| (start_z..start_z + size).map(move |cz| (cx, cz)))
+        .collect::<Vec<_>>();
+
+    println!(
+        "Generating {} chunks of the terrain world from {}, {}.",
+        coords.len(),
+        start_x,
+        start_z
+    );
+
+    let start = Instant::now();
+
+    let counts = coords
+        .par_iter()
+        .map(|(cx, cz)| {
+            let mut chunk = Chunk::new(&nanoid!(), *cx, *cz, &options);
+
+            for stage in stages.iter() {
+                chunk = stage.process(
+                    chunk,
+                    Resources {
+                        registry: &registry,
+                        config: &config,
+                    },
+                    None,
+                );
+            }
+
+            let mut counts = BTreeMap::<u32, BlockCount>::new();
+
+            for vx in chunk.min.0..chunk.max.0 {
+                for vz in chunk.min.2..chunk.max.2 {
+                    for vy in 0..config.max_height as i32 {
+                        let id = BlockUtils::extract_id(chunk.get_raw_voxel(vx, vy, vz));
+
+                        if !registry.is_air(id) {
+                            counts.entry(id).or_default().add(vy);
+                        }
+                    }
+                }
+            }
+
+            counts
+        })
+        .reduce(BTreeMap::new, |mut all, counts| {
+            for (id, count) in counts.iter() {
+                all.entry(*id).or_default().merge(count);
+            }
+
+            all
+        });
+
+    println!("Generated in {:.1}s\n", start.elapsed().as_secs_f64());
+
+    let solid = counts.values().map(|count| count.count).sum::<u64>();
+    let mut rows = counts.into_iter().collect::<Vec<_>>();
+    rows.sort_by(|(_, a), (_, b)| b.count.cmp(&a.count));
+
+    println!(
+        "{:<20} {:>10} {:>10} {:>9} {:>6} {:>6} {:>7}",
+        "Block", "Voxels", "Per chunk", "Share", "Min y", "Max y", "Mean y"
+    );
+
+    for (id, count) in rows {
+        println!(
+            "{:<20} {:>10} {:>10.1} {:>8.4}% {:>6} {:>6} {:>7.1}",
+            registry.get_block_by_id(id).name,
+            count.count,
+            count.count as f64 / coords.len() as f64,
+            count.count as f64 / solid as f64 * 100.0,
+            count.min_y,
+            count.max_y,
+            count.sum_y as f64 / count.count as f64
+        );
+    }
+}
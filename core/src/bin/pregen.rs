@@ -0,0 +1,308 @@
+//! Generates a range of chunks of a world ahead of time and writes them where the server
+//! loads its saved chunks from, so it can boot without generating them itself.
+//!
+//! Run from the `core` folder:
+//!
+//! ```sh
+//! cargo run --release --bin pregen -- main
+//! cargo run --release --bin pregen -- main -8 -8 7 7 --force
+//! ```
+//!
+//! Without a range every chunk between the world's min and max chunk is generated. Chunks
+//! that are already saved are skipped unless `--force` is given.
+//!
+//! Worlds that only keep the changes made to them, like the terrain world, get their
+//! changes written into the chunks by the last stage of their pipeline like any other. The
+//! chunks are stamped with the world's generator version, and the server deletes the file
+//! of a chunk once it changes again, so it's regenerated with the change on top. Since the
+//! stamp covers every chunk, pregenerating them on a new version needs `--force`.
+//!
+//! Chunks are generated one at a time, without the world around them, so worlds with a
+//! stage that needs the voxels of neighboring chunks can't be pregenerated. The files are
+//! written in the format voxelize loads saved chunks from, which the tests at the bottom
+//! check a chunk survives.
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{write::ZlibEncoder, Compression};
+use nanoid::nanoid;
+use rayon::prelude::*;
+use serde::Serialize;
+use voxelize::{Chunk, ChunkOptions, ChunkUtils, Resources, Vec2, VoxelAccess, World};
+use worlds_core::{
+    registry::get_registry,
+    worlds::{saves_deltas, setup_flat_world, setup_main_world, setup_terrain_world, ChunkDeltas},
+};
+
+/// How many chunks are generated before they're written out, so a big range doesn't have
+/// to fit in memory all at once.
+const BATCH_SIZE: usize = 256;
+
+/// The chunk file format the server saves and loads. Voxels and height maps are the
+/// little endian bytes of their values, zlib compressed and base64 encoded.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChunkFileData {
+    id: String,
+    voxels: String,
+    height_map: String,
+}
+
+fn encode(data: &[u32]) -> String {
+    let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+
+    for value in data {
+        encoder.write_all(&value.to_le_bytes()).unwrap();
+    }
+
+    STANDARD.encode(encoder.finish().unwrap())
+}
+
+fn chunk_path(folder: &Path, coords: &Vec2<i32>) -> PathBuf {
+    folder.join(format!(
+        "{}.json",
+        ChunkUtils::get_chunk_name(coords.0, coords.1)
+    ))
+}
+
+fn save_chunk(folder: &Path, chunk: &Chunk) -> Result<(), String> {
+    let mut height_map = vec![];
+
+    for vx in chunk.min.0..chunk.max.0 {
+        for vz in chunk.min.2..chunk.max.2 {
+            height_map.push(chunk.get_max_height(vx, vz));
+        }
+    }
+
+    let data = ChunkFileData {
+        id: chunk.id.to_owned(),
+        voxels: encode(&chunk.voxels.data),
+        height_map: encode(&height_map),
+    };
+
+    let file = File::create(chunk_path(folder, &chunk.coords)).map_err(|e| e.to_string())?;
+    serde_json::to_writer(file, &data).map_err(|e| e.to_string())
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: pregen <main|flat|terrain> [min_x min_z max_x max_z] [--force]");
+    std::process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let force = args.iter().any(|arg| arg == "--force");
+    let args = args
+        .into_iter()
+        .filter(|arg| arg != "--force")
+        .collect::<Vec<_>>();
+
+    let registry = get_registry();
+
+    let world: World = match args.first().map(|name| name.as_str()) {
+        Some("main") => setup_main_world(&registry),
+        Some("flat") => setup_flat_world(&registry),
+        Some("terrain") => setup_terrain_world(&registry),
+        _ => usage(),
+    };
+
+    let config = world.config().to_owned();
+
+    if !config.saving {
+        eprintln!(
+            "The {} world isn't saved, there's nothing to pregenerate.",
+            args[0]
+        );
+        std::process::exit(1);
+    }
+
+    let spaced = world
+        .pipeline()
+        .stages
+        .iter()
+        .filter(|stage| stage.needs_space().is_some())
+        .map(|stage| stage.name())
+        .collect::<Vec<_>>();
+
+    if !spaced.is_empty() {
+        eprintln!(
+            "The {} world has stages that need the chunks around them ({}), which can only \
+             run in the server.",
+            args[0],
+            spaced.join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    let (min, max) = match args[1..]
+        .iter()
+        .map(|arg| arg.parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(range) if range.is_empty() => (config.min_chunk, config.max_chunk),
+        Ok(range) if range.len() == 4 => ([range[0], range[1]], [range[2], range[3]]),
+        _ => usage(),
+    };
+
+    if saves_deltas(&world) {
+        let deltas = world.read_resource::<ChunkDeltas>();
+
+        match deltas.chunks_version() {
+            Some(version) if version != deltas.version && !force => {
+                eprintln!(
+                    "The {} world's chunks were pregenerated on generator version {}, but it's \
+                     at version {} now. Run with --force to pregenerate all of them again.",
+                    args[0], version, deltas.version
+                );
+                std::process::exit(1);
+            }
+            _ => {}
+        }
+    }
+
+    let folder = PathBuf::from(&config.save_dir).join("chunks");
+    fs::create_dir_all(&folder).expect("Failed to create the chunks folder");
+
+    let coords = (min[0]..=max[0])
+        .flat_map(|cx| (min[1]..=max[1]).map(move |cz| Vec2(cx, cz)))
+        .filter(|coords| force || !chunk_path(&folder, coords).exists())
+        .collect::<Vec<_>>();
+
+    let stages = world.pipeline().stages.to_owned();
+    let options = ChunkOptions {
+        size: config.chunk_size,
+        max_height: config.max_height,
+        sub_chunks: config.sub_chunks,
+    };
+
+    println!(
+        "Generating {} chunks of the {} world on {} threads, {} stages each.",
+        coords.len(),
+        args[0],
+        rayon::current_num_threads(),
+        stages.len()
+    );
+
+    let total = coords.len();
+    let done = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let start = Instant::now();
+
+    for batch in coords.chunks(BATCH_SIZE) {
+        batch.par_iter().for_each(|Vec2(cx, cz)| {
+            let mut chunk = Chunk::new(&nanoid!(), *cx, *cz, &options);
+
+            for stage in stages.iter() {
+                chunk = stage.process(
+                    chunk,
+                    Resources {
+                        registry: &registry,
+                        config: &config,
+                    },
+                    None,
+                );
+            }
+
+            if let Err(e) = save_chunk(&folder, &chunk) {
+                eprintln!("Failed to save chunk {}|{}: {}", cx, cz, e);
+                failed.fetch_add(1, Ordering::Relaxed);
+            }
+
+            done.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let done = done.load(Ordering::Relaxed);
+        let elapsed = start.elapsed().as_secs_f64();
+        let remaining = elapsed / done as f64 * (total - done) as f64;
+
+        println!(
+            "{}/{} chunks ({:.1}%), {:.0}s elapsed, about {:.0}s left",
+            done,
+            total,
+            done as f64 / total as f64 * 100.0,
+            elapsed,
+            remaining
+        );
+    }
+
+    if saves_deltas(&world) {
+        if let Err(e) = world.read_resource::<ChunkDeltas>().stamp_chunks() {
+            eprintln!(
+                "Failed to stamp the chunks with the generator version: {}",
+                e
+            );
+        }
+    }
+
+    println!(
+        "Done in {:.1}s, {} chunks failed to save.",
+        start.elapsed().as_secs_f64(),
+        failed.load(Ordering::Relaxed)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+    use serde_json::Value;
+
+    use super::*;
+
+    fn decode(data: &str) -> Vec<u32> {
+        let bytes = STANDARD.decode(data).unwrap();
+        let mut decoded = vec![];
+        ZlibDecoder::new(&bytes[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+
+        decoded
+            .chunks(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn saved_chunks_round_trip() {
+        let folder = std::env::temp_dir().join(format!("pregen-test-{}", nanoid!()));
+        fs::create_dir_all(&folder).unwrap();
+
+        let options = ChunkOptions {
+            size: 16,
+            max_height: 64,
+            sub_chunks: 4,
+        };
+        let mut chunk = Chunk::new("test", -1, 2, &options);
+
+        chunk.set_voxel(-16, 0, 32, 1);
+        chunk.set_voxel(-3, 10, 40, 2);
+        chunk.set_raw_voxel(-8, 63, 47, 0xabcd_0003);
+        chunk.set_max_height(-3, 40, 10);
+        chunk.set_max_height(-8, 47, 63);
+
+        save_chunk(&folder, &chunk).unwrap();
+
+        let path = chunk_path(&folder, &Vec2(-1, 2));
+        assert_eq!(path.file_name().unwrap(), "-1|2.json");
+
+        let saved: Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(saved["id"], "test");
+        assert_eq!(decode(saved["voxels"].as_str().unwrap()), chunk.voxels.data);
+
+        // Columns go x first, then z, from the chunk's lowest corner.
+        let height_map = decode(saved["heightMap"].as_str().unwrap());
+        assert_eq!(height_map.len(), 16 * 16);
+        assert_eq!(height_map[13 * 16 + 8], 10);
+        assert_eq!(height_map[8 * 16 + 15], 63);
+    }
+}
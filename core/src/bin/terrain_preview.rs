@@ -0,0 +1,262 @@
+//! Renders the terrain world's land as PNGs straight from its noise, without starting the
+//! server or generating any chunks, to see what a change to the terrain does in seconds.
+//!
+//! Run from the `core` folder:
+//!
+//! ```sh
+//! cargo run --release --bin terrain_preview
+//! cargo run --release --bin terrain_preview -- 2000 -500 512 4 preview
+//! ```
+//!
+//! The arguments are the voxel the images are centered on, their width in pixels, how many
+//! voxels one pixel covers and the folder they're written to. It writes:
+//!
+//! - `heightmap.png`, the surface height in gray, black at 0 and white at the max height.
+//! - One image per terrain layer with its raw noise, black at its lowest and white at its
+//!   highest value in view.
+//! - `biomes.png`, the biome of every column.
+//! - `relief.png`, the land shaded as if lit from the north west, with water in blue.
+
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use rayon::prelude::*;
+use voxelize::SeededNoise;
+use worlds_core::{
+    registry::get_registry,
+    worlds::terrain::{
+        biomes::terrain_biomes, build_terrain, rough_surface_height, terrain_config,
+        terrain_layer_noise,
+    },
+};
+
+/// How far apart the height search first samples a column, see `rough_surface_height`.
+/// Missing the odd thin overhang is fine for a look.
+const SEARCH_STEP: i32 = 4;
+
+/// The direction the relief is lit from, up and to the north west.
+const LIGHT: [f64; 3] = [-0.5, 0.7, -0.5];
+
+fn biome_color(name: &str) -> [u8; 3] {
+    match name {
+        "Plains" => [124, 189, 86],
+        "Forest" => [46, 120, 52],
+        "Desert" => [226, 205, 134],
+        "Tundra" => [222, 234, 240],
+        "Beach" => [240, 224, 170],
+        _ => [128, 128, 128],
+    }
+}
+
+/// The color of land at a share of the max height, from low green to high gray and white.
+fn height_color(height: f64) -> [f64; 3] {
+    const STOPS: [(f64, [f64; 3]); 5] = [
+        (0.0, [70.0, 130.0, 60.0]),
+        (0.45, [120.0, 170.0, 80.0]),
+        (0.6, [150.0, 130.0, 90.0]),
+        (0.75, [130.0, 125.0, 120.0]),
+        (0.9, [245.0, 245.0, 250.0]),
+    ];
+
+    let height = height.clamp(0.0, 1.0);
+
+    for pair in STOPS.windows(2) {
+        let (from, from_color) = pair[0];
+        let (to, to_color) = pair[1];
+
+        if height <= to {
+            let t = ((height - from) / (to - from)).clamp(0.0, 1.0);
+            return [0, 1, 2].map(|i| from_color[i] + (to_color[i] - from_color[i]) * t);
+        }
+    }
+
+    STOPS[STOPS.len() - 1].1
+}
+
+fn write_png(path: &Path, width: u32, height: u32, color: png::ColorType, data: &[u8]) {
+    let file = File::create(path).expect("Failed to create the image file");
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(data))
+        .expect("Failed to write the image");
+
+    println!("Wrote {:?}", path);
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: terrain_preview [center_x center_z] [size] [scale] [out_dir]");
+    std::process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let number = |index: usize, default: i32| match args.get(index) {
+        Some(arg) => arg.parse::<i32>().unwrap_or_else(|_| usage()),
+        None => default,
+    };
+
+    let (center_x, center_z) = (number(0, 0), number(1, 0));
+    let size = number(2, 512);
+    let scale = number(3, 2);
+    let folder = PathBuf::from(args.get(4).map(|arg| arg.as_str()).unwrap_or("preview"));
+
+    if size <= 0 || scale <= 0 {
+        usage();
+    }
+
+    fs::create_dir_all(&folder).expect("Failed to create the output folder");
+
+    let registry = get_registry();
+    let config = terrain_config();
+    let terrain = build_terrain(&config);
+    let biomes = terrain_biomes(&registry);
+
+    let max_height = config.max_height as i32;
+    let water_level = config.water_level as i32;
+    let pixels = (size * size) as usize;
+
+    // The voxel column a pixel stands for.
+    let column = |index: usize| {
+        let (px, pz) = (index as i32 % size, index as i32 / size);
+        (
+            center_x + (px - size / 2) * scale,
+            center_z + (pz - size / 2) * scale,
+        )
+    };
+
+    println!(
+        "Rendering {}x{} voxels around {}, {} at {} voxels a pixel.",
+        size * scale,
+        size * scale,
+        center_x,
+        center_z,
+        scale
+    );
+
+    let start = Instant::now();
+
+    let heights = (0..pixels)
+        .into_par_iter()
+        .map(|index| {
+            let (vx, vz) = column(index);
+            rough_surface_height(&terrain, vx, vz, max_height, SEARCH_STEP)
+        })
+        .collect::<Vec<_>>();
+
+    println!("Found the surface in {:.2}s", start.elapsed().as_secs_f64());
+
+    let heightmap = heights
+        .iter()
+        .map(|&height| (height * 255 / (max_height - 1).max(1)) as u8)
+        .collect::<Vec<_>>();
+
+    write_png(
+        &folder.join("heightmap.png"),
+        size as u32,
+        size as u32,
+        png::ColorType::Grayscale,
+        &heightmap,
+    );
+
+    for (name, seed, options) in terrain_layer_noise() {
+        let noise = SeededNoise::new(seed, &options);
+        let values = (0..pixels)
+            .into_par_iter()
+            .map(|index| {
+                let (vx, vz) = column(index);
+                noise.get2d(vx, vz)
+            })
+            .collect::<Vec<_>>();
+
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let range = (max - min).max(f64::EPSILON);
+
+        println!("{} ranges from {:.3} to {:.3}", name, min, max);
+
+        let image = values
+            .iter()
+            .map(|value| ((value - min) / range * 255.0) as u8)
+            .collect::<Vec<_>>();
+
+        write_png(
+            &folder.join(format!("{}.png", name)),
+            size as u32,
+            size as u32,
+            png::ColorType::Grayscale,
+            &image,
+        );
+    }
+
+    let biome_map = (0..pixels)
+        .into_par_iter()
+        .flat_map_iter(|index| {
+            let (vx, vz) = column(index);
+            biome_color(&biomes.at(&terrain, vx, heights[index], vz).name)
+        })
+        .collect::<Vec<_>>();
+
+    write_png(
+        &folder.join("biomes.png"),
+        size as u32,
+        size as u32,
+        png::ColorType::Rgb,
+        &biome_map,
+    );
+
+    let length = LIGHT.iter().map(|v| v * v).sum::<f64>().sqrt();
+    let light = LIGHT.map(|v| v / length);
+
+    let relief = (0..pixels)
+        .into_par_iter()
+        .flat_map_iter(|index| {
+            let (px, pz) = (index as i32 % size, index as i32 / size);
+            let height_at = |x: i32, z: i32| {
+                heights[(z.clamp(0, size - 1) * size + x.clamp(0, size - 1)) as usize] as f64
+            };
+
+            let height = heights[index];
+
+            if height < water_level {
+                let depth = ((water_level - height) as f64 / 32.0).min(1.0);
+                return [
+                    (60.0 - 40.0 * depth) as u8,
+                    (120.0 - 60.0 * depth) as u8,
+                    (200.0 - 60.0 * depth) as u8,
+                ];
+            }
+
+            // The surface normal from the slope to the neighboring pixels, with the height
+            // differences spread over the voxels one pixel covers.
+            let dx = (height_at(px + 1, pz) - height_at(px - 1, pz)) / (2.0 * scale as f64);
+            let dz = (height_at(px, pz + 1) - height_at(px, pz - 1)) / (2.0 * scale as f64);
+            let length = (dx * dx + 1.0 + dz * dz).sqrt();
+            let normal = [-dx / length, 1.0 / length, -dz / length];
+
+            let shade = (normal[0] * light[0] + normal[1] * light[1] + normal[2] * light[2])
+                .clamp(0.0, 1.0);
+            let shade = 0.35 + 0.65 * shade;
+
+            height_color(height as f64 / max_height as f64).map(|c| (c * shade).min(255.0) as u8)
+        })
+        .collect::<Vec<_>>();
+
+    write_png(
+        &folder.join("relief.png"),
+        size as u32,
+        size as u32,
+        png::ColorType::Rgb,
+        &relief,
+    );
+
+    println!("Done in {:.2}s", start.elapsed().as_secs_f64());
+}
@@ -0,0 +1,175 @@
+use voxelize::{Registry, Vec3, VoxelAccess};
+
+/// How many ticks a falling block waits before settling. Each settled block turns its old
+/// position into air, which wakes up the block above it, so a column collapses one block
+/// per delay instead of all at once.
+pub const FALL_DELAY_TICKS: u64 = 2;
+
+/// The furthest a single block can drop in one update. Blocks that have further to fall
+/// continue from where they stopped on their next update.
+pub const MAX_FALL_DISTANCE: i32 = 32;
+
+/// Whether a falling block can drop into a voxel. Below the world counts as solid, so
+/// blocks at the bottom stay where they are.
+fn can_fall_into(space: &dyn VoxelAccess, vx: i32, vy: i32, vz: i32, registry: &Registry) -> bool {
+    if vy < 0 {
+        return false;
+    }
+
+    let id = space.get_voxel(vx, vy, vz);
+    registry.is_air(id) || registry.get_block_by_id(id).is_fluid
+}
+
+/// Column settlement: move the block at `voxel` down to the lowest air or fluid voxel
+/// below it, keeping its rotation and stage.
+pub fn settle(
+    voxel: &Vec3<i32>,
+    space: &dyn VoxelAccess,
+    registry: &Registry,
+) -> Vec<(Vec3<i32>, u32)> {
+    let &Vec3(vx, vy, vz) = voxel;
+
+    if !can_fall_into(space, vx, vy - 1, vz, registry) {
+        return vec![];
+    }
+
+    let raw = space.get_raw_voxel(vx, vy, vz);
+
+    let mut landing = vy - 1;
+
+    while landing > 0
+        && vy - landing < MAX_FALL_DISTANCE
+        && can_fall_into(space, vx, landing - 1, vz, registry)
+    {
+        landing -= 1;
+    }
+
+    vec![(Vec3(vx, vy, vz), 0), (Vec3(vx, landing, vz), raw)]
+}
+
+#[cfg(test)]
+mod tests {
+    use voxelize::{Chunk, ChunkOptions};
+
+    use crate::registry::test_registry;
+
+    use super::*;
+
+    const SAND: u32 = 50;
+    const STONE: u32 = 2;
+    const WATER: u32 = 30000;
+
+    fn chunk() -> Chunk {
+        Chunk::new(
+            "test",
+            0,
+            0,
+            &ChunkOptions {
+                size: 16,
+                max_height: 64,
+                sub_chunks: 4,
+            },
+        )
+    }
+
+    #[test]
+    fn settles_on_the_first_solid_block() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 3, 4, STONE);
+        chunk.set_voxel(4, 10, 4, SAND);
+
+        let updates = settle(&Vec3(4, 10, 4), &chunk, test_registry());
+
+        assert_eq!(updates, vec![(Vec3(4, 10, 4), 0), (Vec3(4, 4, 4), SAND)]);
+    }
+
+    #[test]
+    fn stays_on_solid_ground() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 9, 4, STONE);
+        chunk.set_voxel(4, 10, 4, SAND);
+
+        assert!(settle(&Vec3(4, 10, 4), &chunk, test_registry()).is_empty());
+    }
+
+    #[test]
+    fn stays_at_the_bottom_of_the_world() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 0, 4, SAND);
+
+        assert!(settle(&Vec3(4, 0, 4), &chunk, test_registry()).is_empty());
+    }
+
+    #[test]
+    fn sinks_through_fluids() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 3, 4, STONE);
+
+        for vy in 4..8 {
+            chunk.set_voxel(4, vy, 4, WATER);
+        }
+
+        chunk.set_voxel(4, 8, 4, SAND);
+
+        let updates = settle(&Vec3(4, 8, 4), &chunk, test_registry());
+
+        assert_eq!(updates, vec![(Vec3(4, 8, 4), 0), (Vec3(4, 4, 4), SAND)]);
+    }
+
+    #[test]
+    fn keeps_its_stage() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 10, 4, SAND);
+        chunk.set_voxel_stage(4, 10, 4, 3);
+
+        let raw = chunk.get_raw_voxel(4, 10, 4);
+        let updates = settle(&Vec3(4, 10, 4), &chunk, test_registry());
+
+        assert_eq!(updates.last(), Some(&(Vec3(4, 0, 4), raw)));
+    }
+
+    #[test]
+    fn falls_at_most_the_max_distance() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 50, 4, SAND);
+
+        let updates = settle(&Vec3(4, 50, 4), &chunk, test_registry());
+
+        assert_eq!(
+            updates,
+            vec![
+                (Vec3(4, 50, 4), 0),
+                (Vec3(4, 50 - MAX_FALL_DISTANCE, 4), SAND)
+            ]
+        );
+    }
+
+    #[test]
+    fn a_column_settles_one_block_at_a_time() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 2, 4, STONE);
+
+        for vy in 8..11 {
+            chunk.set_voxel(4, vy, 4, SAND);
+        }
+
+        // Only the bottom block has anywhere to go until it moves.
+        assert!(settle(&Vec3(4, 10, 4), &chunk, test_registry()).is_empty());
+        assert!(settle(&Vec3(4, 9, 4), &chunk, test_registry()).is_empty());
+
+        for vy in 8..11 {
+            for (Vec3(vx, vy, vz), id) in settle(&Vec3(4, vy, 4), &chunk, test_registry()) {
+                chunk.set_voxel(vx, vy, vz, id);
+            }
+        }
+
+        let column = (0..12)
+            .map(|vy| chunk.get_voxel(4, vy, 4))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            column,
+            vec![0, 0, STONE, SAND, SAND, SAND, 0, 0, 0, 0, 0, 0]
+        );
+    }
+}
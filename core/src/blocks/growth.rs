@@ -0,0 +1,220 @@
+use voxelize::{Registry, Vec3, VoxelAccess};
+
+/// Grass only spreads onto dirt that gets at least this much sunlight.
+const GRASS_SPREAD_SUNLIGHT: u32 = 9;
+
+/// Mushrooms only spread into spots darker than this.
+const MUSHROOM_SPREAD_SUNLIGHT: u32 = 12;
+
+/// Mushrooms stop spreading once this many are within `MUSHROOM_CROWD_RADIUS`.
+const MUSHROOM_CROWD_LIMIT: usize = 5;
+const MUSHROOM_CROWD_RADIUS: i32 = 4;
+
+fn random_offset(horizontal: i32, below: i32, above: i32) -> (i32, i32, i32) {
+    (
+        fastrand::i32(-horizontal..=horizontal),
+        fastrand::i32(-below..=above),
+        fastrand::i32(-horizontal..=horizontal),
+    )
+}
+
+fn is_opaque(id: u32, registry: &Registry) -> bool {
+    !registry.is_air(id) && registry.get_block_by_id(id).is_opaque
+}
+
+/// Grass blocks covered by an opaque block decay back into dirt. Otherwise, they spread onto
+/// a random nearby dirt block that is lit and not covered.
+pub fn grass_random_tick(
+    voxel: &Vec3<i32>,
+    space: &dyn VoxelAccess,
+    registry: &Registry,
+) -> Vec<(Vec3<i32>, u32)> {
+    let &Vec3(vx, vy, vz) = voxel;
+
+    let dirt = registry.get_block_by_name("Dirt");
+    let grass_block = registry.get_block_by_name("Grass Block");
+
+    if is_opaque(space.get_voxel(vx, vy + 1, vz), registry) {
+        return vec![(Vec3(vx, vy, vz), dirt.id)];
+    }
+
+    let (dx, dy, dz) = random_offset(1, 3, 1);
+    let (tx, ty, tz) = (vx + dx, vy + dy, vz + dz);
+
+    if space.get_voxel(tx, ty, tz) != dirt.id
+        || is_opaque(space.get_voxel(tx, ty + 1, tz), registry)
+        || space.get_sunlight(tx, ty + 1, tz) < GRASS_SPREAD_SUNLIGHT
+    {
+        return vec![];
+    }
+
+    vec![(Vec3(tx, ty, tz), grass_block.id)]
+}
+
+/// Mushrooms spread to a random nearby dark spot that has a block to grow on, as long as the
+/// area is not already crowded with mushrooms.
+pub fn mushroom_random_tick(
+    voxel: &Vec3<i32>,
+    space: &dyn VoxelAccess,
+    registry: &Registry,
+) -> Vec<(Vec3<i32>, u32)> {
+    let &Vec3(vx, vy, vz) = voxel;
+    let mushroom = space.get_voxel(vx, vy, vz);
+
+    let (dx, dy, dz) = random_offset(1, 1, 1);
+    let (tx, ty, tz) = (vx + dx, vy + dy, vz + dz);
+
+    if !registry.is_air(space.get_voxel(tx, ty, tz))
+        || !is_opaque(space.get_voxel(tx, ty - 1, tz), registry)
+        || space.get_sunlight(tx, ty, tz) >= MUSHROOM_SPREAD_SUNLIGHT
+    {
+        return vec![];
+    }
+
+    let mut count = 0;
+
+    for ox in -MUSHROOM_CROWD_RADIUS..=MUSHROOM_CROWD_RADIUS {
+        for oy in -1..=1 {
+            for oz in -MUSHROOM_CROWD_RADIUS..=MUSHROOM_CROWD_RADIUS {
+                if space.get_voxel(vx + ox, vy + oy, vz + oz) == mushroom {
+                    count += 1;
+
+                    if count >= MUSHROOM_CROWD_LIMIT {
+                        return vec![];
+                    }
+                }
+            }
+        }
+    }
+
+    vec![(Vec3(tx, ty, tz), mushroom)]
+}
+
+#[cfg(test)]
+mod tests {
+    use voxelize::{Chunk, ChunkOptions};
+
+    use crate::{blocks::RandomTickFn, registry::test_registry};
+
+    use super::*;
+
+    const DIRT: u32 = 1;
+    const STONE: u32 = 2;
+    const GRASS_BLOCK: u32 = 30001;
+    const MUSHROOM: u32 = 2000;
+
+    /// How many random ticks each test runs, enough to try every spot around a block.
+    const TICKS: usize = 200;
+
+    /// A chunk with a floor of `floor` at y 9, lit by `sunlight` right above it.
+    fn chunk(floor: u32, sunlight: u32) -> Chunk {
+        let mut chunk = Chunk::new(
+            "test",
+            0,
+            0,
+            &ChunkOptions {
+                size: 16,
+                max_height: 64,
+                sub_chunks: 4,
+            },
+        );
+
+        for vx in 0..16 {
+            for vz in 0..16 {
+                chunk.set_voxel(vx, 9, vz, floor);
+                chunk.set_sunlight(vx, 10, vz, sunlight);
+            }
+        }
+
+        chunk
+    }
+
+    fn tick(chunk: &Chunk, voxel: Vec3<i32>, random_tick: RandomTickFn) -> Vec<(Vec3<i32>, u32)> {
+        fastrand::seed(7);
+
+        (0..TICKS)
+            .flat_map(|_| random_tick(&voxel, chunk, test_registry()))
+            .collect()
+    }
+
+    #[test]
+    fn covered_grass_turns_into_dirt() {
+        let mut chunk = chunk(DIRT, 15);
+        chunk.set_voxel(8, 9, 8, GRASS_BLOCK);
+        chunk.set_voxel(8, 10, 8, STONE);
+
+        assert_eq!(
+            grass_random_tick(&Vec3(8, 9, 8), &chunk, test_registry()),
+            vec![(Vec3(8, 9, 8), DIRT)]
+        );
+    }
+
+    #[test]
+    fn grass_spreads_onto_lit_dirt_next_to_it() {
+        let mut chunk = chunk(DIRT, 15);
+        chunk.set_voxel(8, 9, 8, GRASS_BLOCK);
+
+        let updates = tick(&chunk, Vec3(8, 9, 8), grass_random_tick);
+
+        assert!(!updates.is_empty());
+
+        for (Vec3(vx, vy, vz), id) in updates {
+            assert_eq!(id, GRASS_BLOCK);
+            assert_eq!(chunk.get_voxel(vx, vy, vz), DIRT);
+            assert!((vx - 8).abs() <= 1 && (vz - 8).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn grass_does_not_spread_in_the_dark() {
+        let mut chunk = chunk(DIRT, GRASS_SPREAD_SUNLIGHT - 1);
+        chunk.set_voxel(8, 9, 8, GRASS_BLOCK);
+
+        assert!(tick(&chunk, Vec3(8, 9, 8), grass_random_tick).is_empty());
+    }
+
+    #[test]
+    fn mushrooms_spread_into_dark_air_on_solid_ground() {
+        let mut chunk = chunk(STONE, 0);
+        chunk.set_voxel(8, 10, 8, MUSHROOM);
+
+        let updates = tick(&chunk, Vec3(8, 10, 8), mushroom_random_tick);
+
+        assert!(!updates.is_empty());
+
+        for (Vec3(vx, vy, vz), id) in updates {
+            assert_eq!(id, MUSHROOM);
+            assert_eq!(vy, 10);
+            assert_eq!(chunk.get_voxel(vx, vy, vz), 0);
+        }
+    }
+
+    #[test]
+    fn mushrooms_do_not_spread_in_the_light() {
+        let mut chunk = chunk(STONE, MUSHROOM_SPREAD_SUNLIGHT);
+        chunk.set_voxel(8, 10, 8, MUSHROOM);
+
+        assert!(tick(&chunk, Vec3(8, 10, 8), mushroom_random_tick).is_empty());
+    }
+
+    #[test]
+    fn mushrooms_stop_spreading_once_crowded() {
+        let mut chunk = chunk(STONE, 0);
+        let r = MUSHROOM_CROWD_RADIUS;
+
+        // The mushroom itself and the ones at the corners of its crowd radius.
+        let crowd = [(0, 0), (-r, -r), (-r, r), (r, -r), (r, r)];
+        assert_eq!(crowd.len(), MUSHROOM_CROWD_LIMIT);
+
+        for (dx, dz) in crowd {
+            chunk.set_voxel(8 + dx, 10, 8 + dz, MUSHROOM);
+        }
+
+        assert!(tick(&chunk, Vec3(8, 10, 8), mushroom_random_tick).is_empty());
+
+        // One fewer and it spreads again.
+        chunk.set_voxel(8 + r, 10, 8 + r, 0);
+
+        assert!(!tick(&chunk, Vec3(8, 10, 8), mushroom_random_tick).is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+
+use hashbrown::HashSet;
+use voxelize::{BlockBuilder, ChunkUtils, Chunks, LightColor, Registry, Vec2, Vec3, VoxelAccess};
+
+use super::get_block_properties;
+
+pub const WARM_LIGHT: [u32; 3] = [14, 10, 5];
+pub const YELLOW_LIGHT: [u32; 3] = [15, 13, 4];
+pub const BLUE_LIGHT: [u32; 3] = [5, 8, 15];
+pub const PALE_LIGHT: [u32; 3] = [7, 8, 9];
+pub const RED_LIGHT: [u32; 3] = [15, 5, 5];
+pub const WHITE_LIGHT: [u32; 3] = [15, 15, 15];
+pub const GOLD_LIGHT: [u32; 3] = [15, 12, 6];
+
+const TORCH_CHANNELS: [LightColor; 3] = [LightColor::Red, LightColor::Green, LightColor::Blue];
+
+const NEIGHBORS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// Colored light emission on top of voxelize's `torch_light_level`, which lights up
+/// all three channels equally.
+pub trait Emission {
+    fn emission(self, color: [u32; 3]) -> Self;
+}
+
+impl Emission for BlockBuilder {
+    fn emission(self, [red, green, blue]: [u32; 3]) -> Self {
+        self.red_light_level(red)
+            .green_light_level(green)
+            .blue_light_level(blue)
+    }
+}
+
+/// Dim the torch light that passes through the tinting blocks of a lit chunk. For every
+/// tinting voxel, each color channel that the tint filters out is darkened along the path it
+/// flooded out of that voxel, on into the neighboring chunks. Returns the chunks whose light
+/// changed.
+///
+/// Only light that is still exactly as voxelize flooded it gets darkened, so tinting a chunk
+/// again without relighting it changes nothing.
+pub fn apply_light_tints(
+    chunks: &mut Chunks,
+    coords: &Vec2<i32>,
+    chunk_size: usize,
+    registry: &Registry,
+) -> HashSet<Vec2<i32>> {
+    let mut changed = HashSet::new();
+
+    let chunk = match chunks.raw(coords) {
+        Some(chunk) => chunk,
+        None => return changed,
+    };
+
+    let Vec3(min_x, min_y, min_z) = chunk.min;
+    let Vec3(max_x, max_y, max_z) = chunk.max;
+
+    let mut tinted = vec![];
+
+    for vx in min_x..max_x {
+        for vz in min_z..max_z {
+            let height = chunk.get_max_height(vx, vz) as i32;
+
+            for vy in min_y..max_y.min(height + 1) {
+                let id = chunk.get_voxel(vx, vy, vz);
+
+                if let Some(tint) = get_block_properties(id).and_then(|p| p.tint) {
+                    tinted.push((Vec3(vx, vy, vz), tint));
+                }
+            }
+        }
+    }
+
+    for (Vec3(vx, vy, vz), tint) in tinted {
+        for (color, factor) in TORCH_CHANNELS.iter().zip(tint) {
+            let level = chunks.get_torch_light(vx, vy, vz, color);
+            let reduction = level - (level as f32 * factor).floor() as u32;
+
+            if level <= 1 || reduction == 0 {
+                continue;
+            }
+
+            let mut queue = VecDeque::new();
+
+            for [dx, dy, dz] in NEIGHBORS {
+                queue.push_back((Vec3(vx + dx, vy + dy, vz + dz), level - 1));
+            }
+
+            // Follow the light downhill: a neighbor that is exactly one level darker was lit
+            // through this voxel, so it gets the same reduction. Chunks that aren't loaded
+            // have no light, so the search stops at them.
+            while let Some((Vec3(nx, ny, nz), expected)) = queue.pop_front() {
+                if expected == 0
+                    || ny < min_y
+                    || ny >= max_y
+                    || chunks.get_torch_light(nx, ny, nz, color) != expected
+                    || registry
+                        .get_block_by_id(chunks.get_voxel(nx, ny, nz))
+                        .is_opaque
+                {
+                    continue;
+                }
+
+                chunks.set_torch_light(nx, ny, nz, expected.saturating_sub(reduction), color);
+                changed.insert(ChunkUtils::map_voxel_to_chunk(nx, ny, nz, chunk_size));
+
+                for [dx, dy, dz] in NEIGHBORS {
+                    queue.push_back((Vec3(nx + dx, ny + dy, nz + dz), expected - 1));
+                }
+            }
+        }
+    }
+
+    changed
+}
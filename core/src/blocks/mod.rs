@@ -0,0 +1,117 @@
+mod falling;
+mod growth;
+mod lights;
+mod support;
+
+use std::sync::OnceLock;
+
+use hashbrown::HashMap;
+use voxelize::{Registry, Vec3, VoxelAccess};
+
+pub use growth::{grass_random_tick, mushroom_random_tick};
+pub use lights::*;
+pub use support::{collapse_neighbors, Support, SupportFace, Unsupported};
+
+/// Called on a voxel picked by the random-tick system. Returns the voxel updates to apply.
+pub type RandomTickFn = fn(&Vec3<i32>, &dyn VoxelAccess, &Registry) -> Vec<(Vec3<i32>, u32)>;
+
+/// Extra per-block behavior that voxelize's `Block` has no field for.
+/// Looked up by block id from the active functions registered in `registry.rs`.
+#[derive(Debug, Default, Clone)]
+pub struct BlockProperties {
+    /// Whether this block falls down when the block below it becomes air or fluid.
+    pub falls: bool,
+    /// The neighbors this block needs to stay in place, and what happens once they are gone.
+    pub requires_support: Option<Support>,
+    /// What this block does when the random-tick system picks it, such as spreading or decaying.
+    pub random_tick: Option<RandomTickFn>,
+    /// How much of each red, green and blue torch light passes through this block.
+    pub tint: Option<[f32; 3]>,
+}
+
+impl BlockProperties {
+    // Like voxelize's `Block::new`, this starts a builder.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> BlockPropertiesBuilder {
+        BlockPropertiesBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct BlockPropertiesBuilder {
+    falls: bool,
+    requires_support: Option<Support>,
+    random_tick: Option<RandomTickFn>,
+    tint: Option<[f32; 3]>,
+}
+
+impl BlockPropertiesBuilder {
+    pub fn falls(mut self, falls: bool) -> Self {
+        self.falls = falls;
+        self
+    }
+
+    pub fn requires_support(mut self, support: Support) -> Self {
+        self.requires_support = Some(support);
+        self
+    }
+
+    pub fn random_tick(mut self, random_tick: RandomTickFn) -> Self {
+        self.random_tick = Some(random_tick);
+        self
+    }
+
+    pub fn tint(mut self, tint: [f32; 3]) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn build(self) -> BlockProperties {
+        BlockProperties {
+            falls: self.falls,
+            requires_support: self.requires_support,
+            random_tick: self.random_tick,
+            tint: self.tint,
+        }
+    }
+}
+
+static PROPERTIES: OnceLock<HashMap<u32, BlockProperties>> = OnceLock::new();
+
+/// Set the properties of every block. Can only be called once, from `get_registry`.
+pub fn set_block_properties(properties: HashMap<u32, BlockProperties>) {
+    if PROPERTIES.set(properties).is_err() {
+        panic!("Block properties have already been set.");
+    }
+}
+
+pub fn get_block_properties(id: u32) -> Option<&'static BlockProperties> {
+    PROPERTIES.get().and_then(|properties| properties.get(&id))
+}
+
+/// Ticker shared by every block that has declarative behavior.
+pub fn behavior_ticker(_: Vec3<i32>, _: &dyn VoxelAccess, _: &Registry) -> u64 {
+    falling::FALL_DELAY_TICKS
+}
+
+/// Updater shared by every block that has declarative behavior. Dispatches to the
+/// behaviors enabled in the block's `BlockProperties`.
+pub fn behavior_updater(
+    voxel: Vec3<i32>,
+    space: &dyn VoxelAccess,
+    registry: &Registry,
+) -> Vec<(Vec3<i32>, u32)> {
+    let Vec3(vx, vy, vz) = voxel;
+    let id = space.get_voxel(vx, vy, vz);
+
+    let properties = match get_block_properties(id) {
+        Some(properties) => properties,
+        None => return vec![],
+    };
+
+    if properties.falls {
+        return falling::settle(&voxel, space, registry);
+    }
+
+    vec![]
+}
@@ -0,0 +1,229 @@
+use voxelize::{BlockRotation, Registry, Vec3, VoxelAccess};
+
+use super::{falling, get_block_properties};
+
+const NEIGHBORS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+/// Which neighbors of a block can hold it up.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupportFace {
+    /// The block underneath, like grass on a grass block.
+    Below,
+    /// The block on top, for things hanging from a ceiling.
+    Above,
+    /// Any of the four horizontal neighbors.
+    Sides,
+    /// The block the voxel was placed against, derived from its rotation.
+    Attached,
+}
+
+/// What happens to a block when it loses all of its support.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unsupported {
+    /// The block is removed. The air left behind checks its own neighbors, so whole chains
+    /// of dependent blocks break one after another.
+    Break,
+    /// The block drops down like a falling block until it lands on something.
+    Fall,
+}
+
+#[derive(Debug, Clone)]
+pub struct Support {
+    pub faces: Vec<SupportFace>,
+    pub unsupported: Unsupported,
+}
+
+impl Support {
+    pub fn new(faces: &[SupportFace]) -> Self {
+        Self {
+            faces: faces.to_vec(),
+            unsupported: Unsupported::Break,
+        }
+    }
+
+    pub fn unsupported(mut self, unsupported: Unsupported) -> Self {
+        self.unsupported = unsupported;
+        self
+    }
+
+    /// The offsets from the dependent voxel to each voxel that can support it.
+    fn offsets(&self, rotation: &BlockRotation) -> Vec<[i32; 3]> {
+        let mut offsets = vec![];
+
+        for face in self.faces.iter() {
+            match face {
+                SupportFace::Below => offsets.push([0, -1, 0]),
+                SupportFace::Above => offsets.push([0, 1, 0]),
+                SupportFace::Sides => {
+                    offsets.extend([[1, 0, 0], [-1, 0, 0], [0, 0, 1], [0, 0, -1]]);
+                }
+                SupportFace::Attached => offsets.push(match rotation {
+                    BlockRotation::PX(_) => [-1, 0, 0],
+                    BlockRotation::NX(_) => [1, 0, 0],
+                    BlockRotation::PY(_) => [0, -1, 0],
+                    BlockRotation::NY(_) => [0, 1, 0],
+                    BlockRotation::PZ(_) => [0, 0, -1],
+                    BlockRotation::NZ(_) => [0, 0, 1],
+                }),
+            }
+        }
+
+        offsets
+    }
+}
+
+fn is_supporting(id: u32, registry: &Registry) -> bool {
+    if registry.is_air(id) {
+        return false;
+    }
+
+    let block = registry.get_block_by_id(id);
+
+    !block.is_fluid && !block.is_passable
+}
+
+/// Called where a voxel has just become air. Returns the updates that break or drop the
+/// neighbors that depended on it and have no other support left.
+pub fn collapse_neighbors(
+    voxel: &Vec3<i32>,
+    space: &dyn VoxelAccess,
+    registry: &Registry,
+) -> Vec<(Vec3<i32>, u32)> {
+    let &Vec3(vx, vy, vz) = voxel;
+    let mut updates = vec![];
+
+    for [dx, dy, dz] in NEIGHBORS {
+        let (nx, ny, nz) = (vx + dx, vy + dy, vz + dz);
+        let id = space.get_voxel(nx, ny, nz);
+
+        let support = match get_block_properties(id).and_then(|p| p.requires_support.as_ref()) {
+            Some(support) => support,
+            None => continue,
+        };
+
+        let offsets = support.offsets(&space.get_voxel_rotation(nx, ny, nz));
+
+        // Only the neighbors that could have been resting on this voxel are affected.
+        if !offsets.contains(&[-dx, -dy, -dz]) {
+            continue;
+        }
+
+        let still_supported = offsets.iter().any(|[ox, oy, oz]| {
+            is_supporting(space.get_voxel(nx + ox, ny + oy, nz + oz), registry)
+        });
+
+        if still_supported {
+            continue;
+        }
+
+        match support.unsupported {
+            Unsupported::Break => updates.push((Vec3(nx, ny, nz), 0)),
+            Unsupported::Fall => {
+                updates.extend(falling::settle(&Vec3(nx, ny, nz), space, registry))
+            }
+        }
+    }
+
+    updates
+}
+
+#[cfg(test)]
+mod tests {
+    use voxelize::{Chunk, ChunkOptions};
+
+    use crate::registry::test_registry;
+
+    use super::*;
+
+    const DIRT: u32 = 1;
+    const STONE: u32 = 2;
+    const GRASS: u32 = 30300;
+    const TORCH: u32 = 40000;
+    const MUSHROOM: u32 = 2000;
+
+    fn chunk() -> Chunk {
+        Chunk::new(
+            "test",
+            0,
+            0,
+            &ChunkOptions {
+                size: 16,
+                max_height: 64,
+                sub_chunks: 4,
+            },
+        )
+    }
+
+    /// Remove a voxel the way a player would, and return what collapses with it.
+    fn remove(chunk: &mut Chunk, vx: i32, vy: i32, vz: i32) -> Vec<(Vec3<i32>, u32)> {
+        chunk.set_voxel(vx, vy, vz, 0);
+        collapse_neighbors(&Vec3(vx, vy, vz), chunk, test_registry())
+    }
+
+    #[test]
+    fn grass_breaks_without_the_block_below() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 9, 4, DIRT);
+        chunk.set_voxel(4, 10, 4, GRASS);
+
+        assert_eq!(remove(&mut chunk, 4, 9, 4), vec![(Vec3(4, 10, 4), 0)]);
+    }
+
+    #[test]
+    fn grass_ignores_its_other_neighbors() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 9, 4, DIRT);
+        chunk.set_voxel(4, 10, 4, GRASS);
+        chunk.set_voxel(5, 10, 4, STONE);
+        chunk.set_voxel(4, 11, 4, STONE);
+
+        assert!(remove(&mut chunk, 5, 10, 4).is_empty());
+        assert!(remove(&mut chunk, 4, 11, 4).is_empty());
+    }
+
+    #[test]
+    fn grass_breaks_one_after_another() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 8, 4, DIRT);
+        chunk.set_voxel(4, 9, 4, GRASS);
+        chunk.set_voxel(4, 10, 4, GRASS);
+
+        // The lower grass breaks, and the air it leaves breaks the one on top of it.
+        assert_eq!(remove(&mut chunk, 4, 8, 4), vec![(Vec3(4, 9, 4), 0)]);
+        assert_eq!(remove(&mut chunk, 4, 9, 4), vec![(Vec3(4, 10, 4), 0)]);
+    }
+
+    #[test]
+    fn torches_fall_instead_of_breaking() {
+        let mut chunk = chunk();
+        chunk.set_voxel(4, 5, 4, STONE);
+        chunk.set_voxel(4, 9, 4, STONE);
+        chunk.set_voxel(4, 10, 4, TORCH);
+
+        let raw = chunk.get_raw_voxel(4, 10, 4);
+
+        assert_eq!(
+            remove(&mut chunk, 4, 9, 4),
+            vec![(Vec3(4, 10, 4), 0), (Vec3(4, 6, 4), raw)]
+        );
+    }
+
+    #[test]
+    fn mushrooms_hang_on_the_block_they_were_placed_against() {
+        let mut chunk = chunk();
+        chunk.set_voxel(3, 10, 4, STONE);
+        chunk.set_voxel(4, 9, 4, STONE);
+        chunk.set_voxel(4, 10, 4, MUSHROOM);
+        chunk.set_voxel_rotation(4, 10, 4, &BlockRotation::PX(0.0));
+
+        assert!(remove(&mut chunk, 4, 9, 4).is_empty());
+        assert_eq!(remove(&mut chunk, 3, 10, 4), vec![(Vec3(4, 10, 4), 0)]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 pub fn get_preload_radius() -> usize {
     if std::env::var("CARGO_ENV").unwrap_or_default() == "production" {
         6
@@ -5,3 +7,62 @@ pub fn get_preload_radius() -> usize {
         2
     }
 }
+
+/// The key admins sign in with, the same `SECRET_ADMIN_KEY` the client is built with. Only
+/// clients that send it get the owner role, so without it no one does.
+pub fn get_admin_key() -> Option<String> {
+    std::env::var("SECRET_ADMIN_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+/// The timezone that server-side display blocks show their time in.
+pub fn get_timezone() -> chrono_tz::Tz {
+    std::env::var("TIMEZONE")
+        .ok()
+        .and_then(|timezone| timezone.parse().ok())
+        .unwrap_or(chrono_tz::UTC)
+}
+
+/// Where the GitHub contribution calendar for the main world's wall is read from. Set
+/// `CONTRIBUTIONS_PATH` to an empty string to leave the wall alone.
+pub fn get_contributions_path() -> Option<PathBuf> {
+    let path = std::env::var("CONTRIBUTIONS_PATH")
+        .unwrap_or_else(|_| "data/contributions.json".to_owned());
+
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Where the projects shown in the main world's trophy showcase are listed.
+pub fn get_projects_path() -> PathBuf {
+    PathBuf::from(
+        std::env::var("PROJECTS_PATH").unwrap_or_else(|_| "data/projects.json".to_owned()),
+    )
+}
+
+/// The superflat preset the flat world is generated from, see `GridLandStage::from_preset`.
+pub fn get_flat_preset() -> String {
+    std::env::var("FLAT_PRESET").unwrap_or_else(|_| "10*Stone;grid=10:Dirt".to_owned())
+}
+
+/// The folder structures saved with the `save-structure` method are kept in, shared by
+/// every world.
+pub fn get_structures_path() -> PathBuf {
+    PathBuf::from(std::env::var("STRUCTURES_PATH").unwrap_or_else(|_| "data/structures".to_owned()))
+}
+
+/// The folder `.vox` files are imported from, along with the `colors.json` table their
+/// palettes are matched against and the `placements.json` the main world generates.
+pub fn get_vox_path() -> PathBuf {
+    PathBuf::from(std::env::var("VOX_PATH").unwrap_or_else(|_| "data/vox".to_owned()))
+}
+
+/// The folder of template buildings the terrain world scatters, listed in its
+/// `templates.json`.
+pub fn get_templates_path() -> PathBuf {
+    PathBuf::from(std::env::var("TEMPLATES_PATH").unwrap_or_else(|_| "data/templates".to_owned()))
+}
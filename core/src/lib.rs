@@ -0,0 +1,6 @@
+pub mod blocks;
+pub mod constants;
+pub mod projects;
+pub mod registry;
+pub mod session;
+pub mod worlds;
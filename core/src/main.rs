@@ -1,9 +1,5 @@
-mod constants;
-mod registry;
-mod worlds;
-
-use registry::get_registry;
-use voxelize::{Info, Server, WsSession};
+use voxelize::{Info, Server};
+use worlds_core::{constants::get_admin_key, registry::get_registry, session::EditSession, worlds};
 
 use actix::{Actor, Addr};
 use actix_cors::Cors;
@@ -58,9 +54,8 @@ async fn ws_route(
     }
 
     ws::start(
-        WsSession {
+        EditSession {
             id,
-            name: None,
             is_transport,
             addr: srv.get_ref().clone(),
         },
@@ -88,6 +83,10 @@ async fn info(server: web::Data<Addr<Server>>) -> Result<HttpResponse> {
 async fn main() -> std::io::Result<()> {
     let registry = get_registry();
 
+    if get_admin_key().is_none() {
+        warn!("SECRET_ADMIN_KEY isn't set, so no one can use the admin methods.");
+    }
+
     let mut server = Server::new()
         .port(4000)
         .secret("test")
@@ -104,6 +103,9 @@ async fn main() -> std::io::Result<()> {
     server
         .add_world(worlds::setup_terrain_world(&registry))
         .expect("Failed to add the terrain world");
+    server
+        .add_world(worlds::setup_scratch_world(&registry))
+        .expect("Failed to add the scratch world");
 
     server.prepare();
     server.started = true;
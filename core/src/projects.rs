@@ -0,0 +1,106 @@
+use std::{fs::File, path::Path};
+
+use hashbrown::HashSet;
+use log::warn;
+use serde::Deserialize;
+use voxelize::Vec3;
+
+/// The trophy blocks, registered as "Trophy ({name})" under the same ids on every run so
+/// saved chunks keep their trophies whatever the manifest says. A new project needs its
+/// trophy added here, and textured in the client's registry.
+pub const TROPHIES: [(u32, &str); 7] = [
+    (2500, "mc.js"),
+    (2501, "modern-graphql-tutorial"),
+    (2502, "mine.js"),
+    (2503, "voxelize"),
+    (2504, "mc.js-legacy"),
+    (2505, "rust-typescript-template"),
+    (2506, "lunar-lander-ai"),
+];
+
+pub fn trophy_name(name: &str) -> String {
+    format!("Trophy ({})", name)
+}
+
+/// A project shown in the main world's showcase, with the trophy block made for it.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Project {
+    pub name: String,
+    /// Id of the project's trophy block, one of `TROPHIES`.
+    pub trophy: u32,
+    pub description: String,
+    pub link: String,
+    #[serde(default)]
+    pub stars: u32,
+}
+
+impl Project {
+    /// The floating text shown above the project's trophy.
+    pub fn caption(&self) -> String {
+        format!("{} ★ {}\n{}", self.name, self.stars, self.description)
+    }
+}
+
+fn default_anchor() -> Vec3<i32> {
+    Vec3(-10, 41, 64)
+}
+
+fn default_spacing() -> i32 {
+    3
+}
+
+/// The projects manifest, `data/projects.json` by default. The showcase row starts at
+/// `anchor` and runs towards positive x, one project every `spacing` voxels.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProjectManifest {
+    #[serde(default = "default_anchor")]
+    pub anchor: Vec3<i32>,
+    #[serde(default = "default_spacing")]
+    pub spacing: i32,
+    pub projects: Vec<Project>,
+}
+
+impl ProjectManifest {
+    pub fn load(path: &Path) -> Option<Self> {
+        let manifest = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::from_reader::<_, Self>(file).map_err(|e| e.to_string()));
+
+        match manifest {
+            Ok(manifest) => Some(manifest.validated()),
+            Err(e) => {
+                warn!("Failed to load projects from {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Drop the projects whose trophy isn't one of `TROPHIES`, or is already taken by an
+    /// earlier project, so no two projects share a trophy block.
+    pub fn validated(mut self) -> Self {
+        let mut taken = HashSet::new();
+
+        self.projects.retain(|project| {
+            if !TROPHIES.iter().any(|(id, _)| *id == project.trophy) {
+                warn!(
+                    "Project {} uses trophy {}, which isn't a trophy block. Skipping it.",
+                    project.name, project.trophy
+                );
+                return false;
+            }
+
+            if !taken.insert(project.trophy) {
+                warn!(
+                    "Project {} uses trophy {}, which an earlier project already uses. \
+                     Skipping it.",
+                    project.name, project.trophy
+                );
+                return false;
+            }
+
+            true
+        });
+
+        self
+    }
+}
@@ -1,9 +1,23 @@
+#[cfg(test)]
+use std::sync::OnceLock;
+
+use hashbrown::HashMap;
 use voxelize::{
     Block, BlockConditionalPart, BlockDynamicPattern, BlockFaces, BlockRotation, BlockRule,
     BlockRuleLogic, BlockSimpleRule, Registry, Vec3, VoxelPacker, AABB, SIX_FACES_PX, SIX_FACES_PY,
     SIX_FACES_PZ,
 };
 
+use crate::{
+    blocks::{
+        behavior_ticker, behavior_updater, collapse_neighbors, grass_random_tick,
+        mushroom_random_tick, set_block_properties, BlockProperties, Emission, Support,
+        SupportFace, Unsupported, BLUE_LIGHT, GOLD_LIGHT, PALE_LIGHT, RED_LIGHT, WARM_LIGHT,
+        WHITE_LIGHT, YELLOW_LIGHT,
+    },
+    projects::{trophy_name, TROPHIES},
+};
+
 const PLANT_SCALE: f32 = 0.6;
 
 pub fn get_registry() -> Registry {
@@ -51,7 +65,7 @@ pub fn get_registry() -> Registry {
             .aabbs(&[torch_aabbs])
             .is_transparent(true)
             .is_passable(true)
-            .torch_light_level(10)
+            .emission(WARM_LIGHT)
             .build(),
     );
 
@@ -342,7 +356,74 @@ pub fn get_registry() -> Registry {
         .rotatable(true)
         .y_rotatable(true)
         .build();
-    registry.register_blocks(&[year_percentage_block, current_time_block]);
+    let visitor_counter_block = Block::new("Visitor Counter")
+        .id(50004)
+        .is_transparent(true)
+        .faces(&small_display_block_faces)
+        .aabbs(&[AABB::from_faces(&small_display_block_faces)])
+        .rotatable(true)
+        .y_rotatable(true)
+        .build();
+    let uptime_block = Block::new("Uptime")
+        .id(50005)
+        .is_transparent(true)
+        .faces(&small_display_block_faces)
+        .aabbs(&[AABB::from_faces(&small_display_block_faces)])
+        .rotatable(true)
+        .y_rotatable(true)
+        .build();
+    registry.register_blocks(&[
+        year_percentage_block,
+        current_time_block,
+        visitor_counter_block,
+        uptime_block,
+    ]);
+
+    let sign_board_faces = BlockFaces::six_faces()
+        .scale_y(0.5)
+        .scale_z(0.08)
+        .offset_y(0.3)
+        .offset_z(0.92)
+        .auto_uv_offset(true)
+        .prefix("board")
+        .build();
+    let wall_sign_block = Block::new("Wall Sign")
+        .id(50006)
+        .is_transparent(true)
+        .faces(&sign_board_faces)
+        .aabbs(&[AABB::from_faces(&sign_board_faces)])
+        .rotatable(true)
+        .y_rotatable(true)
+        .build();
+
+    let standing_sign_faces = BlockFaces::six_faces()
+        .scale_y(0.4)
+        .scale_z(0.08)
+        .offset_y(0.6)
+        .offset_z(0.46)
+        .auto_uv_offset(true)
+        .prefix("board")
+        .build()
+        .join(
+            BlockFaces::six_faces()
+                .scale_x(0.1)
+                .scale_z(0.1)
+                .scale_y(0.6)
+                .offset_x(0.45)
+                .offset_z(0.45)
+                .auto_uv_offset(true)
+                .prefix("post")
+                .build(),
+        );
+    let standing_sign_block = Block::new("Standing Sign")
+        .id(50007)
+        .is_transparent(true)
+        .faces(&standing_sign_faces)
+        .aabbs(&[AABB::from_faces(&standing_sign_faces)])
+        .rotatable(true)
+        .y_rotatable(true)
+        .build();
+    registry.register_blocks(&[wall_sign_block, standing_sign_block]);
 
     let make_top_slab = |name: &str, id: u32| {
         Block::new(name)
@@ -424,11 +505,7 @@ pub fn get_registry() -> Registry {
                 }
             });
 
-            let voxel_above = space.get_voxel(vx, vy + 1, vz);
-
-            if voxel_above == 1000 || voxel_above == 400 {
-                updates.push((Vec3(vx, vy + 1, vz), 0));
-            }
+            updates.extend(collapse_neighbors(&voxel, space, registry));
 
             updates
         },
@@ -438,7 +515,10 @@ pub fn get_registry() -> Registry {
         Block::new("Dirt").id(1).build(),
         Block::new("Stone").id(2).build(),
         // Stones
-        Block::new("Sand").id(50).build(),
+        Block::new("Sand")
+            .id(50)
+            .active_fn(behavior_ticker, behavior_updater)
+            .build(),
         Block::new("Chalk").id(51).build(),
         Block::new("Quartzite").id(52).build(),
         Block::new("Limestone").id(53).build(),
@@ -453,6 +533,10 @@ pub fn get_registry() -> Registry {
         Block::new("Granite").id(62).build(),
         Block::new("Graphite").id(63).build(),
         Block::new("Marble").id(64).build(),
+        Block::new("Gravel")
+            .id(65)
+            .active_fn(behavior_ticker, behavior_updater)
+            .build(),
         // Rare Rocks
         Block::new("Blue Lace Agate").id(200).build(),
         Block::new("Onyx Agate").id(201).build(),
@@ -662,13 +746,16 @@ pub fn get_registry() -> Registry {
         Block::new("Malachite").id(308).build(),
         Block::new("Pyrite").id(309).build(),
         Block::new("Flint").id(310).build(),
-        Block::new("Moonstone").id(311).torch_light_level(8).build(),
+        Block::new("Moonstone").id(311).emission(PALE_LIGHT).build(),
         Block::new("Aquamarine").id(312).build(),
-        Block::new("Sunstone").id(313).torch_light_level(15).build(),
+        Block::new("Sunstone")
+            .id(313)
+            .emission(YELLOW_LIGHT)
+            .build(),
         Block::new("Opal").id(314).build(),
         Block::new("Bloodstone").id(315).build(),
         Block::new("Rose Quartz").id(316).build(),
-        Block::new("Iolite").id(317).build(),
+        Block::new("Iolite").id(317).emission(BLUE_LIGHT).build(),
         Block::new("Hematite").id(318).build(),
         Block::new("Azurite").id(319).build(),
     ]);
@@ -778,7 +865,7 @@ pub fn get_registry() -> Registry {
 
     let link_block_aabbs = [AABB::from_faces(&link_block)];
 
-    let make_link_block = |name: &str, id: u32| {
+    let make_link_block = |name: &str, id: u32, color: [u32; 3]| {
         Block::new(name)
             .id(id)
             .rotatable(true)
@@ -787,16 +874,16 @@ pub fn get_registry() -> Registry {
             .is_x_transparent(false)
             .faces(&link_block)
             .aabbs(&link_block_aabbs)
-            .torch_light_level(15)
+            .emission(color)
             .build()
     };
 
     registry.register_blocks(&[
-        make_link_block("Youtube", 1500),
-        make_link_block("Twitter", 1501),
-        make_link_block("LinkedIn", 1502),
-        make_link_block("Github", 1503),
-        make_link_block("Mail", 1504),
+        make_link_block("Youtube", 1500, RED_LIGHT),
+        make_link_block("Twitter", 1501, BLUE_LIGHT),
+        make_link_block("LinkedIn", 1502, BLUE_LIGHT),
+        make_link_block("Github", 1503, WHITE_LIGHT),
+        make_link_block("Mail", 1504, WHITE_LIGHT),
         Block::new("BuyMeACoffee")
             .id(1505)
             .rotatable(true)
@@ -805,7 +892,7 @@ pub fn get_registry() -> Registry {
             .is_x_transparent(false)
             .faces(&link_block.independent_at(2 + 6))
             .aabbs(&link_block_aabbs)
-            .torch_light_level(15)
+            .emission(YELLOW_LIGHT)
             .build(),
     ]);
 
@@ -931,64 +1018,22 @@ pub fn get_registry() -> Registry {
 
     let trophy_aabbs = [AABB::from_faces(&trophy)];
 
-    registry.register_blocks(&[
-        Block::new("Trophy (mc.js)")
-            .id(2500)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-        Block::new("Trophy (modern-graphql-tutorial)")
-            .id(2501)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-        Block::new("Trophy (mine.js)")
-            .id(2502)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-        Block::new("Trophy (voxelize)")
-            .id(2503)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-        Block::new("Trophy (mc.js-legacy)")
-            .id(2504)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-        Block::new("Trophy (rust-typescript-template)")
-            .id(2505)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-        Block::new("Trophy (lunar-lander-ai)")
-            .id(2506)
-            .faces(&trophy)
-            .aabbs(&trophy_aabbs)
-            .rotatable(true)
-            .y_rotatable(true)
-            .torch_light_level(15)
-            .build(),
-    ]);
+    // Every project gets its own trophy, textured by the client.
+    registry.register_blocks(
+        &TROPHIES
+            .iter()
+            .map(|(id, name)| {
+                Block::new(&trophy_name(name))
+                    .id(*id)
+                    .faces(&trophy)
+                    .aabbs(&trophy_aabbs)
+                    .rotatable(true)
+                    .y_rotatable(true)
+                    .emission(GOLD_LIGHT)
+                    .build()
+            })
+            .collect::<Vec<_>>(),
+    );
 
     registry.register_block(
         &Block::new("Trophy")
@@ -998,30 +1043,31 @@ pub fn get_registry() -> Registry {
             .rotatable(true)
             .y_rotatable(true)
             .is_transparent(true)
-            .torch_light_level(15)
+            .emission(GOLD_LIGHT)
             .build(),
     );
 
+    // Glow in the same greens as the contribution calendar on Github.
     registry.register_blocks(&[
         Block::new("Github Contribution L0")
             .id(3000)
-            .torch_light_level(15)
+            .emission([6, 6, 6])
             .build(),
         Block::new("Github Contribution L1")
             .id(3001)
-            .torch_light_level(15)
+            .emission([4, 8, 5])
             .build(),
         Block::new("Github Contribution L2")
             .id(3002)
-            .torch_light_level(15)
+            .emission([4, 10, 5])
             .build(),
         Block::new("Github Contribution L3")
             .id(3003)
-            .torch_light_level(15)
+            .emission([5, 13, 6])
             .build(),
         Block::new("Github Contribution L4")
             .id(3004)
-            .torch_light_level(15)
+            .emission([6, 15, 7])
             .build(),
     ]);
 
@@ -1060,5 +1106,61 @@ pub fn get_registry() -> Registry {
         Block::new("Ivory").id(5012).build(),
     ]);
 
+    let mut properties = HashMap::new();
+
+    properties.insert(50, BlockProperties::new().falls(true).build());
+    properties.insert(65, BlockProperties::new().falls(true).build());
+
+    // Plants break once the block they grow on is gone, torches drop down instead.
+    properties.insert(
+        30300,
+        BlockProperties::new()
+            .requires_support(Support::new(&[SupportFace::Below]))
+            .build(),
+    );
+    properties.insert(
+        40000,
+        BlockProperties::new()
+            .requires_support(Support::new(&[SupportFace::Below]).unsupported(Unsupported::Fall))
+            .build(),
+    );
+    properties.insert(
+        2000,
+        BlockProperties::new()
+            .requires_support(Support::new(&[SupportFace::Attached]))
+            .random_tick(mushroom_random_tick)
+            .build(),
+    );
+    // Colored glass and leaves let some colors of torch light through more than others.
+    properties.insert(5000, BlockProperties::new().tint([0.8, 1.0, 1.0]).build());
+    properties.insert(5004, BlockProperties::new().tint([0.6, 1.0, 0.5]).build());
+    properties.insert(
+        50006,
+        BlockProperties::new()
+            .requires_support(Support::new(&[SupportFace::Attached]))
+            .build(),
+    );
+    properties.insert(
+        50007,
+        BlockProperties::new()
+            .requires_support(Support::new(&[SupportFace::Below]))
+            .build(),
+    );
+    properties.insert(
+        30001,
+        BlockProperties::new()
+            .random_tick(grass_random_tick)
+            .build(),
+    );
+
+    set_block_properties(properties);
+
     registry
 }
+
+/// The registry for tests. Block properties can only be set once, so every test shares it.
+#[cfg(test)]
+pub fn test_registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(get_registry)
+}
@@ -0,0 +1,133 @@
+use actix::prelude::*;
+use actix_web_actors::ws;
+use log::warn;
+use voxelize::{
+    decode_message, encode_message, ClientMessage, Connect, Disconnect, EncodedMessage, Message,
+    MessageType, Server,
+};
+
+/// Voxelize's websocket session, except that clients can't set voxels by sending `Update`
+/// messages. Voxelize applies those straight to the world without asking anyone, so every
+/// edit has to go through the `edit-voxels` method instead, where plots and regions are
+/// checked against the client that sent it. Transport servers are trusted as before.
+pub struct EditSession {
+    pub id: String,
+    pub is_transport: bool,
+    pub addr: Addr<Server>,
+}
+
+impl Actor for EditSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let addr = ctx.address();
+        self.addr
+            .send(Connect {
+                id: if self.id.is_empty() {
+                    None
+                } else {
+                    Some(self.id.to_owned())
+                },
+                is_transport: self.is_transport,
+                addr: addr.recipient(),
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(res) => act.id = res,
+                    _ => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.addr.do_send(Disconnect {
+            id: self.id.to_owned(),
+        });
+        Running::Stop
+    }
+}
+
+impl Handler<EncodedMessage> for EditSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: EncodedMessage, ctx: &mut Self::Context) {
+        ctx.binary(msg.0);
+    }
+}
+
+impl Handler<Disconnect> for EditSession {
+    type Result = ();
+
+    fn handle(&mut self, _: Disconnect, ctx: &mut Self::Context) {
+        ctx.terminate();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for EditSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let msg = match msg {
+            Err(_) => {
+                ctx.stop();
+                return;
+            }
+            Ok(msg) => msg,
+        };
+
+        match msg {
+            ws::Message::Binary(bytes) => {
+                let message = match decode_message(&bytes) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!(
+                            "Client {} sent a message that couldn't be read: {}",
+                            self.id, e
+                        );
+                        return;
+                    }
+                };
+
+                if !self.is_transport && message.r#type == MessageType::Update as i32 {
+                    warn!(
+                        "Client {} sent voxel updates directly, which are ignored. Edits go \
+                         through the edit-voxels method.",
+                        self.id
+                    );
+                    return;
+                }
+
+                self.addr
+                    .send(ClientMessage {
+                        id: self.id.to_owned(),
+                        data: message,
+                    })
+                    .into_actor(self)
+                    .then(|res, _, ctx| {
+                        match res {
+                            Ok(Some(error)) => {
+                                warn!("Error: {}", error);
+                                ctx.binary(encode_message(
+                                    &Message::new(&MessageType::Error).text(&error).build(),
+                                ));
+                                ctx.stop();
+                            }
+                            Ok(None) => {}
+                            _ => ctx.stop(),
+                        }
+                        fut::ready(())
+                    })
+                    .wait(ctx);
+            }
+            ws::Message::Close(reason) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            ws::Message::Continuation(_) => {
+                ctx.stop();
+            }
+            _ => (),
+        }
+    }
+}
@@ -1,14 +1,27 @@
+mod plots;
+mod scratch;
 mod stage;
 
 use voxelize::{FlatlandStage, Registry, World, WorldConfig};
 
-use crate::constants::get_preload_radius;
+use crate::constants::{get_flat_preset, get_preload_radius};
 
-use self::stage::GridLandStage;
+pub use self::scratch::setup_scratch_world;
+
+use self::{
+    plots::setup_plots,
+    stage::{FlatLayout, GridLandStage},
+};
 
 use super::shared::{
-    client::setup_client, components::setup_components, entities::setup_entities,
-    methods::setup_methods, systems::setup_dispatcher,
+    block_entities::setup_block_entities,
+    client::setup_client,
+    components::setup_components,
+    entities::setup_entities,
+    history::setup_history,
+    methods::setup_methods,
+    regions::setup_regions,
+    systems::{setup_dispatcher, RandomTickConfig},
 };
 
 pub fn setup_flat_world(registry: &Registry) -> World {
@@ -25,15 +38,27 @@ pub fn setup_flat_world(registry: &Registry) -> World {
 
     let mut world = World::new("flat", &config);
 
+    let stage = GridLandStage::from_preset(&get_flat_preset(), registry, config.max_height)
+        .unwrap_or_else(|e| panic!("Invalid flat world preset: {}", e));
+
     setup_components(&mut world);
     setup_entities(&mut world);
     setup_dispatcher(&mut world);
     setup_methods(&mut world);
     setup_client(&mut world);
+    setup_block_entities(&mut world);
+
+    world.ecs_mut().insert(FlatLayout(stage.clone()));
+    setup_plots(&mut world);
+    setup_regions(&mut world, vec![]);
+    setup_history(&mut world);
+
+    // Plots are built on by players, so nothing should grow or decay under them.
+    world.ecs_mut().insert(RandomTickConfig::new(0));
 
     {
         let mut pipeline = world.pipeline_mut();
-        pipeline.add_stage(GridLandStage::new().add_soiling(2, 10).set_grid(10, 1))
+        pipeline.add_stage(stage)
     }
 
     world
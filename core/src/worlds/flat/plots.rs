@@ -0,0 +1,332 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use voxelize::{ClientFilter, Event, Vec2, Vec3, VoxelAccess, World};
+
+use super::{
+    super::shared::{
+        client::{client_name, client_player, find_player, is_owner},
+        edits::apply_client_edits,
+        plots::{Plot, Plots, MAX_PLOTS_PER_PLAYER},
+    },
+    stage::FlatLayout,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PlotAtPayload {
+    voxel: Vec3<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TrustPlotPayload {
+    voxel: Vec3<i32>,
+    /// The name of a player online or their client id, or the id of a trusted player to
+    /// stop trusting.
+    player: String,
+    trusted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TransferPlotPayload {
+    cell: Vec2<i32>,
+    /// The name of a player online or their client id, or the id of any player.
+    owner: String,
+    owner_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ResetPlotPayload {
+    cell: Vec2<i32>,
+}
+
+/// Sent back to the client after any plot method, with the plot it changed.
+#[derive(Serialize, Debug)]
+struct PlotReply {
+    plot: Option<Plot>,
+    error: Option<String>,
+}
+
+fn reply(world: &mut World, client_id: &str, reply: PlotReply) {
+    world.events_mut().dispatch(
+        Event::new("plot")
+            .payload(reply)
+            .filter(ClientFilter::Direct(client_id.to_owned()))
+            .build(),
+    );
+}
+
+fn reply_error(world: &mut World, client_id: &str, error: &str) {
+    reply(
+        world,
+        client_id,
+        PlotReply {
+            plot: None,
+            error: Some(error.to_owned()),
+        },
+    );
+}
+
+fn deny(world: &mut World, client_id: &str, action: &str) -> bool {
+    if is_owner(world, client_id) {
+        return false;
+    }
+
+    warn!(
+        "Client {} tried to {} without permission.",
+        client_id, action
+    );
+    reply_error(world, client_id, "Only admins can do that.");
+
+    true
+}
+
+/// Put every voxel of a cell back to how the flat world generated it, on behalf of the
+/// admin who asked for it.
+fn reset_cell(world: &mut World, client_id: &str, cell: &Vec2<i32>) {
+    let (Vec2(min_x, min_z), Vec2(max_x, max_z)) = world.read_resource::<Plots>().cell_bounds(cell);
+    let max_height = world.config().max_height as i32;
+
+    let mut updates = vec![];
+
+    {
+        let layout = world.read_resource::<FlatLayout>();
+        let chunks = world.chunks();
+
+        for vx in min_x..max_x {
+            for vz in min_z..max_z {
+                for vy in 0..max_height {
+                    let id = layout.0.block_at(vx, vy, vz).unwrap_or(0);
+
+                    if chunks.get_raw_voxel(vx, vy, vz) != id {
+                        updates.push((Vec3(vx, vy, vz), id));
+                    }
+                }
+            }
+        }
+    }
+
+    apply_client_edits(world, client_id, updates);
+}
+
+/// Who can't keep a plot, because their client never said which player they are.
+const UNKNOWN_PLAYER: &str = "Can't tell who you are, try reloading the page.";
+
+/// Turns the cells of the flat world's grid into plots players can claim and build on.
+/// Plots belong to players rather than their connections, so they're kept across
+/// reconnects.
+pub fn setup_plots(world: &mut World) {
+    let (path, grid_size) = {
+        let config = world.config();
+
+        let path = if config.saving {
+            Some(PathBuf::from(&config.save_dir).join("plots.json"))
+        } else {
+            None
+        };
+
+        (
+            path,
+            world.read_resource::<FlatLayout>().0.grid_size() as i32,
+        )
+    };
+
+    let mut plots = Plots::new(path, grid_size);
+    plots.load();
+
+    world.ecs_mut().insert(plots);
+
+    world.set_method_handle("claim-plot", |world, client_id, payload| {
+        let data: PlotAtPayload = serde_json::from_str(&payload).unwrap();
+        let owner_name = client_name(world, client_id).unwrap_or_default();
+
+        let player = match client_player(world, client_id) {
+            Some(player) => player,
+            None => return reply_error(world, client_id, UNKNOWN_PLAYER),
+        };
+
+        let result = {
+            let mut plots = world.write_resource::<Plots>();
+
+            match plots.cell_of(&data.voxel) {
+                None => Err("That's not inside a plot."),
+                Some(cell) if plots.plots.contains_key(&cell) => {
+                    Err("That plot is already claimed.")
+                }
+                Some(_) if plots.owned_by(&player) >= MAX_PLOTS_PER_PLAYER => {
+                    Err("You can't claim any more plots.")
+                }
+                Some(cell) => {
+                    let plot = Plot {
+                        cell: cell.to_owned(),
+                        owner: player,
+                        owner_name,
+                        trusted: vec![],
+                        claimed_at: Utc::now().timestamp(),
+                    };
+
+                    plots.plots.insert(cell, plot.to_owned());
+                    plots.save();
+
+                    Ok(plot)
+                }
+            }
+        };
+
+        match result {
+            Ok(plot) => reply(
+                world,
+                client_id,
+                PlotReply {
+                    plot: Some(plot),
+                    error: None,
+                },
+            ),
+            Err(error) => reply_error(world, client_id, error),
+        }
+    });
+
+    world.set_method_handle("trust-plot", |world, client_id, payload| {
+        let data: TrustPlotPayload = serde_json::from_str(&payload).unwrap();
+        let is_admin = is_owner(world, client_id);
+        let player = client_player(world, client_id);
+        let target = find_player(world, &data.player);
+
+        let result = {
+            let mut plots = world.write_resource::<Plots>();
+
+            let cell = plots.cell_of(&data.voxel);
+
+            let result = match cell.and_then(|cell| plots.plots.get_mut(&cell)) {
+                None => Err("That plot isn't claimed."),
+                Some(plot) if player.as_ref() != Some(&plot.owner) && !is_admin => {
+                    Err("Only the owner of a plot can change who builds on it.")
+                }
+                Some(plot) => match target {
+                    Some(target) => {
+                        plot.trusted.retain(|id| *id != target);
+
+                        if data.trusted {
+                            plot.trusted.push(target);
+                        }
+
+                        Ok(plot.to_owned())
+                    }
+                    // Players that went offline can still be let go by their id.
+                    None if !data.trusted && plot.trusted.contains(&data.player) => {
+                        plot.trusted.retain(|id| *id != data.player);
+                        Ok(plot.to_owned())
+                    }
+                    None => Err("There's no player online by that name."),
+                },
+            };
+
+            if result.is_ok() {
+                plots.save();
+            }
+
+            result
+        };
+
+        match result {
+            Ok(plot) => reply(
+                world,
+                client_id,
+                PlotReply {
+                    plot: Some(plot),
+                    error: None,
+                },
+            ),
+            Err(error) => reply_error(world, client_id, error),
+        }
+    });
+
+    world.set_method_handle("list-plots", |world, client_id, _| {
+        if deny(world, client_id, "list plots") {
+            return;
+        }
+
+        let plots = world
+            .read_resource::<Plots>()
+            .plots
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        world.events_mut().dispatch(
+            Event::new("plots")
+                .payload(plots)
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    world.set_method_handle("transfer-plot", |world, client_id, payload| {
+        if deny(world, client_id, "transfer a plot") {
+            return;
+        }
+
+        let data: TransferPlotPayload = serde_json::from_str(&payload).unwrap();
+        let owner_name = data
+            .owner_name
+            .or_else(|| client_name(world, &data.owner))
+            .unwrap_or_default();
+
+        // Plots can be handed to players who aren't online by their id.
+        let owner = find_player(world, &data.owner).unwrap_or(data.owner);
+
+        let plot = {
+            let mut plots = world.write_resource::<Plots>();
+
+            let plot = Plot {
+                cell: data.cell.to_owned(),
+                owner,
+                owner_name,
+                trusted: vec![],
+                claimed_at: Utc::now().timestamp(),
+            };
+
+            plots.plots.insert(data.cell, plot.to_owned());
+            plots.save();
+
+            plot
+        };
+
+        reply(
+            world,
+            client_id,
+            PlotReply {
+                plot: Some(plot),
+                error: None,
+            },
+        );
+    });
+
+    world.set_method_handle("reset-plot", |world, client_id, payload| {
+        if deny(world, client_id, "reset a plot") {
+            return;
+        }
+
+        let data: ResetPlotPayload = serde_json::from_str(&payload).unwrap();
+
+        {
+            let mut plots = world.write_resource::<Plots>();
+
+            plots.plots.remove(&data.cell);
+            plots.save();
+        }
+
+        reset_cell(world, client_id, &data.cell);
+
+        reply(
+            world,
+            client_id,
+            PlotReply {
+                plot: None,
+                error: None,
+            },
+        );
+    });
+}
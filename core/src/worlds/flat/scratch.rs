@@ -0,0 +1,132 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use voxelize::{ClientFilter, Event, Registry, Vec3, VoxelAccess, World, WorldConfig};
+
+use crate::constants::get_flat_preset;
+
+use super::{
+    super::shared::{
+        block_entities::setup_block_entities,
+        client::{is_owner, setup_client},
+        components::setup_components,
+        edits::apply_edits,
+        entities::setup_entities,
+        history::setup_history,
+        methods::setup_methods,
+        regions::setup_regions,
+        systems::{setup_dispatcher, RandomTickConfig},
+    },
+    stage::{FlatLayout, GridLandStage},
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PreviewPresetPayload {
+    preset: String,
+}
+
+#[derive(Serialize, Debug)]
+struct PresetPreviewResult {
+    preset: String,
+    error: Option<String>,
+}
+
+/// Rewrite every loaded chunk of the scratch world to the layout of `stage`.
+fn apply_preset(world: &mut World, stage: GridLandStage) {
+    let previous = std::mem::replace(&mut world.write_resource::<FlatLayout>().0, stage.clone());
+
+    let height = previous.top_height().max(stage.top_height()) as i32;
+
+    let mut updates = vec![];
+
+    for chunk in world.chunks().map.values() {
+        let Vec3(min_x, _, min_z) = chunk.min;
+        let Vec3(max_x, _, max_z) = chunk.max;
+
+        for vx in min_x..max_x {
+            for vz in min_z..max_z {
+                for vy in 0..height {
+                    let id = stage.block_at(vx, vy, vz).unwrap_or(0);
+
+                    if chunk.get_voxel(vx, vy, vz) != id {
+                        updates.push((Vec3(vx, vy, vz), id));
+                    }
+                }
+            }
+        }
+    }
+
+    apply_edits(world, updates);
+}
+
+/// A small, unsaved flat world for admins to try out superflat presets on before using
+/// them for the flat world. The whole world is preloaded, so a preview reaches all of it.
+pub fn setup_scratch_world(registry: &Registry) -> World {
+    let config = WorldConfig::new()
+        .preload(true)
+        .preload_radius(2)
+        .min_chunk([-2, -2])
+        .max_chunk([1, 1])
+        .time_per_day(2400)
+        .max_updates_per_tick(1000)
+        .build();
+
+    let mut world = World::new("scratch", &config);
+
+    setup_components(&mut world);
+    setup_entities(&mut world);
+    setup_dispatcher(&mut world);
+    setup_methods(&mut world);
+    setup_client(&mut world);
+    setup_block_entities(&mut world);
+    setup_regions(&mut world, vec![]);
+    setup_history(&mut world);
+
+    world.ecs_mut().insert(RandomTickConfig::new(0));
+
+    let stage = GridLandStage::from_preset(&get_flat_preset(), registry, config.max_height)
+        .unwrap_or_else(|e| panic!("Invalid flat world preset: {}", e));
+
+    world.ecs_mut().insert(FlatLayout(stage.clone()));
+
+    world.set_method_handle("preview-preset", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to preview a preset without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: PreviewPresetPayload = serde_json::from_str(&payload).unwrap();
+        let stage = GridLandStage::from_preset(
+            &data.preset,
+            &world.read_resource::<Registry>(),
+            world.config().max_height,
+        );
+
+        let error = match stage {
+            Ok(stage) => {
+                apply_preset(world, stage);
+                None
+            }
+            Err(e) => Some(e),
+        };
+
+        world.events_mut().dispatch(
+            Event::new("preset-preview")
+                .payload(PresetPreviewResult {
+                    preset: data.preset,
+                    error,
+                })
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    {
+        let mut pipeline = world.pipeline_mut();
+        pipeline.add_stage(stage)
+    }
+
+    world
+}
@@ -1,6 +1,8 @@
-use voxelize::{Chunk, ChunkStage, Resources, Space, Vec3, VoxelAccess};
+use voxelize::{Chunk, ChunkStage, Registry, Resources, Space, Vec3, VoxelAccess};
 
-#[derive(Default)]
+/// A flat world of layers with a grid drawn into it, like the flat world's plots. Can be
+/// described by a preset string, see `GridLandStage::from_preset`.
+#[derive(Default, Clone)]
 pub struct GridLandStage {
     top_height: u32,
     soiling: Vec<u32>,
@@ -8,6 +10,9 @@ pub struct GridLandStage {
     grid_block_id: u32,
 }
 
+/// The layout a flat world was generated with, to put its voxels back as they were.
+pub struct FlatLayout(pub GridLandStage);
+
 impl GridLandStage {
     pub fn new() -> Self {
         Self {
@@ -38,6 +43,119 @@ impl GridLandStage {
     pub fn query_soiling(&self, y: u32) -> Option<u32> {
         self.soiling.get(y as usize).copied()
     }
+
+    pub fn top_height(&self) -> u32 {
+        self.top_height
+    }
+
+    pub fn grid_size(&self) -> u32 {
+        self.grid_size
+    }
+
+    /// Parse a preset such as `"1*10000,9*Stone,1*Grass Block;grid=10:2"`: comma-separated
+    /// layers from the bottom up, each `count*block` or just `block`, then optionally the
+    /// grid's size and block. Blocks are names or ids and have to exist in the registry, and
+    /// the layers have to fit under `max_height`. `grid=none` leaves the grid out.
+    pub fn from_preset(
+        preset: &str,
+        registry: &Registry,
+        max_height: usize,
+    ) -> Result<Self, String> {
+        let mut parts = preset.split(';');
+
+        let layers = parts.next().unwrap_or_default().trim();
+
+        if layers.is_empty() {
+            return Err("a preset needs at least one layer".to_owned());
+        }
+
+        let mut stage = Self::new();
+        let mut total = 0usize;
+
+        for layer in layers.split(',') {
+            let (count, block) = match layer.split_once('*') {
+                Some((count, block)) => {
+                    let count = count
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid layer count in \"{}\"", layer))?;
+                    (count, block)
+                }
+                None => (1, layer),
+            };
+
+            total = total
+                .checked_add(count)
+                .filter(|total| *total <= max_height)
+                .ok_or_else(|| {
+                    format!(
+                        "the layers are taller than the world's max height of {}",
+                        max_height
+                    )
+                })?;
+
+            stage = stage.add_soiling(parse_block(block, registry)?, count);
+        }
+
+        for option in parts {
+            let option = option.trim();
+
+            if option.is_empty() {
+                continue;
+            }
+
+            match option.split_once('=') {
+                Some(("grid", "none")) => stage = stage.set_grid(0, 0),
+                Some(("grid", grid)) => {
+                    let (size, block) = grid
+                        .split_once(':')
+                        .ok_or_else(|| format!("grid should be size:block, got \"{}\"", grid))?;
+                    let size = size
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| format!("invalid grid size \"{}\"", size))?;
+
+                    stage = stage.set_grid(size, parse_block(block, registry)?);
+                }
+                _ => return Err(format!("unknown preset option \"{}\"", option)),
+            }
+        }
+
+        Ok(stage)
+    }
+
+    /// The block this stage generates at a voxel, if any.
+    pub fn block_at(&self, vx: i32, vy: i32, vz: i32) -> Option<u32> {
+        if vy < 0 || vy >= self.top_height as i32 {
+            return None;
+        }
+
+        let grid_size = self.grid_size as i32;
+
+        if grid_size > 0 && (vx % grid_size == 0 || vz % grid_size == 0) {
+            return Some(self.grid_block_id);
+        }
+
+        self.query_soiling(vy as u32)
+    }
+}
+
+fn parse_block(block: &str, registry: &Registry) -> Result<u32, String> {
+    let block = block.trim();
+
+    if let Ok(id) = block.parse::<u32>() {
+        return if registry.blocks_by_id.contains_key(&id) {
+            Ok(id)
+        } else {
+            Err(format!("no block with id {}", id))
+        };
+    }
+
+    registry
+        .blocks_by_name
+        .get(&block.to_lowercase())
+        .map(|block| block.id)
+        .ok_or_else(|| format!("no block named \"{}\"", block))
 }
 
 impl ChunkStage for GridLandStage {
@@ -46,19 +164,14 @@ impl ChunkStage for GridLandStage {
     }
 
     fn process(&self, mut chunk: Chunk, _: Resources, _: Option<Space>) -> Chunk {
-        let grid_size = self.grid_size as i32;
         let Vec3(min_x, _, min_z) = chunk.min;
         let Vec3(max_x, _, max_z) = chunk.max;
 
         for vx in min_x..max_x {
             for vz in min_z..max_z {
-                let is_on_grid_outlines = vx % grid_size == 0 || vz % grid_size == 0;
-
-                for vy in 0..self.top_height {
-                    if is_on_grid_outlines {
-                        chunk.set_voxel(vx, vy as i32, vz, self.grid_block_id);
-                    } else if let Some(soiling) = self.query_soiling(vy) {
-                        chunk.set_voxel(vx, vy as i32, vz, soiling);
+                for vy in 0..self.top_height as i32 {
+                    if let Some(id) = self.block_at(vx, vy, vz) {
+                        chunk.set_voxel(vx, vy, vz, id);
                     }
                 }
             }
@@ -67,3 +180,70 @@ impl ChunkStage for GridLandStage {
         chunk
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::registry::test_registry;
+
+    use super::*;
+
+    const MAX_HEIGHT: usize = 64;
+
+    fn preset(preset: &str) -> Result<GridLandStage, String> {
+        GridLandStage::from_preset(preset, test_registry(), MAX_HEIGHT)
+    }
+
+    #[test]
+    fn layers_stack_from_the_bottom_up() {
+        let stage = preset("2*1,Stone,3 * grass block").unwrap();
+
+        assert_eq!(stage.top_height(), 6);
+        assert_eq!(stage.query_soiling(0), Some(1));
+        assert_eq!(stage.query_soiling(1), Some(1));
+        assert_eq!(stage.query_soiling(2), Some(2));
+        assert_eq!(stage.query_soiling(5), Some(30001));
+        assert_eq!(stage.query_soiling(6), None);
+    }
+
+    #[test]
+    fn the_grid_can_be_set_or_left_out() {
+        let stage = preset("4*Dirt;grid=8:Stone").unwrap();
+
+        assert_eq!(stage.grid_size(), 8);
+        assert_eq!(stage.block_at(16, 3, 3), Some(2));
+        assert_eq!(stage.block_at(3, 3, 3), Some(1));
+        assert_eq!(stage.block_at(3, 4, 3), None);
+
+        let stage = preset("4*Dirt;grid=none").unwrap();
+
+        assert_eq!(stage.grid_size(), 0);
+        assert_eq!(stage.block_at(0, 0, 0), Some(1));
+    }
+
+    #[test]
+    fn the_default_preset_parses() {
+        let stage = preset("10*Stone;grid=10:Dirt").unwrap();
+
+        assert_eq!(stage.top_height(), 10);
+        assert_eq!(stage.grid_size(), 10);
+    }
+
+    #[test]
+    fn bad_presets_are_refused() {
+        assert!(preset("").is_err());
+        assert!(preset("3*Nothing").is_err());
+        assert!(preset("3*4000000").is_err());
+        assert!(preset("x*Stone").is_err());
+        assert!(preset("Stone;grid=10").is_err());
+        assert!(preset("Stone;grid=ten:Dirt").is_err());
+        assert!(preset("Stone;grid=10:Nothing").is_err());
+        assert!(preset("Stone;height=10").is_err());
+    }
+
+    #[test]
+    fn layers_have_to_fit_under_the_max_height() {
+        assert!(preset("64*Stone").is_ok());
+        assert!(preset("60*Stone,5*Dirt").is_err());
+        assert!(preset(&format!("1*Stone,{}*Dirt", usize::MAX)).is_err());
+    }
+}
@@ -1,12 +1,63 @@
-use voxelize::{Registry, World, WorldConfig};
+use voxelize::{Registry, Vec2, Vec3, World, WorldConfig};
 
-use crate::constants::get_preload_radius;
+use crate::{
+    constants::{get_preload_radius, get_projects_path, get_vox_path},
+    projects::ProjectManifest,
+};
 
 use super::shared::{
-    client::setup_client, components::setup_components, entities::setup_entities,
-    methods::setup_methods, stage::LimitedStage, systems::setup_dispatcher,
+    block_data::setup_block_data,
+    block_entities::setup_block_entities,
+    client::setup_client,
+    components::setup_components,
+    contributions::{setup_contribution_wall, ContributionWall},
+    entities::setup_entities,
+    history::setup_history,
+    methods::setup_methods,
+    regions::{setup_regions, Region, RegionBounds, RegionRules},
+    showcase::setup_project_showcase,
+    stage::{IslandShape, LimitedStage},
+    systems::setup_dispatcher,
+    vox::vox_stage,
 };
 
+/// The spawn island, the contribution wall and the project showcase, which only admins
+/// may build on. Used until the world has its own saved regions.
+fn default_regions(world: &World) -> Vec<Region> {
+    let mut regions = vec![Region::new(
+        "spawn",
+        RegionBounds::Chunks {
+            min: Vec2(-2, -2),
+            max: Vec2(1, 1),
+        },
+        RegionRules::admin_only(),
+    )];
+
+    let (min, max) = world.read_resource::<ContributionWall>().bounds();
+
+    regions.push(Region::new(
+        "contributions",
+        RegionBounds::Aabb { min, max },
+        RegionRules::admin_only(),
+    ));
+
+    if let Some(manifest) = ProjectManifest::load(&get_projects_path()) {
+        let Vec3(ax, ay, az) = manifest.anchor;
+        let length = manifest.projects.len().max(1) as i32 * manifest.spacing;
+
+        regions.push(Region::new(
+            "showcase",
+            RegionBounds::Aabb {
+                min: Vec3(ax, ay, az),
+                max: Vec3(ax + length, ay + 2, az),
+            },
+            RegionRules::admin_only(),
+        ));
+    }
+
+    regions
+}
+
 pub fn setup_main_world(registry: &Registry) -> World {
     let config = WorldConfig::new()
         .preload(true)
@@ -26,10 +77,28 @@ pub fn setup_main_world(registry: &Registry) -> World {
     setup_dispatcher(&mut world);
     setup_methods(&mut world);
     setup_client(&mut world);
+    setup_block_entities(&mut world);
+    setup_block_data(&mut world);
+    setup_contribution_wall(&mut world);
+    setup_project_showcase(&mut world);
+
+    let regions = default_regions(&world);
+    setup_regions(&mut world, regions);
+    setup_history(&mut world);
 
     {
         let mut pipeline = world.pipeline_mut();
-        pipeline.add_stage(LimitedStage)
+        pipeline.add_stage(
+            LimitedStage::new(registry, config.seed)
+                .radius(24)
+                .shape(IslandShape::Noisy { roughness: 0.3 })
+                .height(2, 12)
+                .tapered(true)
+                .add_layer(registry.get_block_by_name("Grass Block").id, 1)
+                .add_layer(registry.get_block_by_name("Dirt").id, 3)
+                .filler(registry.get_block_by_name("Stone").id),
+        );
+        pipeline.add_stage(vox_stage(&get_vox_path(), registry));
     }
 
     world
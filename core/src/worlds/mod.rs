@@ -1,8 +1,9 @@
 mod flat;
 mod main;
 mod shared;
-mod terrain;
+pub mod terrain;
 
-pub use flat::setup_flat_world;
+pub use flat::{setup_flat_world, setup_scratch_world};
 pub use main::setup_main_world;
+pub use shared::deltas::{saves_deltas, ChunkDeltas};
 pub use terrain::setup_terrain_world;
@@ -0,0 +1,142 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+use hashbrown::{HashMap, HashSet};
+use serde::Serialize;
+use serde_json::{json, Value};
+use voxelize::{Vec2, Vec3, World};
+
+use crate::constants::get_timezone;
+
+/// Everything a display block can show, captured once per refresh.
+pub struct BlockDataContext {
+    pub now: DateTime<Tz>,
+    pub uptime: u64,
+    pub visitors: usize,
+}
+
+/// Computes the value shown by a display block.
+pub type BlockDataFn = fn(&BlockDataContext) -> Value;
+
+/// The value of a single display voxel, sent to the clients that have its chunk loaded.
+#[derive(Serialize, Clone)]
+pub struct BlockDataPayload {
+    pub voxel: Vec3<i32>,
+    pub id: u32,
+    pub data: Value,
+}
+
+/// Server-side values for display blocks such as "Current Time" and "Year Percentage".
+/// Refreshed every minute by the block data system.
+pub struct BlockData {
+    pub timezone: Tz,
+    pub started_at: Instant,
+    /// Display block id to the function computing what it shows.
+    pub providers: HashMap<u32, BlockDataFn>,
+    /// Every display voxel found in the loaded chunks, with its block id.
+    pub voxels: HashMap<Vec3<i32>, u32>,
+    /// Chunks that have already been searched for display voxels.
+    pub scanned: HashSet<Vec2<i32>>,
+    /// Every player that has joined since the server started, see `client_player`.
+    pub visitors: HashSet<String>,
+    /// The latest computed value of each display block id.
+    pub values: HashMap<u32, Value>,
+    pub last_refresh: Option<i64>,
+}
+
+impl Default for BlockData {
+    fn default() -> Self {
+        Self::new(get_timezone())
+    }
+}
+
+impl BlockData {
+    pub fn new(timezone: Tz) -> Self {
+        Self {
+            timezone,
+            started_at: Instant::now(),
+            providers: HashMap::new(),
+            voxels: HashMap::new(),
+            scanned: HashSet::new(),
+            visitors: HashSet::new(),
+            values: HashMap::new(),
+            last_refresh: None,
+        }
+    }
+
+    pub fn provide(mut self, id: u32, provider: BlockDataFn) -> Self {
+        self.providers.insert(id, provider);
+        self
+    }
+
+    pub fn context(&self) -> BlockDataContext {
+        BlockDataContext {
+            now: Utc::now().with_timezone(&self.timezone),
+            uptime: self.started_at.elapsed().as_secs(),
+            visitors: self.visitors.len(),
+        }
+    }
+
+    /// Recompute the value of every display block id.
+    pub fn refresh(&mut self) {
+        let context = self.context();
+
+        self.values = self
+            .providers
+            .iter()
+            .map(|(id, provider)| (*id, provider(&context)))
+            .collect();
+        self.last_refresh = Some(context.now.timestamp() / 60);
+    }
+
+    pub fn payload(&self, voxel: &Vec3<i32>) -> Option<BlockDataPayload> {
+        let id = *self.voxels.get(voxel)?;
+
+        self.values.get(&id).map(|data| BlockDataPayload {
+            voxel: voxel.to_owned(),
+            id,
+            data: data.to_owned(),
+        })
+    }
+}
+
+pub fn current_time(context: &BlockDataContext) -> Value {
+    json!({
+        "time": context.now.format("%I:%M %p").to_string(),
+        "date": context.now.format("%Y-%m-%d").to_string(),
+        "timezone": context.now.timezone().name(),
+    })
+}
+
+pub fn year_percentage(context: &BlockDataContext) -> Value {
+    let year = context.now.year();
+    let days = NaiveDate::from_ymd_opt(year, 12, 31)
+        .map(|date| date.ordinal())
+        .unwrap_or(365);
+    let elapsed =
+        context.now.ordinal0() as f64 + context.now.num_seconds_from_midnight() as f64 / 86400.0;
+
+    json!({
+        "year": year,
+        "percentage": elapsed / days as f64,
+    })
+}
+
+pub fn visitor_count(context: &BlockDataContext) -> Value {
+    json!({ "count": context.visitors })
+}
+
+pub fn uptime(context: &BlockDataContext) -> Value {
+    json!({ "seconds": context.uptime })
+}
+
+pub fn setup_block_data(world: &mut World) {
+    let block_data = BlockData::default()
+        .provide(50002, year_percentage)
+        .provide(50003, current_time)
+        .provide(50004, visitor_count)
+        .provide(50005, uptime);
+
+    world.ecs_mut().insert(block_data);
+}
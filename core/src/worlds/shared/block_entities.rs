@@ -0,0 +1,208 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use hashbrown::{HashMap, HashSet};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use voxelize::{ChunkUtils, Vec2, Vec3, World};
+
+use super::signs::{sign_data, STANDING_SIGN_ID, WALL_SIGN_ID};
+
+/// Arbitrary data attached to a single voxel, such as the image of a painting or the
+/// target of a link block.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockEntity {
+    pub voxel: Vec3<i32>,
+    pub id: u32,
+    pub data: Value,
+}
+
+/// Sent to clients whenever a block entity is created, edited or removed.
+#[derive(Serialize, Debug, Clone)]
+pub struct BlockEntityPayload {
+    pub voxel: Vec3<i32>,
+    pub entity: Option<BlockEntity>,
+}
+
+/// Every block entity of a world, keyed by voxel position. Saved per chunk under
+/// `{save_dir}/block-entities/{cx}|{cz}.json`, next to the chunks themselves.
+#[derive(Default)]
+pub struct BlockEntities {
+    pub entities: HashMap<Vec3<i32>, BlockEntity>,
+    /// Block ids that get a block entity when placed, with the data it starts out with.
+    pub templates: HashMap<u32, Value>,
+    pub folder: Option<PathBuf>,
+    pub chunk_size: usize,
+    /// Chunks whose block entities changed since the last save.
+    pub dirty: HashSet<Vec2<i32>>,
+    /// The chunks each client has already been sent the block entities of.
+    pub synced: HashMap<String, HashSet<Vec2<i32>>>,
+}
+
+impl BlockEntities {
+    pub fn new(folder: Option<PathBuf>, chunk_size: usize) -> Self {
+        Self {
+            folder,
+            chunk_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn track(mut self, id: u32, template: Value) -> Self {
+        self.templates.insert(id, template);
+        self
+    }
+
+    pub fn is_tracked(&self, id: u32) -> bool {
+        self.templates.contains_key(&id)
+    }
+
+    pub fn get(&self, voxel: &Vec3<i32>) -> Option<&BlockEntity> {
+        self.entities.get(voxel)
+    }
+
+    /// Create a block entity with the template data of `id`.
+    pub fn create(&mut self, voxel: &Vec3<i32>, id: u32) -> Option<&BlockEntity> {
+        let data = self.templates.get(&id)?.to_owned();
+
+        self.set(BlockEntity {
+            voxel: voxel.to_owned(),
+            id,
+            data,
+        });
+
+        self.entities.get(voxel)
+    }
+
+    pub fn set(&mut self, entity: BlockEntity) {
+        self.dirty.insert(self.coords_of(&entity.voxel));
+        self.entities.insert(entity.voxel.to_owned(), entity);
+    }
+
+    pub fn remove(&mut self, voxel: &Vec3<i32>) -> Option<BlockEntity> {
+        let removed = self.entities.remove(voxel);
+
+        if removed.is_some() {
+            self.dirty.insert(self.coords_of(voxel));
+        }
+
+        removed
+    }
+
+    pub fn in_chunk(&self, coords: &Vec2<i32>) -> Vec<&BlockEntity> {
+        self.entities
+            .values()
+            .filter(|entity| self.coords_of(&entity.voxel) == *coords)
+            .collect()
+    }
+
+    pub fn coords_of(&self, voxel: &Vec3<i32>) -> Vec2<i32> {
+        let Vec3(vx, vy, vz) = *voxel;
+        ChunkUtils::map_voxel_to_chunk(vx, vy, vz, self.chunk_size)
+    }
+
+    pub fn load(&mut self) {
+        let folder = match &self.folder {
+            Some(folder) => folder.to_owned(),
+            None => return,
+        };
+
+        if let Err(e) = fs::create_dir_all(&folder) {
+            warn!("Failed to create block entities folder: {}", e);
+            return;
+        }
+
+        let files = match fs::read_dir(&folder) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Failed to read block entities folder: {}", e);
+                return;
+            }
+        };
+
+        for file in files.flatten() {
+            let entities = File::open(file.path())
+                .ok()
+                .and_then(|file| serde_json::from_reader::<_, Vec<BlockEntity>>(file).ok());
+
+            match entities {
+                Some(entities) => {
+                    for entity in entities {
+                        self.entities.insert(entity.voxel.to_owned(), entity);
+                    }
+                }
+                None => warn!("Failed to load block entities from {:?}", file.path()),
+            }
+        }
+    }
+
+    /// Write the block entities of every dirty chunk to disk.
+    pub fn save(&mut self) {
+        let folder = match &self.folder {
+            Some(folder) => folder.to_owned(),
+            None => {
+                self.dirty.clear();
+                return;
+            }
+        };
+
+        let dirty = self.dirty.drain().collect::<Vec<_>>();
+
+        for coords in dirty {
+            let path = folder.join(format!("{}|{}.json", coords.0, coords.1));
+            let entities = self.in_chunk(&coords);
+
+            let result = if entities.is_empty() {
+                fs::remove_file(&path).or_else(|e| {
+                    if e.kind() == std::io::ErrorKind::NotFound {
+                        Ok(())
+                    } else {
+                        Err(e)
+                    }
+                })
+            } else {
+                File::create(&path).and_then(|file| {
+                    serde_json::to_writer(file, &entities).map_err(std::io::Error::from)
+                })
+            };
+
+            if let Err(e) = result {
+                warn!("Failed to save block entities of chunk {:?}: {}", coords, e);
+            }
+        }
+    }
+}
+
+pub fn setup_block_entities(world: &mut World) {
+    let (folder, chunk_size) = {
+        let config = world.config();
+
+        let folder = if config.saving {
+            Some(PathBuf::from(&config.save_dir).join("block-entities"))
+        } else {
+            None
+        };
+
+        (folder, config.chunk_size)
+    };
+
+    let link = json!({ "link": null, "caption": "" });
+
+    let mut block_entities = BlockEntities::new(folder, chunk_size)
+        .track(50001, json!({ "image": null, "caption": "" }))
+        .track(1500, link.to_owned())
+        .track(1501, link.to_owned())
+        .track(1502, link.to_owned())
+        .track(1503, link.to_owned())
+        .track(1504, link.to_owned())
+        .track(1505, link)
+        .track(WALL_SIGN_ID, sign_data("", None))
+        .track(STANDING_SIGN_ID, sign_data("", None));
+
+    block_entities.load();
+
+    world.ecs_mut().insert(block_entities);
+}
@@ -1,32 +1,154 @@
 use serde::Deserialize;
+use sha1::{Digest, Sha1};
 use specs::Entity;
-use voxelize::{default_client_parser, World};
+use voxelize::{default_client_parser, NameComp, World};
 
-use super::components::RoleComp;
+use crate::constants::get_admin_key;
+
+use super::components::{PlayerComp, RoleComp, GUEST_ROLE, OWNER_ROLE};
+
+/// Player keys shorter than this are too easy to guess to tell players apart by.
+const MIN_PLAYER_KEY_LENGTH: usize = 16;
+
+/// The key a client has to send to be given the owner role, `None` if no one can be.
+#[derive(Default)]
+pub struct AdminKey(pub Option<String>);
 
 #[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
 struct ClientJSON {
-    role: String,
+    admin_key: Option<String>,
+    /// A random secret the client keeps, see `player_id`.
+    player_key: Option<String>,
+}
+
+/// The identity of the player with this key. Anything the server keeps about a player is
+/// keyed on this hash instead of the key itself, so the key never has to leave the server
+/// to show whose something is.
+fn player_id(key: &str) -> String {
+    Sha1::new()
+        .chain_update(b"player:")
+        .chain_update(key.as_bytes())
+        .finalize()
+        .iter()
+        .take(12)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A name without the `$color$` codes players' names are drawn with.
+fn plain_name(name: &str) -> String {
+    name.split('$')
+        .enumerate()
+        .filter(|(index, _)| index % 2 == 0)
+        .map(|(_, part)| part)
+        .collect()
 }
 
 fn client_modifier(world: &mut World, ent: Entity) {
-    world.add(ent, RoleComp::default());
+    world.add(ent, RoleComp(GUEST_ROLE.to_owned()));
+    world.add(ent, PlayerComp::default());
 }
 
+/// Clients don't get to say what role they have. The server gives the owner role to the
+/// ones that send the admin key, and every other client is a guest.
 fn client_parser(world: &mut World, metadata: &str, ent: Entity) {
     default_client_parser(world, metadata, ent.to_owned());
 
     let metadata = serde_json::from_str::<ClientJSON>(metadata).unwrap_or_default();
 
+    let is_admin = match (&world.read_resource::<AdminKey>().0, &metadata.admin_key) {
+        (Some(key), Some(sent)) => key == sent,
+        _ => false,
+    };
+
     {
         let mut roles = world.write_component::<RoleComp>();
         if let Some(role) = roles.get_mut(ent) {
-            role.0 = metadata.role.to_owned();
+            role.0 = if is_admin { OWNER_ROLE } else { GUEST_ROLE }.to_owned();
         }
     }
+
+    // A client is the same player for as long as it's connected.
+    if let Some(key) = metadata.player_key {
+        let mut players = world.write_component::<PlayerComp>();
+
+        if let Some(player) = players.get_mut(ent) {
+            if player.0.is_none() && key.len() >= MIN_PLAYER_KEY_LENGTH {
+                player.0 = Some(player_id(&key));
+            }
+        }
+    }
+}
+
+/// Whether the client with this id proved it's the owner.
+pub fn is_owner(world: &World, client_id: &str) -> bool {
+    let entity = match world.clients().get(client_id) {
+        Some(client) => client.entity,
+        None => return false,
+    };
+
+    world
+        .read_component::<RoleComp>()
+        .get(entity)
+        .map(|role| role.is_owner())
+        .unwrap_or(false)
+}
+
+/// The role the server gave the client with this id.
+pub fn client_role(world: &World, client_id: &str) -> Option<String> {
+    let entity = world.clients().get(client_id)?.entity;
+
+    world
+        .read_component::<RoleComp>()
+        .get(entity)
+        .map(|role| role.0.to_owned())
+}
+
+/// The player behind the client with this id, the same every time they join. `None` if
+/// the client never said.
+pub fn client_player(world: &World, client_id: &str) -> Option<String> {
+    let entity = world.clients().get(client_id)?.entity;
+
+    world
+        .read_component::<PlayerComp>()
+        .get(entity)
+        .and_then(|player| player.0.to_owned())
+}
+
+/// The player a client id or the name of someone online stands for. Names are matched
+/// without their color codes.
+pub fn find_player(world: &World, who: &str) -> Option<String> {
+    if let Some(player) = client_player(world, who) {
+        return Some(player);
+    }
+
+    let names = world.read_component::<NameComp>();
+    let players = world.read_component::<PlayerComp>();
+
+    world.clients().values().find_map(|client| {
+        let name = names.get(client.entity)?;
+
+        if plain_name(&name.0) != who {
+            return None;
+        }
+
+        players.get(client.entity)?.0.to_owned()
+    })
+}
+
+/// The name the client with this id joined with.
+pub fn client_name(world: &World, client_id: &str) -> Option<String> {
+    let entity = world.clients().get(client_id)?.entity;
+
+    world
+        .read_component::<NameComp>()
+        .get(entity)
+        .map(|name| name.0.to_owned())
 }
 
 pub fn setup_client(world: &mut World) {
+    world.ecs_mut().insert(AdminKey(get_admin_key()));
     world.set_client_parser(client_parser);
     world.set_client_modifier(client_modifier);
 }
@@ -1,4 +1,5 @@
 mod flags;
+mod player;
 mod role;
 mod rotation;
 mod text;
@@ -6,7 +7,8 @@ mod text;
 use specs::WorldExt;
 
 pub use flags::*;
-pub use role::RoleComp;
+pub use player::PlayerComp;
+pub use role::{RoleComp, GUEST_ROLE, OWNER_ROLE};
 pub use rotation::RotationComp;
 pub use text::TextComp;
 
@@ -17,4 +19,5 @@ pub fn setup_components(world: &mut World) {
     world.ecs_mut().register::<RotationComp>();
     world.ecs_mut().register::<BotFlag>();
     world.ecs_mut().register::<RoleComp>();
+    world.ecs_mut().register::<PlayerComp>();
 }
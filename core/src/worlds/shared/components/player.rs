@@ -0,0 +1,7 @@
+use specs::{Component, VecStorage};
+
+/// The stable identity of the player behind a client, worked out from the player key their
+/// browser keeps, so it's the same every time they join. `None` until the client sent it.
+#[derive(Default, Component)]
+#[storage(VecStorage)]
+pub struct PlayerComp(pub Option<String>);
@@ -1,6 +1,18 @@
 use serde::Serialize;
 use specs::{Component, VecStorage};
 
+/// The role of the site owner, who can edit anything in any world.
+pub const OWNER_ROLE: &str = "OWNER";
+
+/// The role of everyone else.
+pub const GUEST_ROLE: &str = "GUEST";
+
 #[derive(Default, Component, Serialize)]
 #[storage(VecStorage)]
 pub struct RoleComp(pub String);
+
+impl RoleComp {
+    pub fn is_owner(&self) -> bool {
+        self.0 == OWNER_ROLE
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use log::warn;
+use serde::Deserialize;
+use voxelize::{Vec3, World};
+
+use crate::constants::{get_contributions_path, get_timezone};
+
+/// Id of "Github Contribution L0", the rest of the levels follow it.
+pub const CONTRIBUTION_BLOCK_ID: u32 = 3000;
+pub const MAX_CONTRIBUTION_LEVEL: u32 = 4;
+
+/// How many weeks the wall shows, the same as the calendar on a GitHub profile.
+pub const CONTRIBUTION_WEEKS: usize = 53;
+
+/// Top of the oldest week's column in the main world. Newer weeks go towards negative x,
+/// and each week runs from Sunday at the top down to Saturday.
+pub const CONTRIBUTION_WALL_ANCHOR: Vec3<i32> = Vec3(26, 47, 68);
+
+/// The `contributionCalendar` returned by GitHub's GraphQL API.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContributionCalendar {
+    /// The colors of the contribution levels, from least to most active.
+    pub colors: Vec<String>,
+    pub weeks: Vec<ContributionWeek>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContributionWeek {
+    pub contribution_days: Vec<ContributionDay>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ContributionDay {
+    pub color: String,
+    pub weekday: u32,
+}
+
+impl ContributionCalendar {
+    /// Days colored with none of the level colors had no contributions.
+    pub fn level_of(&self, day: &ContributionDay) -> u32 {
+        self.colors
+            .iter()
+            .position(|color| *color == day.color)
+            .map(|index| (index as u32 + 1).min(MAX_CONTRIBUTION_LEVEL))
+            .unwrap_or(0)
+    }
+}
+
+/// Builds the contribution calendar wall out of the "Github Contribution" blocks. The
+/// calendar file is checked once a day, and the wall is only rebuilt if it changed.
+pub struct ContributionWall {
+    pub path: Option<PathBuf>,
+    pub anchor: Vec3<i32>,
+    pub timezone: Tz,
+    /// The day the calendar file was last checked.
+    pub checked: Option<NaiveDate>,
+    /// Modification time of the calendar file the wall was last built from.
+    pub built_from: Option<SystemTime>,
+}
+
+impl Default for ContributionWall {
+    fn default() -> Self {
+        Self::new(None, CONTRIBUTION_WALL_ANCHOR)
+    }
+}
+
+impl ContributionWall {
+    pub fn new(path: Option<PathBuf>, anchor: Vec3<i32>) -> Self {
+        Self {
+            path,
+            anchor,
+            timezone: get_timezone(),
+            checked: None,
+            built_from: None,
+        }
+    }
+
+    /// Make the next check rebuild the wall, whether or not the file changed.
+    pub fn invalidate(&mut self) {
+        self.checked = None;
+        self.built_from = None;
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        let path = self.path.as_ref()?;
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    pub fn load(&self) -> Option<ContributionCalendar> {
+        let path = self.path.as_ref()?;
+
+        let calendar = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::from_reader(file).map_err(|e| e.to_string()));
+
+        match calendar {
+            Ok(calendar) => Some(calendar),
+            Err(e) => {
+                warn!("Failed to load contributions from {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// The corners of the wall, inclusive.
+    pub fn bounds(&self) -> (Vec3<i32>, Vec3<i32>) {
+        let Vec3(ax, ay, az) = self.anchor;
+
+        (
+            Vec3(ax - CONTRIBUTION_WEEKS as i32 + 1, ay - 6, az),
+            Vec3(ax, ay, az),
+        )
+    }
+
+    /// The voxel updates that draw the latest weeks of `calendar`. Days missing from the
+    /// first and last week are cleared.
+    pub fn updates(&self, calendar: &ContributionCalendar) -> Vec<(Vec3<i32>, u32)> {
+        let Vec3(ax, ay, az) = self.anchor;
+
+        let start = calendar.weeks.len().saturating_sub(CONTRIBUTION_WEEKS);
+        let mut updates = vec![];
+
+        for (column, week) in calendar.weeks[start..].iter().enumerate() {
+            let vx = ax - column as i32;
+            let mut levels = [None; 7];
+
+            for day in week.contribution_days.iter() {
+                if let Some(level) = levels.get_mut(day.weekday as usize) {
+                    *level = Some(calendar.level_of(day));
+                }
+            }
+
+            for (weekday, level) in levels.iter().enumerate() {
+                let raw = level
+                    .map(|level| CONTRIBUTION_BLOCK_ID + level)
+                    .unwrap_or(0);
+                updates.push((Vec3(vx, ay - weekday as i32, az), raw));
+            }
+        }
+
+        updates
+    }
+}
+
+pub fn setup_contribution_wall(world: &mut World) {
+    world.ecs_mut().insert(ContributionWall::new(
+        get_contributions_path(),
+        CONTRIBUTION_WALL_ANCHOR,
+    ));
+}
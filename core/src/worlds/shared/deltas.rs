@@ -0,0 +1,369 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, RwLock,
+    },
+    thread,
+};
+
+use hashbrown::HashMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use voxelize::{Chunk, ChunkStage, ChunkUtils, Resources, Space, Vec2, Vec3, VoxelAccess, World};
+
+/// The voxels changed in each chunk since it generated, with the raw value left behind.
+type DeltaMap = HashMap<Vec2<i32>, HashMap<Vec3<i32>, u32>>;
+
+/// How the changes to one chunk are saved.
+#[derive(Serialize, Deserialize, Debug)]
+struct DeltaFile {
+    /// The generator version the changes were made on top of.
+    version: u32,
+    voxels: Vec<(Vec3<i32>, u32)>,
+}
+
+/// A file the delta writer is asked to write or delete.
+enum DeltaJob {
+    Save(PathBuf, DeltaFile),
+    Remove(PathBuf),
+}
+
+/// Writes chunk changes to disk on a thread of its own, in the order they were kept, so
+/// saving never holds up the tick. When a chunk is saved again before its last file was
+/// written, only the latest one is written.
+struct DeltaWriter {
+    sender: Sender<DeltaJob>,
+}
+
+impl DeltaWriter {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<DeltaJob>();
+
+        thread::spawn(move || {
+            while let Ok(first) = receiver.recv() {
+                let mut pending = vec![first];
+                pending.extend(receiver.try_iter());
+
+                for (index, job) in pending.iter().enumerate() {
+                    let result = match job {
+                        DeltaJob::Save(path, data) => {
+                            let superseded = pending[index + 1..].iter().any(
+                                |later| matches!(later, DeltaJob::Save(later, _) if later == path),
+                            );
+
+                            if superseded {
+                                continue;
+                            }
+
+                            File::create(path)
+                                .map_err(|e| e.to_string())
+                                .and_then(|file| {
+                                    serde_json::to_writer(file, data).map_err(|e| e.to_string())
+                                })
+                                .map_err(|e| (path, e))
+                        }
+                        DeltaJob::Remove(path) if path.exists() => {
+                            fs::remove_file(path).map_err(|e| (path, e.to_string()))
+                        }
+                        DeltaJob::Remove(_) => Ok(()),
+                    };
+
+                    if let Err((path, e)) = result {
+                        warn!("Failed to save the chunk changes to {:?}: {}", path, e);
+                    }
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    fn send(&self, job: DeltaJob) {
+        if self.sender.send(job).is_err() {
+            warn!("The chunk changes writer stopped, so changes are no longer saved.");
+        }
+    }
+}
+
+/// What the `pregen` binary stamps a world's pregenerated chunks with.
+#[derive(Serialize, Deserialize, Debug)]
+struct ChunksStamp {
+    /// The generator version the chunks were made with.
+    version: u32,
+}
+
+/// The changes made to a world whose chunks are regenerated from its seed every time
+/// instead of being saved. Only the voxels that changed since generation are kept, whether
+/// players or the server itself changed them, saved per chunk under
+/// `{save_dir}/deltas/{cx}|{cz}.json`, and `DeltaStage` puts them back once a chunk is
+/// generated again.
+///
+/// Each file is stamped with the version of the generator it was saved with. Bump it
+/// whenever a change to the world's generation moves its land around, so the server can
+/// warn that old changes may not line up with the new land.
+///
+/// Chunks can still be made ahead of time with the `pregen` binary, which runs them through
+/// the whole pipeline, `DeltaStage` included, into `{save_dir}/chunks`. The server loads
+/// those as they are, so once a chunk changes again its pregenerated file is deleted and it's
+/// regenerated with every change on top the next time it's needed.
+#[derive(Default)]
+pub struct ChunkDeltas {
+    pub deltas: Arc<RwLock<DeltaMap>>,
+    pub folder: Option<PathBuf>,
+    /// Where pregenerated chunks are loaded from.
+    pub chunks: Option<PathBuf>,
+    pub version: u32,
+    pub chunk_size: usize,
+    writer: Option<DeltaWriter>,
+}
+
+impl ChunkDeltas {
+    pub fn new(folder: PathBuf, chunks: PathBuf, version: u32, chunk_size: usize) -> Self {
+        Self {
+            folder: Some(folder),
+            chunks: Some(chunks),
+            writer: Some(DeltaWriter::spawn()),
+            version,
+            chunk_size,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the world keeps its changes this way at all.
+    pub fn is_enabled(&self) -> bool {
+        self.folder.is_some()
+    }
+
+    pub fn coords_of(&self, voxel: &Vec3<i32>) -> Vec2<i32> {
+        let Vec3(vx, vy, vz) = *voxel;
+        ChunkUtils::map_voxel_to_chunk(vx, vy, vz, self.chunk_size)
+    }
+
+    /// Keep the changes applied to the world, given as `(voxel, raw)`, and save the chunks
+    /// whose kept changes they alter. The files are written by `DeltaWriter` off the tick.
+    pub fn record(&mut self, changes: &[(Vec3<i32>, u32)]) {
+        if !self.is_enabled() || changes.is_empty() {
+            return;
+        }
+
+        let mut touched = vec![];
+
+        {
+            let mut deltas = self.deltas.write().unwrap();
+
+            for (voxel, raw) in changes {
+                let coords = self.coords_of(voxel);
+
+                let kept = deltas
+                    .entry(coords.to_owned())
+                    .or_default()
+                    .insert(voxel.to_owned(), *raw);
+
+                if kept != Some(*raw) && !touched.contains(&coords) {
+                    touched.push(coords);
+                }
+            }
+        }
+
+        for coords in touched {
+            self.drop_pregenerated(&coords);
+            self.save(&coords);
+        }
+    }
+
+    /// Delete the pregenerated file of a chunk that changed, so it isn't loaded without the
+    /// change next time.
+    fn drop_pregenerated(&self, coords: &Vec2<i32>) {
+        if let (Some(chunks), Some(writer)) = (&self.chunks, &self.writer) {
+            let name = ChunkUtils::get_chunk_name(coords.0, coords.1);
+            writer.send(DeltaJob::Remove(chunks.join(format!("{}.json", name))));
+        }
+    }
+
+    fn stamp_path(&self) -> Option<PathBuf> {
+        self.chunks
+            .as_ref()
+            .map(|chunks| chunks.with_extension("json"))
+    }
+
+    /// The generator version the pregenerated chunks were made with, if they were stamped.
+    pub fn chunks_version(&self) -> Option<u32> {
+        let file = File::open(self.stamp_path()?).ok()?;
+
+        serde_json::from_reader::<_, ChunksStamp>(file)
+            .ok()
+            .map(|stamp| stamp.version)
+    }
+
+    /// Stamp the pregenerated chunks with the current generator version.
+    pub fn stamp_chunks(&self) -> Result<(), String> {
+        let path = self
+            .stamp_path()
+            .ok_or_else(|| "The world doesn't keep chunk changes.".to_owned())?;
+        let file = File::create(path).map_err(|e| e.to_string())?;
+
+        serde_json::to_writer(
+            file,
+            &ChunksStamp {
+                version: self.version,
+            },
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn save(&self, coords: &Vec2<i32>) {
+        let (folder, writer) = match (&self.folder, &self.writer) {
+            (Some(folder), Some(writer)) => (folder, writer),
+            _ => return,
+        };
+
+        let data = DeltaFile {
+            version: self.version,
+            voxels: self
+                .deltas
+                .read()
+                .unwrap()
+                .get(coords)
+                .map(|voxels| {
+                    voxels
+                        .iter()
+                        .map(|(voxel, raw)| (voxel.to_owned(), *raw))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        let path = folder.join(format!("{}|{}.json", coords.0, coords.1));
+
+        writer.send(DeltaJob::Save(path, data));
+    }
+
+    pub fn load(&mut self) {
+        let folder = match &self.folder {
+            Some(folder) => folder.to_owned(),
+            None => return,
+        };
+
+        if let Err(e) = fs::create_dir_all(&folder) {
+            warn!("Failed to create the chunk changes folder: {}", e);
+            return;
+        }
+
+        let files = match fs::read_dir(&folder) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Failed to read the chunk changes folder: {}", e);
+                return;
+            }
+        };
+
+        let mut deltas = self.deltas.write().unwrap();
+        let mut outdated: HashMap<u32, usize> = HashMap::new();
+
+        for file in files.flatten() {
+            let data = File::open(file.path())
+                .ok()
+                .and_then(|file| serde_json::from_reader::<_, DeltaFile>(file).ok());
+
+            let data = match data {
+                Some(data) => data,
+                None => {
+                    warn!("Failed to load chunk changes from {:?}", file.path());
+                    continue;
+                }
+            };
+
+            if data.version != self.version {
+                *outdated.entry(data.version).or_default() += 1;
+            }
+
+            for (voxel, raw) in data.voxels {
+                let Vec3(vx, vy, vz) = voxel;
+                let coords = ChunkUtils::map_voxel_to_chunk(vx, vy, vz, self.chunk_size);
+
+                deltas.entry(coords).or_default().insert(voxel, raw);
+            }
+        }
+
+        for (version, count) in outdated {
+            warn!(
+                "{} chunks of changes in {:?} were made on generator version {}, but it's \
+                 at version {} now. They're put back as they are, but may not line up with the \
+                 land anymore.",
+                count, folder, version, self.version
+            );
+        }
+    }
+
+    /// The pipeline stage that puts the changes back into regenerated chunks.
+    pub fn stage(&self) -> DeltaStage {
+        DeltaStage {
+            deltas: self.deltas.clone(),
+        }
+    }
+}
+
+/// Writes the changes to a chunk back over it, after everything else generated it.
+pub struct DeltaStage {
+    deltas: Arc<RwLock<DeltaMap>>,
+}
+
+impl ChunkStage for DeltaStage {
+    fn name(&self) -> String {
+        "Deltas".to_owned()
+    }
+
+    fn process(&self, mut chunk: Chunk, _: Resources, _: Option<Space>) -> Chunk {
+        if let Some(voxels) = self.deltas.read().unwrap().get(&chunk.coords) {
+            for (Vec3(vx, vy, vz), raw) in voxels.iter() {
+                chunk.set_raw_voxel(*vx, *vy, *vz, *raw);
+            }
+        }
+
+        chunk
+    }
+}
+
+/// Whether a world keeps only the changes made to it instead of saving its chunks.
+pub fn saves_deltas(world: &World) -> bool {
+    world
+        .ecs()
+        .try_fetch::<ChunkDeltas>()
+        .is_some_and(|deltas| deltas.is_enabled())
+}
+
+/// Keep only the changes made to a world, saved next to its other data, and put them back
+/// with a stage at the end of its pipeline. Call it after every other stage is added and
+/// before `setup_dispatcher`, which records the changes and leaves chunk saving out for
+/// such worlds.
+pub fn setup_deltas(world: &mut World, version: u32) {
+    let (save_dir, chunk_size) = {
+        let config = world.config();
+        (PathBuf::from(&config.save_dir), config.chunk_size)
+    };
+
+    let chunks = save_dir.join("chunks");
+
+    let mut deltas = ChunkDeltas::new(
+        save_dir.join("deltas"),
+        chunks.to_owned(),
+        version,
+        chunk_size,
+    );
+    deltas.load();
+
+    let pregenerated = fs::read_dir(&chunks).map_or(0, |files| files.count());
+
+    if pregenerated > 0 && deltas.chunks_version() != Some(version) {
+        warn!(
+            "{:?} has {} chunks that weren't pregenerated on generator version {}, which are \
+             loaded as they are. Run pregen with --force or delete them to regenerate them.",
+            chunks, pregenerated, version
+        );
+    }
+
+    world.pipeline_mut().add_stage(deltas.stage());
+    world.ecs_mut().insert(deltas);
+}
@@ -0,0 +1,134 @@
+use serde::Deserialize;
+use voxelize::{
+    BlockRotation, BlockUtils, Message, MessageType, UpdateProtocol, Vec3, VoxelAccess, World,
+};
+
+use super::{
+    client::{client_name, client_player, client_role, is_owner},
+    history::EditHistory,
+    plots::Plots,
+    regions::{EditAction, Regions},
+};
+
+/// A voxel update in the shape the client's `updateVoxels` takes, for edits that go through
+/// the `edit-voxels` method so the server knows who made them.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VoxelUpdate {
+    pub vx: i32,
+    pub vy: i32,
+    pub vz: i32,
+    #[serde(rename = "type")]
+    pub id: u32,
+    #[serde(default)]
+    pub rotation: u32,
+    #[serde(default)]
+    pub y_rotation: u32,
+}
+
+impl VoxelUpdate {
+    pub fn voxel(&self) -> Vec3<i32> {
+        Vec3(self.vx, self.vy, self.vz)
+    }
+
+    pub fn raw(&self) -> u32 {
+        let raw = BlockUtils::insert_id(0, self.id);
+        BlockUtils::insert_rotation(raw, &BlockRotation::encode(self.rotation, self.y_rotation))
+    }
+}
+
+/// Whether a client may set a voxel to `raw`, going by the world's plots and regions.
+/// Admins may edit anywhere.
+pub fn can_edit(world: &World, client_id: &str, voxel: &Vec3<i32>, raw: u32) -> bool {
+    if is_owner(world, client_id) {
+        return true;
+    }
+
+    let Vec3(vx, vy, vz) = *voxel;
+    let old = world.chunks().get_voxel(vx, vy, vz);
+    let role = client_role(world, client_id);
+    let player = client_player(world, client_id);
+
+    world
+        .read_resource::<Plots>()
+        .can_edit(player.as_deref(), voxel)
+        && world.read_resource::<Regions>().allows_edit(
+            role.as_deref(),
+            voxel,
+            old,
+            BlockUtils::extract_id(raw),
+        )
+}
+
+/// Whether a client may interact with the block at a voxel, such as editing a sign.
+pub fn can_interact(world: &World, client_id: &str, voxel: &Vec3<i32>) -> bool {
+    if is_owner(world, client_id) {
+        return true;
+    }
+
+    let role = client_role(world, client_id);
+
+    world
+        .read_resource::<Regions>()
+        .allows(role.as_deref(), EditAction::Interact, voxel)
+}
+
+/// Send a client the voxels as they are on the server, so any edit to them it shows that
+/// the server turned down is undone. This goes straight to the client instead of through
+/// the message queue, since nothing changed for anyone else.
+pub fn resync_voxels(world: &World, client_id: &str, voxels: &[Vec3<i32>]) {
+    let updates = {
+        let chunks = world.chunks();
+
+        voxels
+            .iter()
+            .map(|&Vec3(vx, vy, vz)| UpdateProtocol {
+                vx,
+                vy,
+                vz,
+                voxel: chunks.get_raw_voxel(vx, vy, vz),
+                light: chunks.get_raw_light(vx, vy, vz),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if let Some(client) = world.clients().get(client_id) {
+        world.send(
+            &client.addr,
+            &Message::new(&MessageType::Update).updates(&updates).build(),
+        );
+    }
+}
+
+/// Queue edits that have already been checked.
+pub fn apply_edits(world: &mut World, edits: Vec<(Vec3<i32>, u32)>) {
+    let mut chunks = world.chunks_mut();
+
+    for (voxel, raw) in edits {
+        chunks.update_voxel(&voxel, raw);
+    }
+}
+
+/// Queue edits a client made and record them in the world's edit history.
+pub fn apply_client_edits(world: &mut World, client_id: &str, edits: Vec<(Vec3<i32>, u32)>) {
+    let name = client_name(world, client_id).unwrap_or_default();
+    let player = client_player(world, client_id);
+
+    let records = {
+        let chunks = world.chunks();
+
+        edits
+            .iter()
+            .map(|(voxel, raw)| {
+                let Vec3(vx, vy, vz) = *voxel;
+                (voxel.to_owned(), chunks.get_raw_voxel(vx, vy, vz), *raw)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    world
+        .write_resource::<EditHistory>()
+        .record(client_id, player.as_deref(), &name, &records);
+
+    apply_edits(world, edits);
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use chrono::Utc;
+use hashbrown::HashMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use voxelize::{Vec3, World};
+
+/// How many edits a world without a history log keeps in memory, dropping the oldest.
+pub const MAX_MEMORY_EDITS: usize = 100_000;
+
+/// One accepted voxel update, as written to the history log.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EditRecord {
+    /// Client id of the player who made the edit, `None` if it came from no known client.
+    pub client_id: Option<String>,
+    /// The stable id of the player who made the edit, which stays the same across visits.
+    #[serde(default)]
+    pub player: Option<String>,
+    /// The name the player had at the time. Only for showing, since names can be changed.
+    pub name: String,
+    pub timestamp: i64,
+    pub voxel: Vec3<i32>,
+    pub old: u32,
+    pub new: u32,
+}
+
+/// Which edits a rollback undoes. Every filter that is set has to match, and at least a
+/// player or a region has to be given.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EditFilter {
+    /// Stable id of the player, as in `PlayerComp`.
+    pub player: Option<String>,
+    /// Unix timestamps, both inclusive.
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    /// Corners of a box of voxels, both inclusive.
+    pub min: Option<Vec3<i32>>,
+    pub max: Option<Vec3<i32>>,
+}
+
+impl EditFilter {
+    pub fn is_bounded(&self) -> bool {
+        self.player.is_some() || (self.min.is_some() && self.max.is_some())
+    }
+
+    pub fn matches(&self, record: &EditRecord) -> bool {
+        if let Some(player) = &self.player {
+            if record.player.as_ref() != Some(player) {
+                return false;
+            }
+        }
+
+        if self.since.is_some_and(|since| record.timestamp < since)
+            || self.until.is_some_and(|until| record.timestamp > until)
+        {
+            return false;
+        }
+
+        if let (Some(min), Some(max)) = (&self.min, &self.max) {
+            let Vec3(vx, vy, vz) = record.voxel;
+
+            if vx < min.0 || vx > max.0 || vy < min.1 || vy > max.1 || vz < min.2 || vz > max.2 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Every accepted voxel update of a world, appended to `{save_dir}/history.jsonl` one
+/// record per line. Rollbacks are recorded like any other edit, so nothing is ever
+/// rewritten. The log is read back whenever the history is looked at, so it's never held in
+/// memory. Worlds that don't save keep only their last `MAX_MEMORY_EDITS` edits instead.
+#[derive(Default)]
+pub struct EditHistory {
+    pub records: VecDeque<EditRecord>,
+    pub path: Option<PathBuf>,
+}
+
+impl EditHistory {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        client_id: &str,
+        player: Option<&str>,
+        name: &str,
+        edits: &[(Vec3<i32>, u32, u32)],
+    ) {
+        if edits.is_empty() {
+            return;
+        }
+
+        let timestamp = Utc::now().timestamp();
+
+        let records = edits
+            .iter()
+            .filter(|(_, old, new)| old != new)
+            .map(|(voxel, old, new)| EditRecord {
+                client_id: Some(client_id.to_owned()),
+                player: player.map(|player| player.to_owned()),
+                name: name.to_owned(),
+                timestamp,
+                voxel: voxel.to_owned(),
+                old: *old,
+                new: *new,
+            })
+            .collect::<Vec<_>>();
+
+        if self.path.is_some() {
+            self.append(&records);
+            return;
+        }
+
+        self.records.extend(records);
+
+        let excess = self.records.len().saturating_sub(MAX_MEMORY_EDITS);
+        self.records.drain(..excess);
+    }
+
+    /// Every edit ever made to a voxel, oldest first.
+    pub fn at(&self, voxel: &Vec3<i32>) -> Vec<EditRecord> {
+        let mut records = vec![];
+
+        self.for_each(|record| {
+            if record.voxel == *voxel {
+                records.push(record);
+            }
+        });
+
+        records
+    }
+
+    /// The edits that undo everything matching `filter`, along with the number of voxels
+    /// left alone because someone else changed them afterwards. `current` gives the raw
+    /// value a voxel has right now.
+    pub fn rollback<F: Fn(&Vec3<i32>) -> u32>(
+        &self,
+        filter: &EditFilter,
+        current: F,
+    ) -> (Vec<(Vec3<i32>, u32)>, usize) {
+        // For each voxel, the value from before its first matching edit and the value its
+        // last matching edit left behind.
+        let mut changed: HashMap<Vec3<i32>, (u32, u32)> = HashMap::new();
+        let mut order = vec![];
+
+        self.for_each(|record| {
+            if !filter.matches(&record) {
+                return;
+            }
+
+            match changed.get_mut(&record.voxel) {
+                Some((_, last)) => *last = record.new,
+                None => {
+                    changed.insert(record.voxel.to_owned(), (record.old, record.new));
+                    order.push(record.voxel);
+                }
+            }
+        });
+
+        let mut edits = vec![];
+        let mut skipped = 0;
+
+        for voxel in order {
+            let (first, last) = changed[&voxel];
+
+            if current(&voxel) != last {
+                skipped += 1;
+                continue;
+            }
+
+            if first != last {
+                edits.push((voxel, first));
+            }
+        }
+
+        (edits, skipped)
+    }
+
+    fn append(&self, records: &[EditRecord]) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|mut file| {
+                let mut lines = String::new();
+
+                for record in records {
+                    lines.push_str(&serde_json::to_string(record).map_err(|e| e.to_string())?);
+                    lines.push('\n');
+                }
+
+                file.write_all(lines.as_bytes()).map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            warn!("Failed to append to the edit history at {:?}: {}", path, e);
+        }
+    }
+
+    /// Go through every edit in the history, oldest first, reading the log line by line.
+    fn for_each<F: FnMut(EditRecord)>(&self, mut f: F) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                self.records.iter().cloned().for_each(f);
+                return;
+            }
+        };
+
+        if !path.exists() {
+            return;
+        }
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Failed to read the edit history from {:?}: {}", path, e);
+                return;
+            }
+        };
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let record = line
+                .map_err(|e| e.to_string())
+                .and_then(|line| serde_json::from_str(&line).map_err(|e| e.to_string()));
+
+            match record {
+                Ok(record) => f(record),
+                Err(e) => warn!(
+                    "Skipping line {} of the edit history at {:?}: {}",
+                    index + 1,
+                    path,
+                    e
+                ),
+            }
+        }
+    }
+}
+
+pub fn setup_history(world: &mut World) {
+    let path = {
+        let config = world.config();
+
+        if config.saving {
+            Some(PathBuf::from(&config.save_dir).join("history.jsonl"))
+        } else {
+            None
+        }
+    };
+
+    world.ecs_mut().insert(EditHistory::new(path));
+}
@@ -1,12 +1,31 @@
+use hashbrown::HashSet;
+use log::warn;
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
 use specs::{Builder, Join, WorldExt};
 use voxelize::{
-    CollisionsComp, CurrentChunkComp, ETypeComp, EntityFlag, IDComp, MetadataComp, PositionComp,
-    Vec3, World,
+    ClientFilter, CollisionsComp, CurrentChunkComp, ETypeComp, EntityFlag, Event, IDComp,
+    MetadataComp, PositionComp, Registry, Vec2, Vec3, VoxelAccess, World,
 };
 
-use super::components::{BotFlag, TextComp};
+use crate::constants::{get_structures_path, get_vox_path};
+
+use super::{
+    block_data::BlockData,
+    block_entities::{BlockEntities, BlockEntity, BlockEntityPayload},
+    client::{client_player, find_player, is_owner},
+    components::{BotFlag, TextComp},
+    contributions::ContributionWall,
+    edits::{apply_client_edits, can_edit, can_interact, resync_voxels, VoxelUpdate},
+    history::{EditFilter, EditHistory, EditRecord},
+    regions::{Region, Regions},
+    showcase::ProjectShowcase,
+    signs::{is_sign, is_sign_placer, sanitize_sign_text, sign_data},
+    structures::{
+        list_structures, structure_path, Structure, StructureTransform, MAX_STRUCTURE_VOLUME,
+    },
+    vox::{import_vox, ColorTable},
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 struct TimeMethodPayload {
@@ -24,11 +43,203 @@ struct RemoveFloatingTextPayload {
     id: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct SetBlockEntityPayload {
+    voxel: Vec3<i32>,
+    data: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BlockEntitiesPayload {
+    chunks: Vec<Vec2<i32>>,
+}
+
+/// The sign to place in the shape of the client's voxel updates, with its text.
+#[derive(Deserialize, Debug)]
+struct PlaceSignPayload {
+    #[serde(flatten)]
+    update: VoxelUpdate,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct EditSignPayload {
+    voxel: Vec3<i32>,
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct EditVoxelsPayload {
+    updates: Vec<VoxelUpdate>,
+}
+
+#[derive(Serialize, Debug)]
+struct EditRejectedPayload {
+    voxels: Vec<Vec3<i32>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct VoxelHistoryPayload {
+    voxel: Vec3<i32>,
+}
+
+#[derive(Serialize, Debug)]
+struct VoxelHistoryReply {
+    voxel: Vec3<i32>,
+    records: Vec<EditRecord>,
+}
+
+#[derive(Serialize, Debug)]
+struct RollbackReply {
+    restored: usize,
+    skipped: usize,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SaveStructurePayload {
+    name: String,
+    min: Vec3<i32>,
+    max: Vec3<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PasteStructurePayload {
+    name: String,
+    origin: Vec3<i32>,
+    #[serde(flatten)]
+    transform: StructureTransform,
+    /// Clear the whole box to air first instead of only placing the structure's blocks.
+    #[serde(default)]
+    clear: bool,
+}
+
+/// Sent back to the client after saving or pasting a structure.
+#[derive(Serialize, Debug)]
+struct StructureReply {
+    name: String,
+    size: Option<Vec3<i32>>,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ImportVoxPayload {
+    /// File name inside the vox folder.
+    file: String,
+    /// Name to save the structure under, the file's name by default.
+    name: Option<String>,
+    /// Where to paste it right away, if anywhere.
+    origin: Option<Vec3<i32>>,
+    #[serde(flatten)]
+    transform: StructureTransform,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RemoveRegionPayload {
+    name: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct SpawnMethodPayload {
     position: Vec3<f32>,
 }
 
+fn reply_structure(
+    world: &mut World,
+    client_id: &str,
+    name: String,
+    result: Result<Vec3<i32>, String>,
+) {
+    let reply = match result {
+        Ok(size) => StructureReply {
+            name,
+            size: Some(size),
+            error: None,
+        },
+        Err(error) => StructureReply {
+            name,
+            size: None,
+            error: Some(error),
+        },
+    };
+
+    world.events_mut().dispatch(
+        Event::new("structure")
+            .payload(reply)
+            .filter(ClientFilter::Direct(client_id.to_owned()))
+            .build(),
+    );
+}
+
+/// Paste a structure on behalf of an admin, along with its block entities. Returns the size
+/// of the box it took up.
+fn paste_structure(
+    world: &mut World,
+    client_id: &str,
+    structure: &Structure,
+    origin: &Vec3<i32>,
+    transform: &StructureTransform,
+    clear: bool,
+) -> Vec3<i32> {
+    let size = transform.size(&structure.size);
+    let Vec3(ox, oy, oz) = *origin;
+
+    let mut updates = structure.place(&world.read_resource::<Registry>(), origin, transform);
+
+    if clear {
+        let placed = updates
+            .iter()
+            .map(|(voxel, _)| voxel.to_owned())
+            .collect::<HashSet<_>>();
+
+        for x in 0..size.0 {
+            for y in 0..size.1 {
+                for z in 0..size.2 {
+                    let voxel = Vec3(ox + x, oy + y, oz + z);
+
+                    if !placed.contains(&voxel) {
+                        updates.push((voxel, 0));
+                    }
+                }
+            }
+        }
+    }
+
+    apply_client_edits(world, client_id, updates);
+
+    let entities = structure.place_entities(&world.read_resource::<Registry>(), origin, transform);
+
+    let mut events = vec![];
+
+    {
+        let mut block_entities = world.write_resource::<BlockEntities>();
+
+        for entity in entities {
+            let coords = block_entities.coords_of(&entity.voxel);
+            block_entities.set(entity.to_owned());
+
+            events.push(
+                Event::new("block-entity")
+                    .payload(BlockEntityPayload {
+                        voxel: entity.voxel.to_owned(),
+                        entity: Some(entity),
+                    })
+                    .location(coords)
+                    .build(),
+            );
+        }
+
+        block_entities.save();
+    }
+
+    for event in events {
+        world.events_mut().dispatch(event);
+    }
+
+    size
+}
+
 pub fn setup_methods(world: &mut World) {
     world.set_method_handle("time", |world, _, payload| {
         let time_per_day = world.config().time_per_day as f32;
@@ -99,4 +310,509 @@ pub fn setup_methods(world: &mut World) {
                 .expect("Failed to delete entity");
         }
     });
+
+    // Display blocks are only pushed every minute, so new clients ask for the current values.
+    world.set_method_handle("block-data", |world, client_id, _| {
+        let payloads = {
+            let block_data = world.read_resource::<BlockData>();
+
+            block_data
+                .voxels
+                .keys()
+                .filter_map(|voxel| block_data.payload(voxel))
+                .collect::<Vec<_>>()
+        };
+
+        world.events_mut().dispatch(
+            Event::new("block-data-all")
+                .payload(payloads)
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    world.set_method_handle("set-block-entity", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to edit a block entity without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: SetBlockEntityPayload = serde_json::from_str(&payload).unwrap();
+
+        let (entity, coords) = {
+            let mut block_entities = world.write_resource::<BlockEntities>();
+
+            let mut entity = match block_entities.get(&data.voxel) {
+                Some(entity) => entity.to_owned(),
+                None => return,
+            };
+
+            entity.data = data.data;
+            block_entities.set(entity.to_owned());
+            block_entities.save();
+
+            (entity, block_entities.coords_of(&data.voxel))
+        };
+
+        world.events_mut().dispatch(
+            Event::new("block-entity")
+                .payload(BlockEntityPayload {
+                    voxel: data.voxel,
+                    entity: Some(entity),
+                })
+                .location(coords)
+                .build(),
+        );
+    });
+
+    world.set_method_handle("block-entities", |world, client_id, payload| {
+        let data: BlockEntitiesPayload = serde_json::from_str(&payload).unwrap();
+
+        let entities = {
+            let block_entities = world.read_resource::<BlockEntities>();
+
+            data.chunks
+                .iter()
+                .flat_map(|coords| block_entities.in_chunk(coords))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        world.events_mut().dispatch(
+            Event::new("block-entities")
+                .payload(entities)
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    // Signs are placed through here instead of a plain voxel update, so the server knows who
+    // placed them and gets the text in the same step.
+    world.set_method_handle("place-sign", |world, client_id, payload| {
+        let data: PlaceSignPayload = serde_json::from_str(&payload).unwrap();
+        let (voxel, raw) = (data.update.voxel(), data.update.raw());
+        let id = data.update.id;
+
+        if !is_sign(id) {
+            warn!(
+                "Client {} tried to place block {} as a sign.",
+                client_id, id
+            );
+            return;
+        }
+
+        let Vec3(vx, vy, vz) = voxel;
+
+        if world.chunks().get_voxel(vx, vy, vz) != 0 {
+            return;
+        }
+
+        if !can_edit(world, client_id, &voxel, raw) {
+            return;
+        }
+
+        let text = sanitize_sign_text(&data.text);
+        let placer = client_player(world, client_id);
+
+        let (entity, coords) = {
+            let mut block_entities = world.write_resource::<BlockEntities>();
+
+            let entity = BlockEntity {
+                voxel: voxel.to_owned(),
+                id,
+                data: sign_data(&text, placer.as_deref()),
+            };
+
+            block_entities.set(entity.to_owned());
+            block_entities.save();
+
+            (entity, block_entities.coords_of(&voxel))
+        };
+
+        apply_client_edits(world, client_id, vec![(voxel.to_owned(), raw)]);
+
+        world.events_mut().dispatch(
+            Event::new("block-entity")
+                .payload(BlockEntityPayload {
+                    voxel,
+                    entity: Some(entity),
+                })
+                .location(coords)
+                .build(),
+        );
+    });
+
+    world.set_method_handle("edit-sign", |world, client_id, payload| {
+        let data: EditSignPayload = serde_json::from_str(&payload).unwrap();
+
+        let entity = match world.read_resource::<BlockEntities>().get(&data.voxel) {
+            Some(entity) if is_sign(entity.id) => entity.to_owned(),
+            _ => return,
+        };
+
+        let player = client_player(world, client_id);
+
+        if !can_interact(world, client_id, &data.voxel)
+            || (!is_sign_placer(&entity.data, player.as_deref()) && !is_owner(world, client_id))
+        {
+            warn!(
+                "Client {} tried to edit a sign they did not place.",
+                client_id
+            );
+            return;
+        }
+
+        let placer = entity
+            .data
+            .get("placer")
+            .and_then(|placer| placer.as_str())
+            .map(|placer| placer.to_owned());
+
+        let entity = BlockEntity {
+            data: sign_data(&sanitize_sign_text(&data.text), placer.as_deref()),
+            ..entity
+        };
+
+        let coords = {
+            let mut block_entities = world.write_resource::<BlockEntities>();
+
+            block_entities.set(entity.to_owned());
+            block_entities.save();
+
+            block_entities.coords_of(&data.voxel)
+        };
+
+        world.events_mut().dispatch(
+            Event::new("block-entity")
+                .payload(BlockEntityPayload {
+                    voxel: data.voxel,
+                    entity: Some(entity),
+                })
+                .location(coords)
+                .build(),
+        );
+    });
+
+    world.set_method_handle("rebuild-contributions", |world, client_id, _| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to rebuild the contribution wall without permission.",
+                client_id
+            );
+            return;
+        }
+
+        world.write_resource::<ContributionWall>().invalidate();
+    });
+
+    world.set_method_handle("rebuild-showcase", |world, client_id, _| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to rebuild the project showcase without permission.",
+                client_id
+            );
+            return;
+        }
+
+        world.write_resource::<ProjectShowcase>().invalidate();
+    });
+
+    // Player edits go through here instead of plain voxel updates wherever voxels are
+    // protected, since only methods know which client they came from.
+    world.set_method_handle("edit-voxels", |world, client_id, payload| {
+        let data: EditVoxelsPayload = serde_json::from_str(&payload).unwrap();
+        let (allowed, rejected): (Vec<_>, Vec<_>) = data
+            .updates
+            .into_iter()
+            .partition(|update| can_edit(world, client_id, &update.voxel(), update.raw()));
+
+        if !rejected.is_empty() {
+            let voxels = rejected
+                .iter()
+                .map(|update| update.voxel())
+                .collect::<Vec<_>>();
+
+            resync_voxels(world, client_id, &voxels);
+
+            world.events_mut().dispatch(
+                Event::new("edit-rejected")
+                    .payload(EditRejectedPayload { voxels })
+                    .filter(ClientFilter::Direct(client_id.to_owned()))
+                    .build(),
+            );
+        }
+
+        apply_client_edits(
+            world,
+            client_id,
+            allowed
+                .iter()
+                .map(|update| (update.voxel(), update.raw()))
+                .collect(),
+        );
+    });
+
+    world.set_method_handle("set-region", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to change a region without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let region: Region = serde_json::from_str(&payload).unwrap();
+
+        let mut regions = world.write_resource::<Regions>();
+        regions.set(region);
+        regions.save();
+    });
+
+    world.set_method_handle("remove-region", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to remove a region without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: RemoveRegionPayload = serde_json::from_str(&payload).unwrap();
+
+        let mut regions = world.write_resource::<Regions>();
+
+        if regions.remove(&data.name).is_some() {
+            regions.save();
+        }
+    });
+
+    world.set_method_handle("list-regions", |world, client_id, _| {
+        if !is_owner(world, client_id) {
+            return;
+        }
+
+        let regions = world.read_resource::<Regions>().regions.to_owned();
+
+        world.events_mut().dispatch(
+            Event::new("regions")
+                .payload(regions)
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    world.set_method_handle("voxel-history", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to inspect the edit history without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: VoxelHistoryPayload = serde_json::from_str(&payload).unwrap();
+        let records = world.read_resource::<EditHistory>().at(&data.voxel);
+
+        world.events_mut().dispatch(
+            Event::new("voxel-history")
+                .payload(VoxelHistoryReply {
+                    voxel: data.voxel,
+                    records,
+                })
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    world.set_method_handle("rollback", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to roll back edits without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let mut filter: EditFilter = serde_json::from_str(&payload).unwrap();
+
+        // Admins can name someone online by client id or name, but edits are matched by
+        // the player's stable id, since names can be changed to anyone else's.
+        if let Some(player) = filter.player.take() {
+            filter.player = Some(find_player(world, &player).unwrap_or(player));
+        }
+
+        let reply = if filter.is_bounded() {
+            let (edits, skipped) = {
+                let history = world.read_resource::<EditHistory>();
+                let chunks = world.chunks();
+
+                history.rollback(&filter, |voxel| {
+                    let Vec3(vx, vy, vz) = *voxel;
+                    chunks.get_raw_voxel(vx, vy, vz)
+                })
+            };
+
+            let restored = edits.len();
+            apply_client_edits(world, client_id, edits);
+
+            RollbackReply {
+                restored,
+                skipped,
+                error: None,
+            }
+        } else {
+            RollbackReply {
+                restored: 0,
+                skipped: 0,
+                error: Some("Give a player or a region to roll back.".to_owned()),
+            }
+        };
+
+        world.events_mut().dispatch(
+            Event::new("rollback")
+                .payload(reply)
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
+
+    world.set_method_handle("save-structure", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to save a structure without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: SaveStructurePayload = serde_json::from_str(&payload).unwrap();
+
+        let min = Vec3(
+            data.min.0.min(data.max.0),
+            data.min.1.min(data.max.1),
+            data.min.2.min(data.max.2),
+        );
+        let max = Vec3(
+            data.min.0.max(data.max.0),
+            data.min.1.max(data.max.1),
+            data.min.2.max(data.max.2),
+        );
+        // Worked out in i64 so a huge box can't overflow past the limit.
+        let volume = [(min.0, max.0), (min.1, max.1), (min.2, max.2)]
+            .into_iter()
+            .try_fold(1i64, |volume, (from, to)| {
+                volume.checked_mul(to as i64 - from as i64 + 1)
+            })
+            .filter(|volume| *volume <= MAX_STRUCTURE_VOLUME);
+
+        let result = match structure_path(&get_structures_path(), &data.name) {
+            None => Err("Structure names can only use letters, digits, - and _.".to_owned()),
+            Some(_) if volume.is_none() => Err(format!(
+                "Structures can be at most {} voxels.",
+                MAX_STRUCTURE_VOLUME
+            )),
+            Some(path) => {
+                let structure = Structure::copy(
+                    &*world.chunks(),
+                    &world.read_resource::<Registry>(),
+                    &world.read_resource::<BlockEntities>(),
+                    &min,
+                    &max,
+                );
+
+                structure.save(&path).map(|_| structure.size)
+            }
+        };
+
+        reply_structure(world, client_id, data.name, result);
+    });
+
+    world.set_method_handle("paste-structure", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to paste a structure without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: PasteStructurePayload = serde_json::from_str(&payload).unwrap();
+
+        let structure = structure_path(&get_structures_path(), &data.name)
+            .ok_or_else(|| "No structure with that name.".to_owned())
+            .and_then(|path| Structure::load(&path));
+
+        let result = structure.map(|structure| {
+            paste_structure(
+                world,
+                client_id,
+                &structure,
+                &data.origin,
+                &data.transform,
+                data.clear,
+            )
+        });
+
+        reply_structure(world, client_id, data.name, result);
+    });
+
+    world.set_method_handle("import-vox", |world, client_id, payload| {
+        if !is_owner(world, client_id) {
+            warn!(
+                "Client {} tried to import a vox file without permission.",
+                client_id
+            );
+            return;
+        }
+
+        let data: ImportVoxPayload = serde_json::from_str(&payload).unwrap();
+
+        let folder = get_vox_path();
+        let stem = data.file.trim_end_matches(".vox").to_owned();
+        let name = data.name.unwrap_or_else(|| stem.to_owned());
+
+        let structure = match (
+            structure_path(&folder, &stem),
+            structure_path(&get_structures_path(), &name),
+        ) {
+            (Some(_), Some(path)) => {
+                let registry = world.read_resource::<Registry>();
+                let table = ColorTable::load(&folder.join("colors.json"), &registry);
+
+                import_vox(&folder.join(format!("{}.vox", stem)), &registry, &table)
+                    .and_then(|structure| structure.save(&path).map(|_| structure))
+            }
+            _ => Err("File and structure names can only use letters, digits, - and _.".to_owned()),
+        };
+
+        let result = structure.map(|structure| match data.origin {
+            Some(origin) => paste_structure(
+                world,
+                client_id,
+                &structure,
+                &origin,
+                &data.transform,
+                false,
+            ),
+            None => structure.size,
+        });
+
+        reply_structure(world, client_id, name, result);
+    });
+
+    world.set_method_handle("list-structures", |world, client_id, _| {
+        if !is_owner(world, client_id) {
+            return;
+        }
+
+        world.events_mut().dispatch(
+            Event::new("structures")
+                .payload(list_structures(&get_structures_path()))
+                .filter(ClientFilter::Direct(client_id.to_owned()))
+                .build(),
+        );
+    });
 }
@@ -1,8 +1,20 @@
 pub mod astar;
+pub mod block_data;
+pub mod block_entities;
 pub mod client;
 pub mod components;
+pub mod contributions;
+pub mod deltas;
+pub mod edits;
 pub mod entities;
+pub mod history;
 pub mod methods;
+pub mod plots;
 pub mod quaternion;
+pub mod regions;
+pub mod showcase;
+pub mod signs;
 pub mod stage;
+pub mod structures;
 pub mod systems;
+pub mod vox;
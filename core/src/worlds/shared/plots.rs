@@ -0,0 +1,138 @@
+use std::{fs::File, path::PathBuf};
+
+use hashbrown::HashMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use voxelize::{Vec2, Vec3};
+
+/// How many plots a player can claim. Admins can hand out more with `transfer-plot`.
+pub const MAX_PLOTS_PER_PLAYER: usize = 1;
+
+/// A claimed cell of the flat world's grid.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Plot {
+    pub cell: Vec2<i32>,
+    /// The player who owns it, see `client_player`.
+    pub owner: String,
+    pub owner_name: String,
+    /// The players the owner lets build here.
+    #[serde(default)]
+    pub trusted: Vec<String>,
+    pub claimed_at: i64,
+}
+
+impl Plot {
+    pub fn can_edit(&self, player: &str) -> bool {
+        self.owner == player || self.trusted.iter().any(|id| id == player)
+    }
+}
+
+/// The claimed plots of a world, saved as `{save_dir}/plots.json`. Every cell between the
+/// grid outlines of the world's `GridLandStage` is a plot, the outlines themselves belong
+/// to no one.
+#[derive(Default)]
+pub struct Plots {
+    pub plots: HashMap<Vec2<i32>, Plot>,
+    /// Size of the grid, 0 if the world has no plots.
+    pub grid_size: i32,
+    pub path: Option<PathBuf>,
+}
+
+impl Plots {
+    pub fn new(path: Option<PathBuf>, grid_size: i32) -> Self {
+        Self {
+            path,
+            grid_size,
+            ..Default::default()
+        }
+    }
+
+    /// The cell a voxel is in, or `None` if it's on a grid outline.
+    pub fn cell_of(&self, voxel: &Vec3<i32>) -> Option<Vec2<i32>> {
+        let Vec3(vx, _, vz) = *voxel;
+        let size = self.grid_size;
+
+        if size <= 0 || vx.rem_euclid(size) == 0 || vz.rem_euclid(size) == 0 {
+            return None;
+        }
+
+        Some(Vec2(vx.div_euclid(size), vz.div_euclid(size)))
+    }
+
+    /// The voxel columns inside a cell, from the lowest corner to the highest, exclusive.
+    pub fn cell_bounds(&self, cell: &Vec2<i32>) -> (Vec2<i32>, Vec2<i32>) {
+        let Vec2(cx, cz) = *cell;
+        let size = self.grid_size;
+
+        (
+            Vec2(cx * size + 1, cz * size + 1),
+            Vec2((cx + 1) * size, (cz + 1) * size),
+        )
+    }
+
+    pub fn owned_by(&self, player: &str) -> usize {
+        self.plots
+            .values()
+            .filter(|plot| plot.owner == player)
+            .count()
+    }
+
+    /// Whether a player may change a voxel, not counting admins. Voxel updates that came
+    /// from no known player are checked with `None`, so they stay out of every claim.
+    pub fn can_edit(&self, player: Option<&str>, voxel: &Vec3<i32>) -> bool {
+        if self.grid_size <= 0 {
+            return true;
+        }
+
+        let cell = match self.cell_of(voxel) {
+            Some(cell) => cell,
+            None => return false,
+        };
+
+        match (self.plots.get(&cell), player) {
+            (None, _) => true,
+            (Some(plot), Some(player)) => plot.can_edit(player),
+            (Some(_), None) => false,
+        }
+    }
+
+    pub fn load(&mut self) {
+        let path = match &self.path {
+            Some(path) if path.exists() => path.to_owned(),
+            _ => return,
+        };
+
+        let plots = File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                serde_json::from_reader::<_, Vec<Plot>>(file).map_err(|e| e.to_string())
+            });
+
+        match plots {
+            Ok(plots) => {
+                for plot in plots {
+                    self.plots.insert(plot.cell.to_owned(), plot);
+                }
+            }
+            Err(e) => warn!("Failed to load plots from {:?}: {}", path, e),
+        }
+    }
+
+    pub fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let plots = self.plots.values().collect::<Vec<_>>();
+
+        let result = File::create(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::to_writer(file, &plots).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            warn!("Failed to save plots to {:?}: {}", path, e);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{fs::File, path::PathBuf};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use voxelize::{ChunkUtils, Vec2, Vec3, World};
+
+/// Lets a rule apply to every role, including voxel updates from no known client.
+pub const ANY_ROLE: &str = "*";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditAction {
+    Place,
+    Break,
+    Interact,
+}
+
+impl EditAction {
+    /// What changing a voxel from `old` to `new` counts as. Replacing one block with
+    /// another is both breaking and placing.
+    pub fn of(old: u32, new: u32) -> Vec<Self> {
+        match (old, new) {
+            (_, 0) => vec![EditAction::Break],
+            (0, _) => vec![EditAction::Place],
+            _ => vec![EditAction::Break, EditAction::Place],
+        }
+    }
+}
+
+/// Where a region is, either a box of voxels or a range of chunks, both inclusive.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RegionBounds {
+    Aabb { min: Vec3<i32>, max: Vec3<i32> },
+    Chunks { min: Vec2<i32>, max: Vec2<i32> },
+}
+
+/// The roles allowed to do each action inside a region. Admins can always do everything.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RegionRules {
+    #[serde(default)]
+    pub place: Vec<String>,
+    #[serde(default, rename = "break")]
+    pub break_: Vec<String>,
+    #[serde(default)]
+    pub interact: Vec<String>,
+}
+
+impl RegionRules {
+    /// Only admins may place or break, anyone may interact.
+    pub fn admin_only() -> Self {
+        Self {
+            interact: vec![ANY_ROLE.to_owned()],
+            ..Default::default()
+        }
+    }
+
+    pub fn allows(&self, role: Option<&str>, action: EditAction) -> bool {
+        let roles = match action {
+            EditAction::Place => &self.place,
+            EditAction::Break => &self.break_,
+            EditAction::Interact => &self.interact,
+        };
+
+        roles
+            .iter()
+            .any(|allowed| allowed == ANY_ROLE || Some(allowed.as_str()) == role)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Region {
+    pub name: String,
+    pub bounds: RegionBounds,
+    pub rules: RegionRules,
+}
+
+impl Region {
+    pub fn new(name: &str, bounds: RegionBounds, rules: RegionRules) -> Self {
+        Self {
+            name: name.to_owned(),
+            bounds,
+            rules,
+        }
+    }
+}
+
+/// The protected regions of a world, saved as `{save_dir}/regions.json`.
+#[derive(Default)]
+pub struct Regions {
+    pub regions: Vec<Region>,
+    pub path: Option<PathBuf>,
+    pub chunk_size: usize,
+}
+
+impl Regions {
+    pub fn new(path: Option<PathBuf>, chunk_size: usize) -> Self {
+        Self {
+            path,
+            chunk_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn contains(&self, region: &Region, voxel: &Vec3<i32>) -> bool {
+        let Vec3(vx, vy, vz) = *voxel;
+
+        match &region.bounds {
+            RegionBounds::Aabb { min, max } => {
+                vx >= min.0
+                    && vx <= max.0
+                    && vy >= min.1
+                    && vy <= max.1
+                    && vz >= min.2
+                    && vz <= max.2
+            }
+            RegionBounds::Chunks { min, max } => {
+                let Vec2(cx, cz) = ChunkUtils::map_voxel_to_chunk(vx, vy, vz, self.chunk_size);
+                cx >= min.0 && cx <= max.0 && cz >= min.1 && cz <= max.1
+            }
+        }
+    }
+
+    /// Whether a role may do an action at a voxel, not counting admins. Voxel updates that
+    /// came from no known client are checked with `None`.
+    pub fn allows(&self, role: Option<&str>, action: EditAction, voxel: &Vec3<i32>) -> bool {
+        self.regions
+            .iter()
+            .filter(|region| self.contains(region, voxel))
+            .all(|region| region.rules.allows(role, action))
+    }
+
+    pub fn allows_edit(&self, role: Option<&str>, voxel: &Vec3<i32>, old: u32, new: u32) -> bool {
+        EditAction::of(old, new)
+            .into_iter()
+            .all(|action| self.allows(role, action, voxel))
+    }
+
+    /// Add a region, replacing any with the same name.
+    pub fn set(&mut self, region: Region) {
+        self.remove(&region.name);
+        self.regions.push(region);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Region> {
+        let index = self.regions.iter().position(|region| region.name == name)?;
+        Some(self.regions.remove(index))
+    }
+
+    /// Load the saved regions, or start out with `defaults` if there are none yet.
+    pub fn load(&mut self, defaults: Vec<Region>) {
+        let path = match &self.path {
+            Some(path) if path.exists() => path.to_owned(),
+            _ => {
+                self.regions = defaults;
+                return;
+            }
+        };
+
+        let regions = File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::from_reader(file).map_err(|e| e.to_string()));
+
+        match regions {
+            Ok(regions) => self.regions = regions,
+            Err(e) => {
+                warn!("Failed to load regions from {:?}: {}", path, e);
+                self.regions = defaults;
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = File::create(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::to_writer(file, &self.regions).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            warn!("Failed to save regions to {:?}: {}", path, e);
+        }
+    }
+}
+
+pub fn setup_regions(world: &mut World, defaults: Vec<Region>) {
+    let (path, chunk_size) = {
+        let config = world.config();
+
+        let path = if config.saving {
+            Some(PathBuf::from(&config.save_dir).join("regions.json"))
+        } else {
+            None
+        };
+
+        (path, config.chunk_size)
+    };
+
+    let mut regions = Regions::new(path, chunk_size);
+    regions.load(defaults);
+
+    world.ecs_mut().insert(regions);
+}
@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use voxelize::{Vec3, World};
+
+use crate::{
+    constants::get_projects_path,
+    projects::{Project, ProjectManifest},
+};
+
+/// The "Github" link block placed next to each trophy.
+pub const SHOWCASE_LINK_ID: u32 = 1503;
+
+/// How often the projects manifest is checked for changes.
+pub const SHOWCASE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Where one project of the showcase row goes.
+pub struct ShowcaseSlot<'a> {
+    pub project: &'a Project,
+    pub trophy: Vec3<i32>,
+    pub link: Vec3<i32>,
+    pub caption: Vec3<f32>,
+}
+
+/// Lays out the projects manifest as a row of trophies in the main world, each with a
+/// link block to the project and a floating caption. The row is rebuilt whenever the
+/// manifest changes.
+#[derive(Default)]
+pub struct ProjectShowcase {
+    pub path: Option<PathBuf>,
+    pub last_check: Option<Instant>,
+    /// Modification time of the manifest the row was last built from.
+    pub built_from: Option<SystemTime>,
+    /// The manifest the row was last built from, to clear out projects that were removed.
+    pub manifest: Option<ProjectManifest>,
+}
+
+impl ProjectShowcase {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Make the next check rebuild the row, whether or not the manifest changed.
+    pub fn invalidate(&mut self) {
+        self.last_check = None;
+        self.built_from = None;
+    }
+
+    /// Whether it's time to check the manifest again.
+    pub fn due(&mut self) -> bool {
+        if self
+            .last_check
+            .map(|last_check| last_check.elapsed() < SHOWCASE_CHECK_INTERVAL)
+            .unwrap_or(false)
+        {
+            return false;
+        }
+
+        self.last_check = Some(Instant::now());
+        true
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        let path = self.path.as_ref()?;
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    pub fn load(&self) -> Option<ProjectManifest> {
+        ProjectManifest::load(self.path.as_ref()?)
+    }
+}
+
+/// The trophy goes at the start of each slot with its link block right after it, and the
+/// caption floats above the trophy.
+pub fn showcase_slots(manifest: &ProjectManifest) -> Vec<ShowcaseSlot<'_>> {
+    let Vec3(ax, ay, az) = manifest.anchor;
+
+    manifest
+        .projects
+        .iter()
+        .enumerate()
+        .map(|(index, project)| {
+            let vx = ax + index as i32 * manifest.spacing;
+
+            ShowcaseSlot {
+                project,
+                trophy: Vec3(vx, ay, az),
+                link: Vec3(vx + 1, ay, az),
+                caption: Vec3(vx as f32 + 0.5, ay as f32 + 1.5, az as f32 + 0.5),
+            }
+        })
+        .collect()
+}
+
+pub fn setup_project_showcase(world: &mut World) {
+    world
+        .ecs_mut()
+        .insert(ProjectShowcase::new(Some(get_projects_path())));
+}
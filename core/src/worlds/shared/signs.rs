@@ -0,0 +1,40 @@
+use serde_json::{json, Value};
+
+pub const WALL_SIGN_ID: u32 = 50006;
+pub const STANDING_SIGN_ID: u32 = 50007;
+
+pub const MAX_SIGN_LINES: usize = 4;
+pub const MAX_SIGN_LINE_LENGTH: usize = 24;
+
+pub fn is_sign(id: u32) -> bool {
+    id == WALL_SIGN_ID || id == STANDING_SIGN_ID
+}
+
+/// The block entity data of a sign. `placer` is the player who placed it, see
+/// `client_player`, the only one other than the owner allowed to change its text.
+pub fn sign_data(text: &str, placer: Option<&str>) -> Value {
+    json!({ "text": text, "placer": placer })
+}
+
+/// Whether `player` placed the sign with this block entity data. Signs no known player
+/// placed are no one's.
+pub fn is_sign_placer(data: &Value, player: Option<&str>) -> bool {
+    player.is_some() && data.get("placer").and_then(Value::as_str) == player
+}
+
+/// Clean up sign text sent by a client: control characters are dropped, and the text is
+/// cut down to `MAX_SIGN_LINES` lines of at most `MAX_SIGN_LINE_LENGTH` characters.
+pub fn sanitize_sign_text(text: &str) -> String {
+    text.lines()
+        .take(MAX_SIGN_LINES)
+        .map(|line| {
+            line.chars()
+                .filter(|c| !c.is_control())
+                .take(MAX_SIGN_LINE_LENGTH)
+                .collect::<String>()
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,32 +1,144 @@
-use voxelize::{Chunk, ChunkStage, Resources, Space, Vec3, VoxelAccess};
+use voxelize::{
+    Chunk, ChunkStage, NoiseOptions, Registry, Resources, SeededNoise, Space, Vec3, VoxelAccess,
+};
 
-const ISLAND_LIMIT: i32 = 1;
-const ISLAND_HEIGHT: i32 = 10;
+/// The outline of an island seen from above.
+#[derive(Clone, Copy, Debug)]
+pub enum IslandShape {
+    Square,
+    Circle,
+    /// A circle whose edge wobbles in and out by up to `roughness` times the radius.
+    Noisy {
+        roughness: f64,
+    },
+}
+
+/// A single island centered on the origin, with nothing generated around it.
+pub struct LimitedStage {
+    radius: i32,
+    shape: IslandShape,
+    /// The y of the island's top surface.
+    top: i32,
+    /// The lowest y of the island, reached at its center.
+    bottom: i32,
+    /// Whether the underside narrows towards the edge instead of dropping straight down.
+    tapered: bool,
+    /// Blocks from the surface downwards, each repeated for its thickness.
+    layers: Vec<u32>,
+    /// The block below the layers.
+    filler: u32,
+    noise: SeededNoise,
+}
+
+impl LimitedStage {
+    pub fn new(registry: &Registry, seed: u32) -> Self {
+        Self {
+            radius: 24,
+            shape: IslandShape::Square,
+            top: 9,
+            bottom: 0,
+            tapered: false,
+            layers: vec![],
+            filler: registry.get_block_by_name("Stone").id,
+            noise: SeededNoise::new(seed, &NoiseOptions::new().frequency(0.05).build()),
+        }
+    }
+
+    pub fn radius(mut self, radius: i32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn shape(mut self, shape: IslandShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn height(mut self, bottom: i32, top: i32) -> Self {
+        self.bottom = bottom;
+        self.top = top;
+        self
+    }
+
+    pub fn tapered(mut self, tapered: bool) -> Self {
+        self.tapered = tapered;
+        self
+    }
+
+    pub fn add_layer(mut self, block: u32, thickness: usize) -> Self {
+        for _ in 0..thickness {
+            self.layers.push(block);
+        }
+
+        self
+    }
+
+    pub fn filler(mut self, block: u32) -> Self {
+        self.filler = block;
+        self
+    }
 
-pub struct LimitedStage;
+    /// How far out a column is, where 0 is the center and 1 is the edge. Columns at 1 or
+    /// further are not part of the island.
+    fn distance(&self, vx: i32, vz: i32) -> f64 {
+        let radius = self.radius.max(1) as f64;
+        let (x, z) = (vx as f64 + 0.5, vz as f64 + 0.5);
+
+        match self.shape {
+            IslandShape::Square => x.abs().max(z.abs()) / radius,
+            IslandShape::Circle => (x * x + z * z).sqrt() / radius,
+            IslandShape::Noisy { roughness } => {
+                let wobble = self.noise.get2d(vx, vz).clamp(-1.0, 1.0) * roughness;
+                (x * x + z * z).sqrt() / (radius * (1.0 + wobble))
+            }
+        }
+    }
+
+    /// The furthest any column can be from the center.
+    fn extent(&self) -> i32 {
+        match self.shape {
+            IslandShape::Noisy { roughness } => {
+                (self.radius as f64 * (1.0 + roughness.abs())).ceil() as i32
+            }
+            _ => self.radius,
+        }
+    }
+}
 
 impl ChunkStage for LimitedStage {
     fn name(&self) -> String {
         "Limited Stage".to_owned()
     }
 
-    fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
-        if chunk.coords.0 > ISLAND_LIMIT
-            || chunk.coords.1 > ISLAND_LIMIT
-            || chunk.coords.0 < -ISLAND_LIMIT
-            || chunk.coords.1 < -ISLAND_LIMIT
-        {
-            return chunk;
-        }
-
-        let id = resources.registry.get_block_by_name("Stone").id;
+    fn process(&self, mut chunk: Chunk, _: Resources, _: Option<Space>) -> Chunk {
+        let extent = self.extent();
 
         let Vec3(min_x, _, min_z) = chunk.min;
         let Vec3(max_x, _, max_z) = chunk.max;
 
+        if min_x > extent || min_z > extent || max_x < -extent || max_z < -extent {
+            return chunk;
+        }
+
         for vx in min_x..max_x {
             for vz in min_z..max_z {
-                for vy in 0..ISLAND_HEIGHT {
+                let distance = self.distance(vx, vz);
+
+                if distance >= 1.0 {
+                    continue;
+                }
+
+                let bottom = if self.tapered {
+                    let thickness = (self.top - self.bottom) as f64;
+                    self.top - (thickness * (1.0 - distance * distance).sqrt()).round() as i32
+                } else {
+                    self.bottom
+                };
+
+                for vy in bottom..=self.top {
+                    let depth = (self.top - vy) as usize;
+                    let id = self.layers.get(depth).copied().unwrap_or(self.filler);
+
                     chunk.set_voxel(vx, vy, vz, id);
                 }
             }
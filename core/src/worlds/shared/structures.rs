@@ -0,0 +1,525 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use hashbrown::HashMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use voxelize::{
+    BlockRotation, BlockUtils, Chunk, ChunkStage, ChunkUtils, Registry, Resources, Space, Vec2,
+    Vec3, VoxelAccess, VoxelPacker,
+};
+
+use super::block_entities::{BlockEntities, BlockEntity};
+
+/// The largest box of voxels that can be saved as one structure.
+pub const MAX_STRUCTURE_VOLUME: i64 = 64 * 64 * 64;
+
+/// How many steps a full turn around the y axis is split into, same as voxelize.
+const Y_ROTATION_SEGMENTS: u32 = 16;
+
+const PY_ROTATION: u32 = 0;
+const NY_ROTATION: u32 = 1;
+const PX_ROTATION: u32 = 2;
+const NX_ROTATION: u32 = 3;
+const PZ_ROTATION: u32 = 4;
+const NZ_ROTATION: u32 = 5;
+
+/// One non-air voxel of a structure, relative to its lowest corner.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureVoxel {
+    pub offset: Vec3<i32>,
+    /// Index into the structure's palette.
+    pub block: usize,
+    #[serde(default)]
+    pub rotation: u32,
+    #[serde(default)]
+    pub y_rotation: u32,
+    #[serde(default)]
+    pub stage: u32,
+}
+
+/// The data of a block entity inside a structure, relative to its lowest corner.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StructureEntity {
+    pub offset: Vec3<i32>,
+    pub data: Value,
+}
+
+/// How a structure is turned before it's placed. It's mirrored along the x axis first,
+/// then turned clockwise as seen from above in quarter turns.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct StructureTransform {
+    #[serde(default)]
+    pub turns: u32,
+    #[serde(default)]
+    pub mirror: bool,
+}
+
+impl StructureTransform {
+    /// Where an offset inside a structure of `size` ends up.
+    pub fn offset(&self, offset: &Vec3<i32>, size: &Vec3<i32>) -> Vec3<i32> {
+        let Vec3(mut x, y, mut z) = *offset;
+        let Vec3(mut sx, _, mut sz) = *size;
+
+        if self.mirror {
+            x = sx - 1 - x;
+        }
+
+        for _ in 0..self.turns % 4 {
+            (x, z) = (sz - 1 - z, x);
+            (sx, sz) = (sz, sx);
+        }
+
+        Vec3(x, y, z)
+    }
+
+    /// The size of a structure of `size` once it's turned.
+    pub fn size(&self, size: &Vec3<i32>) -> Vec3<i32> {
+        let Vec3(sx, sy, sz) = *size;
+
+        if self.turns % 2 == 1 {
+            Vec3(sz, sy, sx)
+        } else {
+            Vec3(sx, sy, sz)
+        }
+    }
+
+    /// Turn a block's rotation along with the structure.
+    pub fn rotation(&self, rotation: u32, y_rotation: u32) -> (u32, u32) {
+        let (mut rotation, mut y_rotation) = (rotation, y_rotation);
+        let quarter = Y_ROTATION_SEGMENTS / 4;
+
+        if self.mirror {
+            rotation = match rotation {
+                PX_ROTATION => NX_ROTATION,
+                NX_ROTATION => PX_ROTATION,
+                other => other,
+            };
+            y_rotation = (Y_ROTATION_SEGMENTS - y_rotation) % Y_ROTATION_SEGMENTS;
+        }
+
+        for _ in 0..self.turns % 4 {
+            rotation = match rotation {
+                PX_ROTATION => PZ_ROTATION,
+                PZ_ROTATION => NX_ROTATION,
+                NX_ROTATION => NZ_ROTATION,
+                NZ_ROTATION => PX_ROTATION,
+                other => other,
+            };
+
+            if rotation == PY_ROTATION || rotation == NY_ROTATION {
+                y_rotation = (y_rotation + quarter) % Y_ROTATION_SEGMENTS;
+            }
+        }
+
+        (rotation, y_rotation)
+    }
+}
+
+/// A box of voxels copied out of a world, saved as JSON. Blocks are stored by name so a
+/// structure survives block ids being moved around in the registry.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Structure {
+    pub size: Vec3<i32>,
+    /// Names of the blocks the voxels use.
+    pub palette: Vec<String>,
+    pub voxels: Vec<StructureVoxel>,
+    #[serde(default)]
+    pub entities: Vec<StructureEntity>,
+}
+
+impl Structure {
+    /// Copy the voxels from `min` to `max`, both inclusive.
+    pub fn copy(
+        space: &dyn VoxelAccess,
+        registry: &Registry,
+        block_entities: &BlockEntities,
+        min: &Vec3<i32>,
+        max: &Vec3<i32>,
+    ) -> Self {
+        let mut structure = Structure {
+            size: Vec3(max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1),
+            ..Default::default()
+        };
+        let mut palette = HashMap::new();
+
+        for vx in min.0..=max.0 {
+            for vy in min.1..=max.1 {
+                for vz in min.2..=max.2 {
+                    let raw = space.get_raw_voxel(vx, vy, vz);
+                    let id = BlockUtils::extract_id(raw);
+
+                    if registry.is_air(id) {
+                        continue;
+                    }
+
+                    let block = *palette.entry(id).or_insert_with(|| {
+                        structure
+                            .palette
+                            .push(registry.get_block_by_id(id).name.to_owned());
+                        structure.palette.len() - 1
+                    });
+                    let (rotation, y_rotation) =
+                        BlockRotation::decode(&BlockUtils::extract_rotation(raw));
+                    let offset = Vec3(vx - min.0, vy - min.1, vz - min.2);
+
+                    if let Some(entity) = block_entities.get(&Vec3(vx, vy, vz)) {
+                        structure.entities.push(StructureEntity {
+                            offset: offset.to_owned(),
+                            data: entity.data.to_owned(),
+                        });
+                    }
+
+                    structure.voxels.push(StructureVoxel {
+                        offset,
+                        block,
+                        rotation,
+                        y_rotation,
+                        stage: BlockUtils::extract_stage(raw),
+                    });
+                }
+            }
+        }
+
+        structure
+    }
+
+    /// The block ids of the palette in this registry, `None` for blocks it doesn't have.
+    fn palette_ids(&self, registry: &Registry) -> Vec<Option<u32>> {
+        self.palette
+            .iter()
+            .map(|name| {
+                let id = registry
+                    .blocks_by_name
+                    .get(&name.to_lowercase())
+                    .map(|block| block.id);
+
+                if id.is_none() {
+                    warn!("Structure uses unknown block \"{}\", skipping it.", name);
+                }
+
+                id
+            })
+            .collect()
+    }
+
+    /// The raw value of one of the structure's voxels once turned, given its block id.
+    fn pack(voxel: &StructureVoxel, id: u32, transform: &StructureTransform) -> u32 {
+        let (rotation, y_rotation) = transform.rotation(voxel.rotation, voxel.y_rotation);
+
+        VoxelPacker::new()
+            .with_id(id)
+            .with_rotation(BlockRotation::encode(rotation, y_rotation))
+            .with_stage(voxel.stage)
+            .pack()
+    }
+
+    /// The raw voxels of the structure with its lowest corner at `origin`, once turned.
+    pub fn place(
+        &self,
+        registry: &Registry,
+        origin: &Vec3<i32>,
+        transform: &StructureTransform,
+    ) -> Vec<(Vec3<i32>, u32)> {
+        let ids = self.palette_ids(registry);
+
+        self.voxels
+            .iter()
+            .filter_map(|voxel| {
+                let id = (*ids.get(voxel.block)?)?;
+                let Vec3(x, y, z) = transform.offset(&voxel.offset, &self.size);
+
+                Some((
+                    Vec3(origin.0 + x, origin.1 + y, origin.2 + z),
+                    Self::pack(voxel, id, transform),
+                ))
+            })
+            .collect()
+    }
+
+    /// The block entities of the structure with its lowest corner at `origin`, once turned.
+    pub fn place_entities(
+        &self,
+        registry: &Registry,
+        origin: &Vec3<i32>,
+        transform: &StructureTransform,
+    ) -> Vec<BlockEntity> {
+        let ids = self.palette_ids(registry);
+
+        self.entities
+            .iter()
+            .filter_map(|entity| {
+                let voxel = self
+                    .voxels
+                    .iter()
+                    .find(|voxel| voxel.offset == entity.offset)?;
+                let id = (*ids.get(voxel.block)?)?;
+                let Vec3(x, y, z) = transform.offset(&entity.offset, &self.size);
+
+                Some(BlockEntity {
+                    voxel: Vec3(origin.0 + x, origin.1 + y, origin.2 + z),
+                    id,
+                    data: entity.data.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Write the part of the structure that falls inside a chunk. Block entities aren't
+    /// placed, since chunk stages have no access to the world.
+    pub fn place_in_chunk(
+        &self,
+        chunk: &mut Chunk,
+        registry: &Registry,
+        origin: &Vec3<i32>,
+        transform: &StructureTransform,
+    ) {
+        let Vec3(min_x, min_y, min_z) = chunk.min;
+        let Vec3(max_x, max_y, max_z) = chunk.max;
+        let Vec3(sx, sy, sz) = transform.size(&self.size);
+
+        if origin.0 >= max_x
+            || origin.0 + sx <= min_x
+            || origin.1 >= max_y
+            || origin.1 + sy <= min_y
+            || origin.2 >= max_z
+            || origin.2 + sz <= min_z
+        {
+            return;
+        }
+
+        let ids = self.palette_ids(registry);
+
+        // Only the voxels that land in the chunk are packed and written.
+        for voxel in self.voxels.iter() {
+            let Vec3(x, y, z) = transform.offset(&voxel.offset, &self.size);
+            let (vx, vy, vz) = (origin.0 + x, origin.1 + y, origin.2 + z);
+
+            if vx < min_x || vx >= max_x || vy < min_y || vy >= max_y || vz < min_z || vz >= max_z {
+                continue;
+            }
+
+            if let Some(Some(id)) = ids.get(voxel.block) {
+                chunk.set_raw_voxel(vx, vy, vz, Self::pack(voxel, *id, transform));
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        serde_json::from_reader(file).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        }
+
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        serde_json::to_writer(file, self).map_err(|e| e.to_string())
+    }
+}
+
+/// The file a named structure is kept in, or `None` if the name isn't safe to use as one.
+pub fn structure_path(folder: &Path, name: &str) -> Option<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Some(folder.join(format!("{}.json", name)))
+    } else {
+        None
+    }
+}
+
+/// The names of every structure saved in a folder.
+pub fn list_structures(folder: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(folder)
+        .map(|files| {
+            files
+                .filter_map(|file| {
+                    let path = file.ok()?.path();
+
+                    if path.extension()? != "json" {
+                        return None;
+                    }
+
+                    Some(path.file_stem()?.to_str()?.to_owned())
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    names.sort();
+    names
+}
+
+/// The raw voxels of every placed structure, grouped by the chunk they fall in.
+type PlacedVoxels = HashMap<Vec2<i32>, Vec<(Vec3<i32>, u32)>>;
+
+/// Places structures at fixed spots while chunks generate, each chunk writing only its own
+/// part of them. The structures are turned and split into chunks once, the first time a
+/// chunk generates, instead of for every chunk.
+pub struct StructureStage {
+    placements: Vec<(Structure, Vec3<i32>, StructureTransform)>,
+    placed: OnceLock<PlacedVoxels>,
+}
+
+impl StructureStage {
+    pub fn new() -> Self {
+        Self {
+            placements: vec![],
+            placed: OnceLock::new(),
+        }
+    }
+
+    pub fn place(
+        mut self,
+        structure: Structure,
+        origin: Vec3<i32>,
+        transform: StructureTransform,
+    ) -> Self {
+        self.placements.push((structure, origin, transform));
+        self
+    }
+}
+
+impl ChunkStage for StructureStage {
+    fn name(&self) -> String {
+        "Structures".to_owned()
+    }
+
+    fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
+        let placed = self.placed.get_or_init(|| {
+            let chunk_size = resources.config.chunk_size;
+            let max_height = resources.config.max_height as i32;
+            let mut placed = PlacedVoxels::new();
+
+            for (structure, origin, transform) in self.placements.iter() {
+                for (voxel, raw) in structure.place(resources.registry, origin, transform) {
+                    let Vec3(vx, vy, vz) = voxel;
+
+                    if vy < 0 || vy >= max_height {
+                        continue;
+                    }
+
+                    placed
+                        .entry(ChunkUtils::map_voxel_to_chunk(vx, vy, vz, chunk_size))
+                        .or_default()
+                        .push((voxel, raw));
+                }
+            }
+
+            placed
+        });
+
+        if let Some(voxels) = placed.get(&chunk.coords) {
+            for (Vec3(vx, vy, vz), raw) in voxels.iter() {
+                chunk.set_raw_voxel(*vx, *vy, *vz, *raw);
+            }
+        }
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Vec3<i32> = Vec3(3, 2, 5);
+
+    fn turned(turns: u32, mirror: bool) -> StructureTransform {
+        StructureTransform { turns, mirror }
+    }
+
+    #[test]
+    fn turns_swap_the_size() {
+        assert_eq!(turned(0, false).size(&SIZE), Vec3(3, 2, 5));
+        assert_eq!(turned(1, false).size(&SIZE), Vec3(5, 2, 3));
+        assert_eq!(turned(2, true).size(&SIZE), Vec3(3, 2, 5));
+        assert_eq!(turned(3, false).size(&SIZE), Vec3(5, 2, 3));
+    }
+
+    #[test]
+    fn a_quarter_turn_is_clockwise_from_above() {
+        let transform = turned(1, false);
+
+        // The corner at the lowest x and z ends up at the highest x.
+        assert_eq!(transform.offset(&Vec3(0, 1, 0), &SIZE), Vec3(4, 1, 0));
+        assert_eq!(transform.offset(&Vec3(2, 0, 0), &SIZE), Vec3(4, 0, 2));
+        assert_eq!(transform.offset(&Vec3(0, 0, 4), &SIZE), Vec3(0, 0, 0));
+    }
+
+    #[test]
+    fn every_offset_stays_inside_the_turned_size() {
+        for turns in 0..4 {
+            for mirror in [false, true] {
+                let transform = turned(turns, mirror);
+                let Vec3(sx, sy, sz) = transform.size(&SIZE);
+                let mut seen = vec![];
+
+                for x in 0..SIZE.0 {
+                    for y in 0..SIZE.1 {
+                        for z in 0..SIZE.2 {
+                            let offset = transform.offset(&Vec3(x, y, z), &SIZE);
+                            let Vec3(ox, oy, oz) = offset;
+
+                            assert!((0..sx).contains(&ox));
+                            assert!((0..sy).contains(&oy));
+                            assert!((0..sz).contains(&oz));
+                            assert!(!seen.contains(&offset));
+
+                            seen.push(offset);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn four_turns_come_back_around() {
+        let offset = Vec3(1, 1, 3);
+        let mut size = SIZE;
+        let mut turned_offset = offset.to_owned();
+
+        for _ in 0..4 {
+            turned_offset = turned(1, false).offset(&turned_offset, &size);
+            size = turned(1, false).size(&size);
+        }
+
+        assert_eq!(turned_offset, offset);
+        assert_eq!(turned(4, false).offset(&offset, &SIZE), offset);
+    }
+
+    #[test]
+    fn mirroring_flips_x() {
+        let transform = turned(0, true);
+
+        assert_eq!(transform.offset(&Vec3(0, 1, 2), &SIZE), Vec3(2, 1, 2));
+        assert_eq!(transform.rotation(PX_ROTATION, 0), (NX_ROTATION, 0));
+        assert_eq!(transform.rotation(PZ_ROTATION, 0), (PZ_ROTATION, 0));
+        assert_eq!(transform.rotation(PY_ROTATION, 4), (PY_ROTATION, 12));
+    }
+
+    #[test]
+    fn rotations_turn_with_the_structure() {
+        let transform = turned(1, false);
+
+        assert_eq!(transform.rotation(PX_ROTATION, 0), (PZ_ROTATION, 0));
+        assert_eq!(transform.rotation(NZ_ROTATION, 0), (PX_ROTATION, 0));
+        assert_eq!(transform.rotation(PY_ROTATION, 0), (PY_ROTATION, 4));
+        assert_eq!(transform.rotation(NY_ROTATION, 14), (NY_ROTATION, 2));
+
+        // Sideways blocks keep their y rotation.
+        assert_eq!(turned(2, false).rotation(PX_ROTATION, 3), (NX_ROTATION, 3));
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::Utc;
+use specs::{Read, ReadExpect, ReadStorage, System, Write, WriteExpect};
+use voxelize::{
+    BlockUtils, ChunkStatus, ChunkUtils, Chunks, Clients, Event, Events, Vec3, VoxelAccess,
+    WorldConfig,
+};
+
+use crate::worlds::shared::{block_data::BlockData, components::PlayerComp};
+
+use super::voxel_changes::VoxelChanges;
+
+/// Keeps track of where the display blocks are and pushes their values to the clients
+/// that can see them, every minute and whenever a new one shows up.
+pub struct BlockDataSystem;
+
+impl<'a> System<'a> for BlockDataSystem {
+    type SystemData = (
+        ReadExpect<'a, Chunks>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Clients>,
+        ReadStorage<'a, PlayerComp>,
+        Read<'a, VoxelChanges>,
+        Write<'a, BlockData>,
+        WriteExpect<'a, Events>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (chunks, config, clients, players, changes, mut block_data, mut events) = data;
+
+        // Worlds without display blocks have nothing to look for.
+        if block_data.providers.is_empty() {
+            return;
+        }
+
+        block_data.visitors.extend(
+            clients
+                .values()
+                .filter_map(|client| players.get(client.entity)?.0.to_owned()),
+        );
+
+        let mut fresh = vec![];
+
+        for (voxel, raw) in changes.0.iter() {
+            let id = BlockUtils::extract_id(*raw);
+
+            if block_data.providers.contains_key(&id) {
+                block_data.voxels.insert(voxel.to_owned(), id);
+                fresh.push(voxel.to_owned());
+            } else {
+                block_data.voxels.remove(voxel);
+            }
+        }
+
+        block_data
+            .scanned
+            .retain(|coords| chunks.map.contains_key(coords));
+
+        let pending = chunks
+            .map
+            .iter()
+            .filter(|(coords, chunk)| {
+                matches!(chunk.status, ChunkStatus::Ready) && !block_data.scanned.contains(*coords)
+            })
+            .map(|(coords, _)| coords.to_owned())
+            .collect::<Vec<_>>();
+
+        for coords in pending {
+            if let Some(chunk) = chunks.raw(&coords) {
+                let Vec3(min_x, min_y, min_z) = chunk.min;
+                let Vec3(max_x, max_y, max_z) = chunk.max;
+
+                for vx in min_x..max_x {
+                    for vz in min_z..max_z {
+                        for vy in min_y..max_y {
+                            let id = chunk.get_voxel(vx, vy, vz);
+
+                            if block_data.providers.contains_key(&id) {
+                                block_data.voxels.insert(Vec3(vx, vy, vz), id);
+                                fresh.push(Vec3(vx, vy, vz));
+                            }
+                        }
+                    }
+                }
+            }
+
+            block_data.scanned.insert(coords);
+        }
+
+        let chunk_size = config.chunk_size;
+
+        block_data.voxels.retain(|Vec3(vx, vy, vz), _| {
+            chunks
+                .map
+                .contains_key(&ChunkUtils::map_voxel_to_chunk(*vx, *vy, *vz, chunk_size))
+        });
+
+        if block_data.last_refresh != Some(Utc::now().timestamp() / 60) {
+            block_data.refresh();
+            fresh = block_data.voxels.keys().cloned().collect();
+        }
+
+        for voxel in fresh {
+            if let Some(payload) = block_data.payload(&voxel) {
+                let Vec3(vx, vy, vz) = voxel;
+
+                events.dispatch(
+                    Event::new("block-data")
+                        .payload(payload)
+                        .location(ChunkUtils::map_voxel_to_chunk(vx, vy, vz, chunk_size))
+                        .build(),
+                );
+            }
+        }
+    }
+}
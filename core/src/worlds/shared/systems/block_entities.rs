@@ -0,0 +1,61 @@
+use specs::{Read, System, Write, WriteExpect};
+use voxelize::{BlockUtils, Event, Events};
+
+use crate::worlds::shared::block_entities::{BlockEntities, BlockEntityPayload};
+
+use super::voxel_changes::VoxelChanges;
+
+/// Creates and removes block entities as their blocks are placed and broken, broadcasts
+/// the changes and saves the touched chunks.
+pub struct BlockEntitiesSystem;
+
+impl<'a> System<'a> for BlockEntitiesSystem {
+    type SystemData = (
+        Read<'a, VoxelChanges>,
+        Write<'a, BlockEntities>,
+        WriteExpect<'a, Events>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (changes, mut block_entities, mut events) = data;
+
+        let mut changed = vec![];
+
+        for (voxel, raw) in changes.0.iter() {
+            let id = BlockUtils::extract_id(*raw);
+            let existing = block_entities.get(voxel).map(|entity| entity.id);
+
+            if existing == Some(id) {
+                continue;
+            }
+
+            let entity = if block_entities.is_tracked(id) {
+                block_entities.create(voxel, id).cloned()
+            } else if block_entities.remove(voxel).is_some() {
+                None
+            } else {
+                continue;
+            };
+
+            changed.push(BlockEntityPayload {
+                voxel: voxel.to_owned(),
+                entity,
+            });
+        }
+
+        for payload in changed {
+            let coords = block_entities.coords_of(&payload.voxel);
+
+            events.dispatch(
+                Event::new("block-entity")
+                    .payload(payload)
+                    .location(coords)
+                    .build(),
+            );
+        }
+
+        if !block_entities.dirty.is_empty() {
+            block_entities.save();
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use hashbrown::HashSet;
+use specs::{Join, ReadStorage, System, Write, WriteExpect};
+use voxelize::{ClientFilter, ClientFlag, CurrentChunkComp, Event, Events, IDComp, Vec2};
+
+use crate::worlds::shared::block_entities::BlockEntities;
+
+/// Clients get the block entities of every chunk within this many chunks of them.
+pub const BLOCK_ENTITY_SYNC_RADIUS: i32 = 8;
+
+/// Sends the block entities of the chunks around each client as they come into range,
+/// so sign text and link targets arrive together with the chunks themselves.
+pub struct BlockEntitiesSyncSystem;
+
+impl<'a> System<'a> for BlockEntitiesSyncSystem {
+    type SystemData = (
+        ReadStorage<'a, ClientFlag>,
+        ReadStorage<'a, IDComp>,
+        ReadStorage<'a, CurrentChunkComp>,
+        Write<'a, BlockEntities>,
+        WriteExpect<'a, Events>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (flags, ids, current_chunks, mut block_entities, mut events) = data;
+
+        let mut online = HashSet::new();
+
+        for (_, id, current_chunk) in (&flags, &ids, &current_chunks).join() {
+            online.insert(id.0.to_owned());
+
+            let Vec2(cx, cz) = current_chunk.coords;
+            let radius = BLOCK_ENTITY_SYNC_RADIUS;
+
+            let mut synced = block_entities.synced.remove(&id.0).unwrap_or_default();
+
+            // Chunks far out of range are forgotten, so coming back sends them again.
+            synced
+                .retain(|Vec2(x, z)| (x - cx).abs() <= radius + 2 && (z - cz).abs() <= radius + 2);
+
+            let mut entities = vec![];
+
+            for x in (cx - radius)..=(cx + radius) {
+                for z in (cz - radius)..=(cz + radius) {
+                    if synced.insert(Vec2(x, z)) {
+                        entities.extend(block_entities.in_chunk(&Vec2(x, z)).into_iter().cloned());
+                    }
+                }
+            }
+
+            block_entities.synced.insert(id.0.to_owned(), synced);
+
+            if !entities.is_empty() {
+                events.dispatch(
+                    Event::new("block-entities")
+                        .payload(entities)
+                        .filter(ClientFilter::Direct(id.0.to_owned()))
+                        .build(),
+                );
+            }
+        }
+
+        block_entities.synced.retain(|id, _| online.contains(id));
+    }
+}
@@ -0,0 +1,26 @@
+use specs::{Read, System, Write};
+
+use crate::worlds::shared::deltas::ChunkDeltas;
+
+use super::voxel_changes::VoxelChanges;
+
+/// Keeps every voxel change applied to the world, in worlds that regenerate their chunks.
+/// Edits from players and the changes the server makes itself, like sand falling, blocks
+/// breaking off and grass spreading, all go through the same update queue, so they're all
+/// put back once a chunk is generated again. Only changes that were actually applied are
+/// kept, so updates voxelize dropped or put off aren't saved until they land.
+pub struct ChunkDeltasSystem;
+
+impl<'a> System<'a> for ChunkDeltasSystem {
+    type SystemData = (Write<'a, ChunkDeltas>, Read<'a, VoxelChanges>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut deltas, changes) = data;
+
+        if !deltas.is_enabled() || changes.0.is_empty() {
+            return;
+        }
+
+        deltas.record(&changes.0);
+    }
+}
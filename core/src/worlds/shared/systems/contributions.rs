@@ -0,0 +1,69 @@
+use chrono::Utc;
+use log::info;
+use specs::{ReadExpect, System, Write, WriteExpect};
+use voxelize::{ChunkStatus, ChunkUtils, Chunks, Vec2, Vec3, WorldConfig};
+
+use crate::worlds::shared::contributions::ContributionWall;
+
+/// Rebuilds the contribution wall once a day if its calendar file changed, as soon as the
+/// chunks it stands in are ready.
+pub struct ContributionWallSystem;
+
+impl<'a> System<'a> for ContributionWallSystem {
+    type SystemData = (
+        ReadExpect<'a, WorldConfig>,
+        Write<'a, ContributionWall>,
+        WriteExpect<'a, Chunks>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (config, mut wall, mut chunks) = data;
+
+        if wall.path.is_none() {
+            return;
+        }
+
+        let today = Utc::now().with_timezone(&wall.timezone).date_naive();
+
+        if wall.checked == Some(today) {
+            return;
+        }
+
+        let (Vec3(min_x, min_y, min_z), Vec3(max_x, max_y, max_z)) = wall.bounds();
+        let min = ChunkUtils::map_voxel_to_chunk(min_x, min_y, min_z, config.chunk_size);
+        let max = ChunkUtils::map_voxel_to_chunk(max_x, max_y, max_z, config.chunk_size);
+
+        for cx in min.0..=max.0 {
+            for cz in min.1..=max.1 {
+                let ready = chunks
+                    .raw(&Vec2(cx, cz))
+                    .map(|chunk| matches!(chunk.status, ChunkStatus::Ready))
+                    .unwrap_or(false);
+
+                if !ready {
+                    return;
+                }
+            }
+        }
+
+        wall.checked = Some(today);
+
+        let modified = wall.modified();
+
+        if modified.is_some() && modified == wall.built_from {
+            return;
+        }
+
+        if let Some(calendar) = wall.load() {
+            let updates = wall.updates(&calendar);
+
+            info!("Updating {} blocks for Github contributions", updates.len());
+
+            for (voxel, raw) in updates {
+                chunks.update_voxel(&voxel, raw);
+            }
+
+            wall.built_from = modified;
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use hashbrown::HashSet;
+use specs::{Read, ReadExpect, System, Write, WriteExpect};
+use voxelize::{ChunkUtils, Chunks, Mesher, MessageType, Registry, Vec2, Vec3, WorldConfig};
+
+use crate::blocks::apply_light_tints;
+
+use super::voxel_changes::VoxelChanges;
+
+/// Chunks whose light has already been filtered through tinting blocks.
+#[derive(Default)]
+pub struct TintedChunks(pub HashSet<Vec2<i32>>);
+
+/// The chunk and the ones around it, which a relight or a tint can reach into.
+fn around(coords: Vec2<i32>) -> impl Iterator<Item = Vec2<i32>> {
+    let Vec2(cx, cz) = coords;
+    (-1..=1).flat_map(move |dx| (-1..=1).map(move |dz| Vec2(cx + dx, cz + dz)))
+}
+
+/// Voxelize floods light without knowing about tints, so this filters the torch light of
+/// every chunk once it is lit, and again after a voxel change relit it. Runs after the
+/// voxel changes system, so the relight doesn't wash the tint out again.
+pub struct LightTintSystem;
+
+impl<'a> System<'a> for LightTintSystem {
+    type SystemData = (
+        ReadExpect<'a, Registry>,
+        ReadExpect<'a, WorldConfig>,
+        Read<'a, VoxelChanges>,
+        Write<'a, TintedChunks>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, Mesher>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (registry, config, changes, mut tinted, mut chunks, mut mesher) = data;
+
+        tinted.0.retain(|coords| chunks.map.contains_key(coords));
+
+        // A change relights its chunk, and the relight spreads into the chunks around it
+        // while their own tints reach back in, so all of them are tinted again.
+        for (Vec3(vx, vy, vz), _) in changes.0.iter() {
+            let coords = ChunkUtils::map_voxel_to_chunk(*vx, *vy, *vz, config.chunk_size);
+
+            for coords in around(coords) {
+                tinted.0.remove(&coords);
+            }
+        }
+
+        let pending = chunks
+            .map
+            .keys()
+            .filter(|coords| chunks.is_chunk_ready(coords) && !tinted.0.contains(*coords))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // Tints reach into the neighboring chunks, so the ones around a newly lit chunk are
+        // tinted again to reach into it. Light that's already tinted is left as it is.
+        let pending = pending
+            .into_iter()
+            .flat_map(around)
+            .filter(|coords| chunks.is_chunk_ready(coords))
+            .collect::<HashSet<_>>();
+
+        let mut remesh = HashSet::new();
+
+        for coords in pending {
+            remesh.extend(apply_light_tints(
+                &mut chunks,
+                &coords,
+                config.chunk_size,
+                &registry,
+            ));
+
+            tinted.0.insert(coords);
+        }
+
+        if remesh.is_empty() {
+            return;
+        }
+
+        // Meshed again the way a voxel update would, so chunks sent later carry the tint, and
+        // sent to the clients that already have them.
+        let processes = remesh
+            .into_iter()
+            .filter(|coords| chunks.is_chunk_ready(coords))
+            .map(|coords| {
+                let space = chunks
+                    .make_space(&coords, config.max_light_level as usize)
+                    .needs_height_maps()
+                    .needs_voxels()
+                    .needs_lights()
+                    .build();
+
+                (chunks.raw(&coords).unwrap().to_owned(), space)
+            })
+            .collect::<Vec<_>>();
+
+        for (chunk, _) in processes.iter() {
+            chunks.add_chunk_to_send(&chunk.coords, &MessageType::Update, false);
+        }
+
+        mesher.process(processes, &MessageType::Update, &registry, &config);
+    }
+}
@@ -1,7 +1,16 @@
+mod block_data;
+mod block_entities;
+mod block_entities_sync;
+mod chunk_deltas;
+mod contributions;
+mod light_tint;
+mod random_tick;
 mod role_metadata;
 mod rotation_metadata;
+mod showcase;
 mod text_metadata;
 mod void_kill;
+mod voxel_changes;
 
 use specs::DispatcherBuilder;
 use voxelize::{
@@ -12,14 +21,26 @@ use voxelize::{
     TargetMetadataSystem, UpdateStatsSystem, WalkTowardsSystem, World,
 };
 
+use crate::worlds::shared::deltas::saves_deltas;
+
+pub use self::random_tick::RandomTickConfig;
+
 use self::{
-    role_metadata::ExtraPeerMetaSystem, rotation_metadata::RotationMetadataSystem,
+    block_data::BlockDataSystem, block_entities::BlockEntitiesSystem,
+    block_entities_sync::BlockEntitiesSyncSystem, chunk_deltas::ChunkDeltasSystem,
+    contributions::ContributionWallSystem, light_tint::LightTintSystem,
+    random_tick::RandomTickSystem, role_metadata::ExtraPeerMetaSystem,
+    rotation_metadata::RotationMetadataSystem, showcase::ProjectShowcaseSystem,
     text_metadata::TextMetadataSystem, void_kill::VoidKillSystem,
+    voxel_changes::VoxelChangesSystem,
 };
 
 pub fn setup_dispatcher(world: &mut World) {
-    world.set_dispatcher(|| {
-        DispatcherBuilder::new()
+    // Worlds that only keep the changes made to them regenerate their chunks instead.
+    let save_chunks = !saves_deltas(world);
+
+    world.set_dispatcher(move || {
+        let dispatcher = DispatcherBuilder::new()
             .with(VoidKillSystem, "void-kill", &[])
             .with(UpdateStatsSystem, "update-stats", &[])
             .with(EntityObserveSystem, "entity-observe", &[])
@@ -47,14 +68,42 @@ pub fn setup_dispatcher(world: &mut World) {
             .with(ExtraPeerMetaSystem, "peers-extra-meta", &[])
             .with(CurrentChunkSystem, "current-chunk", &[])
             .with(ChunkUpdatingSystem, "chunk-updating", &["current-chunk"])
+            .with(VoxelChangesSystem, "voxel-changes", &["chunk-updating"])
+            .with(ChunkDeltasSystem, "chunk-deltas", &["voxel-changes"])
+            .with(LightTintSystem, "light-tint", &["voxel-changes"])
+            .with(BlockDataSystem, "block-data", &["voxel-changes"])
+            .with(BlockEntitiesSystem, "block-entities", &["voxel-changes"])
+            .with(RandomTickSystem, "random-tick", &["chunk-updating"])
+            .with(
+                ContributionWallSystem,
+                "contribution-wall",
+                &["chunk-updating"],
+            )
+            .with(
+                ProjectShowcaseSystem,
+                "project-showcase",
+                &["chunk-updating"],
+            )
+            .with(
+                BlockEntitiesSyncSystem,
+                "block-entities-sync",
+                &["current-chunk", "block-entities"],
+            )
             .with(ChunkRequestsSystem, "chunk-requests", &["current-chunk"])
             .with(
                 ChunkGeneratingSystem,
                 "chunk-generation",
                 &["chunk-requests"],
             )
-            .with(ChunkSendingSystem, "chunk-sending", &["chunk-generation"])
-            .with(ChunkSavingSystem, "chunk-saving", &["chunk-generation"])
+            .with(ChunkSendingSystem, "chunk-sending", &["chunk-generation"]);
+
+        let dispatcher = if save_chunks {
+            dispatcher.with(ChunkSavingSystem, "chunk-saving", &["chunk-generation"])
+        } else {
+            dispatcher
+        };
+
+        dispatcher
             .with(PhysicsSystem, "physics", &["current-chunk", "update-stats"])
             .with(DataSavingSystem, "entities-saving", &["entities-meta"])
             .with(
@@ -70,7 +119,12 @@ pub fn setup_dispatcher(world: &mut World) {
             .with(
                 BroadcastSystem,
                 "broadcast",
-                &["chunk-sending", "entities-sending", "peers-sending"],
+                &[
+                    "chunk-sending",
+                    "entities-sending",
+                    "peers-sending",
+                    "voxel-changes",
+                ],
             )
             .with(
                 CleanupSystem,
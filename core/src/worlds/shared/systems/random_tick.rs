@@ -0,0 +1,68 @@
+use specs::{Read, ReadExpect, System, WriteExpect};
+use voxelize::{ChunkStatus, Chunks, Registry, Vec3, VoxelAccess};
+
+use crate::blocks::get_block_properties;
+
+/// How many random ticks each world gets. Insert a different one into a world's ECS to
+/// change how fast its plants grow.
+pub struct RandomTickConfig {
+    /// Number of voxels picked from each loaded chunk every tick.
+    pub per_chunk: usize,
+}
+
+impl Default for RandomTickConfig {
+    fn default() -> Self {
+        Self { per_chunk: 3 }
+    }
+}
+
+impl RandomTickConfig {
+    pub fn new(per_chunk: usize) -> Self {
+        Self { per_chunk }
+    }
+}
+
+pub struct RandomTickSystem;
+
+impl<'a> System<'a> for RandomTickSystem {
+    type SystemData = (
+        ReadExpect<'a, Registry>,
+        Read<'a, RandomTickConfig>,
+        WriteExpect<'a, Chunks>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (registry, config, mut chunks) = data;
+
+        if config.per_chunk == 0 {
+            return;
+        }
+
+        let mut updates = vec![];
+
+        for chunk in chunks.map.values() {
+            if !matches!(chunk.status, ChunkStatus::Ready) {
+                continue;
+            }
+
+            let Vec3(min_x, min_y, min_z) = chunk.min;
+            let Vec3(max_x, max_y, max_z) = chunk.max;
+
+            for _ in 0..config.per_chunk {
+                let vx = fastrand::i32(min_x..max_x);
+                let vy = fastrand::i32(min_y..max_y);
+                let vz = fastrand::i32(min_z..max_z);
+
+                let id = chunk.get_voxel(vx, vy, vz);
+
+                if let Some(random_tick) = get_block_properties(id).and_then(|p| p.random_tick) {
+                    updates.extend(random_tick(&Vec3(vx, vy, vz), &*chunks, &registry));
+                }
+            }
+        }
+
+        for (voxel, raw) in updates {
+            chunks.update_voxel(&voxel, raw);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use hashbrown::HashSet;
+use log::{info, warn};
+use nanoid::nanoid;
+use serde_json::json;
+use specs::{
+    Builder, Entities, Join, LazyUpdate, Read, ReadExpect, ReadStorage, System, Write, WriteExpect,
+};
+use voxelize::{
+    ChunkStatus, ChunkUtils, Chunks, CollisionsComp, CurrentChunkComp, ETypeComp, EntityFlag,
+    Event, Events, IDComp, MetadataComp, PositionComp, Registry, Vec3, WorldConfig,
+};
+
+use crate::worlds::shared::{
+    block_entities::{BlockEntities, BlockEntity, BlockEntityPayload},
+    components::TextComp,
+    showcase::{showcase_slots, ProjectShowcase, SHOWCASE_LINK_ID},
+};
+
+/// Rebuilds the project showcase row whenever the projects manifest changes.
+pub struct ProjectShowcaseSystem;
+
+impl<'a> System<'a> for ProjectShowcaseSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Registry>,
+        ReadExpect<'a, WorldConfig>,
+        Read<'a, LazyUpdate>,
+        ReadStorage<'a, ETypeComp>,
+        ReadStorage<'a, PositionComp>,
+        Write<'a, ProjectShowcase>,
+        Write<'a, BlockEntities>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, Events>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            registry,
+            config,
+            lazy,
+            etypes,
+            positions,
+            mut showcase,
+            mut block_entities,
+            mut chunks,
+            mut events,
+        ) = data;
+
+        if showcase.path.is_none() || !showcase.due() {
+            return;
+        }
+
+        let modified = showcase.modified();
+
+        if modified.is_some() && modified == showcase.built_from {
+            return;
+        }
+
+        let manifest = match showcase.load() {
+            Some(manifest) => manifest,
+            None => return,
+        };
+
+        let slots = showcase_slots(&manifest);
+
+        let ready = slots.iter().all(|slot| {
+            let Vec3(vx, vy, vz) = slot.trophy;
+
+            chunks
+                .raw(&ChunkUtils::map_voxel_to_chunk(
+                    vx,
+                    vy,
+                    vz,
+                    config.chunk_size,
+                ))
+                .map(|chunk| matches!(chunk.status, ChunkStatus::Ready))
+                .unwrap_or(false)
+        });
+
+        // Checked again once the interval passes.
+        if !ready {
+            return;
+        }
+
+        let previous = showcase.manifest.take();
+        let previous_slots = previous.as_ref().map(showcase_slots).unwrap_or_default();
+
+        let voxels = slots
+            .iter()
+            .flat_map(|slot| [slot.trophy.to_owned(), slot.link.to_owned()])
+            .collect::<HashSet<_>>();
+
+        for slot in previous_slots.iter() {
+            for voxel in [&slot.trophy, &slot.link] {
+                if !voxels.contains(voxel) {
+                    chunks.update_voxel(voxel, 0);
+                }
+            }
+        }
+
+        // Captions are saved with the world, so the ones from before a restart are found
+        // by where they float.
+        let captions = previous_slots
+            .iter()
+            .chain(slots.iter())
+            .map(|slot| slot.caption.to_owned())
+            .collect::<Vec<_>>();
+
+        for (entity, etype, position) in (&entities, &etypes, &positions).join() {
+            if etype.0 != "floating-text" {
+                continue;
+            }
+
+            let Vec3(px, py, pz) = position.0;
+
+            if captions.iter().any(|Vec3(cx, cy, cz)| {
+                (px - cx).abs() < 0.5 && (py - cy).abs() < 0.5 && (pz - cz).abs() < 0.5
+            }) {
+                entities.delete(entity).ok();
+            }
+        }
+
+        for slot in slots.iter() {
+            let project = slot.project;
+
+            if registry.blocks_by_id.contains_key(&project.trophy) {
+                chunks.update_voxel(&slot.trophy, project.trophy);
+            } else {
+                warn!(
+                    "Trophy {} of {} is not registered yet, restart the server to show it.",
+                    project.trophy, project.name
+                );
+            }
+
+            let entity = BlockEntity {
+                voxel: slot.link.to_owned(),
+                id: SHOWCASE_LINK_ID,
+                data: json!({ "link": project.link, "caption": project.name }),
+            };
+
+            block_entities.set(entity.to_owned());
+            chunks.update_voxel(&slot.link, SHOWCASE_LINK_ID);
+
+            events.dispatch(
+                Event::new("block-entity")
+                    .payload(BlockEntityPayload {
+                        voxel: slot.link.to_owned(),
+                        entity: Some(entity),
+                    })
+                    .location(block_entities.coords_of(&slot.link))
+                    .build(),
+            );
+
+            let Vec3(cx, cy, cz) = slot.caption;
+
+            lazy.create_entity(&entities)
+                .with(IDComp::new(&nanoid!()))
+                .with(EntityFlag)
+                .with(ETypeComp::new("floating-text", false))
+                .with(MetadataComp::new())
+                .with(CurrentChunkComp::default())
+                .with(CollisionsComp::new())
+                .with(PositionComp::new(cx, cy, cz))
+                .with(TextComp::new(&project.caption()))
+                .build();
+        }
+
+        info!("Built the showcase for {} projects", slots.len());
+
+        showcase.manifest = Some(manifest);
+        showcase.built_from = modified;
+    }
+}
@@ -0,0 +1,36 @@
+use specs::{ReadExpect, System, Write};
+use voxelize::{MessageQueue, MessageType, Vec3};
+
+/// The voxels the chunk updating system set this tick, with the raw value each was set to.
+/// Updates it dropped or put off for a later tick aren't in here.
+#[derive(Default)]
+pub struct VoxelChanges(pub Vec<(Vec3<i32>, u32)>);
+
+/// Voxelize keeps its update queue to itself, but it tells every client which voxels it set
+/// with an `Update` message. This reads them back out of the message queue before they're
+/// broadcast, so the systems after it can react to every change to the world, whoever made
+/// it. Runs after the chunk updating system and before the broadcast system.
+pub struct VoxelChangesSystem;
+
+impl<'a> System<'a> for VoxelChangesSystem {
+    type SystemData = (ReadExpect<'a, MessageQueue>, Write<'a, VoxelChanges>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (queue, mut changes) = data;
+
+        changes.0.clear();
+
+        for (message, _) in queue.iter() {
+            if message.r#type != MessageType::Update as i32 {
+                continue;
+            }
+
+            changes.0.extend(
+                message
+                    .updates
+                    .iter()
+                    .map(|update| (Vec3(update.vx, update.vy, update.vz), update.voxel)),
+            );
+        }
+    }
+}
@@ -0,0 +1,763 @@
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+
+use hashbrown::HashMap;
+use log::warn;
+use serde::Deserialize;
+use voxelize::{Registry, Vec3};
+
+use super::structures::{Structure, StructureStage, StructureTransform, StructureVoxel};
+
+/// A 3x3 rotation made of 0s, 1s and -1s, how MagicaVoxel turns its models.
+type Rotation = [[i32; 3]; 3];
+
+const IDENTITY: Rotation = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+
+struct VoxModel {
+    size: [i32; 3],
+    voxels: Vec<([i32; 3], u8)>,
+}
+
+enum VoxNode {
+    Transform {
+        child: i32,
+        rotation: Rotation,
+        translation: [i32; 3],
+    },
+    Group {
+        children: Vec<i32>,
+    },
+    Shape {
+        models: Vec<i32>,
+    },
+}
+
+/// The parts of a `.vox` file the importer needs: every model, the scene placing them and
+/// the palette. Colour index `i` of a voxel is `palette[i - 1]`. Files without a palette
+/// of their own use MagicaVoxel's default one.
+struct VoxFile {
+    models: Vec<VoxModel>,
+    nodes: HashMap<i32, VoxNode>,
+    palette: Vec<[u8; 3]>,
+}
+
+/// MagicaVoxel's default palette, laid out like a file's own with colour index `i` at
+/// `i - 1`. It's every mix of six levels of red, green and blue from white down, leaving
+/// out black, then ramps of red, green, blue and gray in ten steps each.
+fn default_palette() -> Vec<[u8; 3]> {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut palette = vec![];
+
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                palette.push([r, g, b]);
+            }
+        }
+    }
+
+    // Black isn't one of the colours.
+    palette.pop();
+
+    palette.extend(RAMP.map(|v| [v, 0, 0]));
+    palette.extend(RAMP.map(|v| [0, v, 0]));
+    palette.extend(RAMP.map(|v| [0, 0, v]));
+    palette.extend(RAMP.map(|v| [v, v, v]));
+
+    // The last slot of a palette is never used by any voxel.
+    palette.push([0, 0, 0]);
+
+    palette
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, at: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.at >= self.bytes.len()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if count > self.bytes.len() - self.at {
+            return Err("unexpected end of file".to_owned());
+        }
+
+        let taken = &self.bytes[self.at..self.at + count];
+        self.at += count;
+
+        Ok(taken)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.i32()?.max(0) as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, String> {
+        let count = self.i32()?;
+        let mut dict = HashMap::new();
+
+        for _ in 0..count {
+            let key = self.string()?;
+            let value = self.string()?;
+            dict.insert(key, value);
+        }
+
+        Ok(dict)
+    }
+}
+
+/// Unpack the byte MagicaVoxel stores a rotation in. The low bits give the column of the
+/// non-zero entry in the first two rows, the high bits whether each row's entry is negative.
+fn decode_rotation(packed: u8) -> Rotation {
+    let first = (packed & 3) as usize;
+    let second = ((packed >> 2) & 3) as usize;
+    let third = 3usize.saturating_sub(first + second);
+
+    let mut rotation = [[0; 3]; 3];
+
+    for (row, column) in [first, second, third].into_iter().enumerate() {
+        rotation[row][column.min(2)] = if (packed >> (4 + row)) & 1 == 1 {
+            -1
+        } else {
+            1
+        };
+    }
+
+    rotation
+}
+
+fn rotate(rotation: &Rotation, v: [i32; 3]) -> [i32; 3] {
+    let mut out = [0; 3];
+
+    for (row, entries) in rotation.iter().enumerate() {
+        out[row] = entries[0] * v[0] + entries[1] * v[1] + entries[2] * v[2];
+    }
+
+    out
+}
+
+fn compose(outer: &Rotation, inner: &Rotation) -> Rotation {
+    let mut out = [[0; 3]; 3];
+
+    for row in 0..3 {
+        for column in 0..3 {
+            out[row][column] = (0..3).map(|k| outer[row][k] * inner[k][column]).sum();
+        }
+    }
+
+    out
+}
+
+fn parse_vox(bytes: &[u8]) -> Result<VoxFile, String> {
+    let mut reader = Reader::new(bytes);
+
+    if reader.take(4)? != b"VOX " {
+        return Err("not a .vox file".to_owned());
+    }
+
+    reader.i32()?;
+
+    if reader.take(4)? != b"MAIN" {
+        return Err("missing MAIN chunk".to_owned());
+    }
+
+    let content = reader.i32()?.max(0) as usize;
+    reader.i32()?;
+    reader.take(content)?;
+
+    let mut file = VoxFile {
+        models: vec![],
+        nodes: HashMap::new(),
+        palette: default_palette(),
+    };
+    let mut size = None;
+
+    while !reader.is_empty() {
+        let id = reader.take(4)?;
+        let content = reader.i32()?.max(0) as usize;
+        let children = reader.i32()?.max(0) as usize;
+        let mut chunk = Reader::new(reader.take(content)?);
+        reader.take(children)?;
+
+        match id {
+            b"SIZE" => size = Some([chunk.i32()?, chunk.i32()?, chunk.i32()?]),
+            b"XYZI" => {
+                let size = size.take().ok_or("XYZI chunk without a SIZE chunk")?;
+                let count = chunk.i32()?.max(0);
+                let mut voxels = vec![];
+
+                for _ in 0..count {
+                    let voxel = chunk.take(4)?;
+                    voxels.push((
+                        [voxel[0] as i32, voxel[1] as i32, voxel[2] as i32],
+                        voxel[3],
+                    ));
+                }
+
+                file.models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                let mut palette = vec![];
+
+                for _ in 0..256 {
+                    let color = chunk.take(4)?;
+                    palette.push([color[0], color[1], color[2]]);
+                }
+
+                file.palette = palette;
+            }
+            b"nTRN" => {
+                let node = chunk.i32()?;
+                chunk.dict()?;
+                let child = chunk.i32()?;
+                chunk.i32()?;
+                chunk.i32()?;
+
+                let frames = chunk.i32()?;
+                let frame = if frames > 0 {
+                    chunk.dict()?
+                } else {
+                    HashMap::new()
+                };
+
+                let rotation = frame
+                    .get("_r")
+                    .and_then(|r| r.parse::<u8>().ok())
+                    .map(decode_rotation)
+                    .unwrap_or(IDENTITY);
+
+                let mut translation = [0; 3];
+
+                if let Some(t) = frame.get("_t") {
+                    for (axis, value) in t.split_whitespace().take(3).enumerate() {
+                        translation[axis] = value.parse().unwrap_or(0);
+                    }
+                }
+
+                file.nodes.insert(
+                    node,
+                    VoxNode::Transform {
+                        child,
+                        rotation,
+                        translation,
+                    },
+                );
+            }
+            b"nGRP" => {
+                let node = chunk.i32()?;
+                chunk.dict()?;
+
+                let count = chunk.i32()?;
+                let mut children = vec![];
+
+                for _ in 0..count {
+                    children.push(chunk.i32()?);
+                }
+
+                file.nodes.insert(node, VoxNode::Group { children });
+            }
+            b"nSHP" => {
+                let node = chunk.i32()?;
+                chunk.dict()?;
+
+                let count = chunk.i32()?;
+                let mut models = vec![];
+
+                for _ in 0..count {
+                    models.push(chunk.i32()?);
+                    chunk.dict()?;
+                }
+
+                file.nodes.insert(node, VoxNode::Shape { models });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(file)
+}
+
+impl VoxFile {
+    /// Every voxel of the scene in MagicaVoxel's coordinates, where z is up, with its
+    /// colour index. Files without a scene graph just have their models on top of each other.
+    fn voxels(&self) -> Vec<([i32; 3], u8)> {
+        let mut voxels = vec![];
+
+        if self.nodes.contains_key(&0) {
+            self.walk(0, &IDENTITY, [0; 3], &mut voxels, 0);
+        } else {
+            for model in self.models.iter() {
+                voxels.extend(model.voxels.iter().cloned());
+            }
+        }
+
+        voxels
+    }
+
+    fn walk(
+        &self,
+        node: i32,
+        rotation: &Rotation,
+        translation: [i32; 3],
+        voxels: &mut Vec<([i32; 3], u8)>,
+        depth: usize,
+    ) {
+        // Guards against scenes that loop back on themselves.
+        if depth > 64 {
+            return;
+        }
+
+        match self.nodes.get(&node) {
+            Some(VoxNode::Transform {
+                child,
+                rotation: own_rotation,
+                translation: own_translation,
+            }) => {
+                let offset = rotate(rotation, *own_translation);
+                let translation = [
+                    translation[0] + offset[0],
+                    translation[1] + offset[1],
+                    translation[2] + offset[2],
+                ];
+
+                self.walk(
+                    *child,
+                    &compose(rotation, own_rotation),
+                    translation,
+                    voxels,
+                    depth + 1,
+                );
+            }
+            Some(VoxNode::Group { children }) => {
+                for child in children {
+                    self.walk(*child, rotation, translation, voxels, depth + 1);
+                }
+            }
+            Some(VoxNode::Shape { models }) => {
+                for model in models.iter().filter_map(|id| self.models.get(*id as usize)) {
+                    let half = [model.size[0] / 2, model.size[1] / 2, model.size[2] / 2];
+
+                    for (voxel, color) in model.voxels.iter() {
+                        let centered = [voxel[0] - half[0], voxel[1] - half[1], voxel[2] - half[2]];
+                        let turned = rotate(rotation, centered);
+
+                        voxels.push((
+                            [
+                                turned[0] + translation[0],
+                                turned[1] + translation[1],
+                                turned[2] + translation[2],
+                            ],
+                            *color,
+                        ));
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim_start_matches('#');
+
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// The colour each block is matched by when a `.vox` file is imported, read from
+/// `colors.json` in the vox folder as `{ "Block Name": "#rrggbb" }`.
+#[derive(Default)]
+pub struct ColorTable {
+    pub colors: Vec<(String, [u8; 3])>,
+}
+
+impl ColorTable {
+    /// Load the table, leaving out blocks the registry doesn't have.
+    pub fn load(path: &Path, registry: &Registry) -> Self {
+        let table = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                serde_json::from_reader::<_, HashMap<String, String>>(file)
+                    .map_err(|e| e.to_string())
+            });
+
+        let table = match table {
+            Ok(table) => table,
+            Err(e) => {
+                warn!("Failed to load the vox colour table from {:?}: {}", path, e);
+                return Self::default();
+            }
+        };
+
+        let mut colors = vec![];
+
+        for (name, color) in table {
+            if !registry.blocks_by_name.contains_key(&name.to_lowercase()) {
+                warn!("Vox colour table lists unknown block \"{}\".", name);
+                continue;
+            }
+
+            match parse_color(&color) {
+                Some(color) => colors.push((name, color)),
+                None => warn!("Vox colour table has a bad colour for \"{}\".", name),
+            }
+        }
+
+        colors.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Self { colors }
+    }
+
+    /// The block whose colour is closest to `color`.
+    pub fn nearest(&self, color: &[u8; 3]) -> Option<&str> {
+        self.colors
+            .iter()
+            .min_by_key(|(_, other)| {
+                (0..3)
+                    .map(|i| (color[i] as i32 - other[i] as i32).pow(2))
+                    .sum::<i32>()
+            })
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// Pick the block for every colour index a file uses. `mapping` comes first, keyed by the
+/// colour index or `#rrggbb`, and maps to a block name or to "Air" to leave the voxel out.
+/// Everything else goes to the nearest colour in `table`.
+fn map_palette(
+    file: &VoxFile,
+    used: &[u8],
+    mapping: &HashMap<String, String>,
+    table: &ColorTable,
+) -> Result<HashMap<u8, Option<String>>, String> {
+    let mut blocks = HashMap::new();
+
+    for &index in used {
+        let color = file.palette.get(index as usize - 1).cloned();
+        let hex = color.map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b));
+
+        let block = mapping
+            .get(&index.to_string())
+            .or_else(|| hex.as_ref().and_then(|hex| mapping.get(hex)))
+            .map(|name| name.to_owned())
+            .or_else(|| color.and_then(|color| table.nearest(&color).map(|name| name.to_owned())));
+
+        match block {
+            Some(name) if name.eq_ignore_ascii_case("air") => blocks.insert(index, None),
+            Some(name) => blocks.insert(index, Some(name)),
+            None => return Err(format!("no block for colour index {}", index)),
+        };
+    }
+
+    Ok(blocks)
+}
+
+/// Turn a `.vox` file into a structure, with the voxels of every model in its scene. A
+/// `{name}.mapping.json` next to the file picks blocks for its colours explicitly.
+pub fn import_vox(
+    path: &Path,
+    registry: &Registry,
+    table: &ColorTable,
+) -> Result<Structure, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let file = parse_vox(&bytes)?;
+
+    let mapping_path = path.with_extension("mapping.json");
+    let mapping = if mapping_path.exists() {
+        let mapping = File::open(&mapping_path).map_err(|e| e.to_string())?;
+        serde_json::from_reader(mapping).map_err(|e| e.to_string())?
+    } else {
+        HashMap::new()
+    };
+
+    // MagicaVoxel has z going up, turn it into y going up without mirroring the model.
+    let voxels = file
+        .voxels()
+        .into_iter()
+        .filter(|(_, color)| *color != 0)
+        .map(|([x, y, z], color)| ([x, z, -y], color))
+        .collect::<Vec<_>>();
+
+    if voxels.is_empty() {
+        return Err("the file has no voxels".to_owned());
+    }
+
+    let mut used = voxels.iter().map(|(_, color)| *color).collect::<Vec<_>>();
+    used.sort_unstable();
+    used.dedup();
+
+    let blocks = map_palette(&file, &used, &mapping, table)?;
+
+    let mut min = [i32::MAX; 3];
+    let mut max = [i32::MIN; 3];
+
+    for (voxel, _) in voxels.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(voxel[axis]);
+            max[axis] = max[axis].max(voxel[axis]);
+        }
+    }
+
+    let mut structure = Structure {
+        size: Vec3(
+            max[0] - min[0] + 1,
+            max[1] - min[1] + 1,
+            max[2] - min[2] + 1,
+        ),
+        ..Default::default()
+    };
+    let mut palette: HashMap<String, usize> = HashMap::new();
+
+    for (voxel, color) in voxels {
+        let name = match blocks.get(&color) {
+            Some(Some(name)) => name,
+            _ => continue,
+        };
+
+        let id = match registry.blocks_by_name.get(&name.to_lowercase()) {
+            Some(block) => block.id,
+            None => return Err(format!("no block named \"{}\"", name)),
+        };
+
+        let block = *palette.entry(name.to_lowercase()).or_insert_with(|| {
+            structure
+                .palette
+                .push(registry.get_block_by_id(id).name.to_owned());
+            structure.palette.len() - 1
+        });
+
+        structure.voxels.push(StructureVoxel {
+            offset: Vec3(voxel[0] - min[0], voxel[1] - min[1], voxel[2] - min[2]),
+            block,
+            rotation: 0,
+            y_rotation: 0,
+            stage: 0,
+        });
+    }
+
+    Ok(structure)
+}
+
+/// A `.vox` file placed in a world when its chunks generate.
+#[derive(Deserialize, Debug, Clone)]
+pub struct VoxPlacement {
+    /// Path of the file, relative to the vox folder.
+    pub file: String,
+    pub origin: Vec3<i32>,
+    #[serde(flatten)]
+    pub transform: StructureTransform,
+}
+
+/// A stage that places every file listed in `placements.json` in the vox folder.
+pub fn vox_stage(folder: &Path, registry: &Registry) -> StructureStage {
+    let mut stage = StructureStage::new();
+    let path = folder.join("placements.json");
+
+    if !path.exists() {
+        return stage;
+    }
+
+    let placements = File::open(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|file| {
+            serde_json::from_reader::<_, Vec<VoxPlacement>>(file).map_err(|e| e.to_string())
+        });
+
+    let placements = match placements {
+        Ok(placements) => placements,
+        Err(e) => {
+            warn!("Failed to load vox placements from {:?}: {}", path, e);
+            return stage;
+        }
+    };
+
+    let table = ColorTable::load(&folder.join("colors.json"), registry);
+
+    for placement in placements {
+        match import_vox(&folder.join(&placement.file), registry, &table) {
+            Ok(structure) => {
+                stage = stage.place(structure, placement.origin, placement.transform);
+            }
+            Err(e) => warn!("Failed to import {}: {}", placement.file, e),
+        }
+    }
+
+    stage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(value: i32) -> Vec<u8> {
+        value.to_le_bytes().to_vec()
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        [int(value.len() as i32), value.as_bytes().to_vec()].concat()
+    }
+
+    fn dict(pairs: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = int(pairs.len() as i32);
+
+        for (key, value) in pairs {
+            bytes.extend(string(key));
+            bytes.extend(string(value));
+        }
+
+        bytes
+    }
+
+    fn chunk(id: &[u8; 4], content: Vec<u8>) -> Vec<u8> {
+        [id.to_vec(), int(content.len() as i32), int(0), content].concat()
+    }
+
+    fn file(chunks: Vec<Vec<u8>>) -> Vec<u8> {
+        let children = chunks.concat();
+
+        [
+            b"VOX ".to_vec(),
+            int(150),
+            b"MAIN".to_vec(),
+            int(0),
+            int(children.len() as i32),
+            children,
+        ]
+        .concat()
+    }
+
+    /// A 2x2x2 model with the given voxels.
+    fn model(voxels: &[([u8; 3], u8)]) -> Vec<Vec<u8>> {
+        let mut xyzi = int(voxels.len() as i32);
+
+        for ([x, y, z], color) in voxels {
+            xyzi.extend([*x, *y, *z, *color]);
+        }
+
+        vec![
+            chunk(b"SIZE", [int(2), int(2), int(2)].concat()),
+            chunk(b"XYZI", xyzi),
+        ]
+    }
+
+    #[test]
+    fn reads_the_voxels_of_its_models() {
+        let bytes = file(model(&[([0, 1, 0], 3), ([1, 1, 1], 9)]));
+        let file = parse_vox(&bytes).unwrap();
+
+        assert_eq!(file.models.len(), 1);
+        assert_eq!(file.models[0].size, [2, 2, 2]);
+        assert_eq!(file.voxels(), vec![([0, 1, 0], 3), ([1, 1, 1], 9)]);
+    }
+
+    #[test]
+    fn falls_back_to_the_default_palette() {
+        let file = parse_vox(&file(model(&[([0, 0, 0], 1)]))).unwrap();
+
+        assert_eq!(file.palette.len(), 256);
+        assert_eq!(file.palette[0], [0xff, 0xff, 0xff]);
+        assert_eq!(file.palette[1], [0xff, 0xff, 0xcc]);
+        assert_eq!(file.palette[6], [0xff, 0xcc, 0xff]);
+        assert_eq!(file.palette[214], [0x00, 0x00, 0x33]);
+        assert_eq!(file.palette[215], [0xee, 0x00, 0x00]);
+        assert_eq!(file.palette[225], [0x00, 0xee, 0x00]);
+        assert_eq!(file.palette[235], [0x00, 0x00, 0xee]);
+        assert_eq!(file.palette[254], [0x11, 0x11, 0x11]);
+    }
+
+    #[test]
+    fn reads_its_own_palette() {
+        let rgba = (0..256)
+            .flat_map(|i| [i as u8, 255 - i as u8, 7, 255])
+            .collect::<Vec<_>>();
+
+        let mut chunks = model(&[([0, 0, 0], 1)]);
+        chunks.push(chunk(b"RGBA", rgba));
+
+        let file = parse_vox(&file(chunks)).unwrap();
+
+        assert_eq!(file.palette.len(), 256);
+        assert_eq!(file.palette[0], [0, 255, 7]);
+        assert_eq!(file.palette[200], [200, 55, 7]);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        assert!(parse_vox(b"NOPE").is_err());
+        assert!(parse_vox(b"VOX ").is_err());
+
+        // Says it has more voxels than it does.
+        let bytes = file(vec![
+            chunk(b"SIZE", [int(2), int(2), int(2)].concat()),
+            chunk(b"XYZI", [int(5), vec![0, 0, 0, 1]].concat()),
+        ]);
+        assert!(parse_vox(&bytes).is_err());
+
+        // Voxels without a size.
+        let bytes = file(vec![chunk(b"XYZI", [int(1), vec![0, 0, 0, 1]].concat())]);
+        assert!(parse_vox(&bytes).is_err());
+    }
+
+    #[test]
+    fn places_models_where_the_scene_puts_them() {
+        let mut chunks = model(&[([1, 1, 1], 4), ([0, 1, 1], 5)]);
+
+        chunks.push(chunk(
+            b"nTRN",
+            [
+                int(0),
+                dict(&[]),
+                int(1),
+                int(-1),
+                int(0),
+                int(1),
+                dict(&[("_t", "10 0 5")]),
+            ]
+            .concat(),
+        ));
+        chunks.push(chunk(
+            b"nSHP",
+            [int(1), dict(&[]), int(1), int(0), dict(&[])].concat(),
+        ));
+
+        let file = parse_vox(&file(chunks)).unwrap();
+
+        // Models are centered on their node before they're moved.
+        assert_eq!(file.voxels(), vec![([10, 0, 5], 4), ([9, 0, 5], 5)]);
+    }
+
+    #[test]
+    fn decodes_rotations() {
+        assert_eq!(decode_rotation(0b0000_0100), IDENTITY);
+        assert_eq!(
+            decode_rotation(0b0001_0100),
+            [[-1, 0, 0], [0, 1, 0], [0, 0, 1]]
+        );
+        assert_eq!(
+            decode_rotation(0b0000_0001),
+            [[0, 1, 0], [1, 0, 0], [0, 0, 1]]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use hashbrown::HashMap;
+use voxelize::{Registry, Terrain};
+
+/// Where each biome sits in the noise space of the terrain layers, as
+/// `[continentalness, peaks and valleys, erosion]`. A column gets the biome whose point is
+/// closest to its noise values.
+pub const BIOME_POINTS: [([f64; 3], &str); 21] = [
+    ([0.0, 0.0, 0.0], "Plains"),
+    ([0.0, 0.0, 0.2], "Plains"),
+    ([0.0, 0.2, 0.0], "Forest"),
+    ([0.0, 0.2, 0.2], "Forest"),
+    ([0.2, 0.0, 0.0], "Beach"),
+    ([0.2, 0.0, 0.2], "Beach"),
+    ([0.2, 0.2, 0.0], "Plains"),
+    ([0.2, 0.2, 0.2], "Beach"),
+    ([0.0, 0.0, -0.2], "Forest"),
+    ([0.0, -0.2, 0.0], "Plains"),
+    ([0.0, -0.2, -0.2], "Desert"),
+    ([-0.2, 0.0, 0.0], "Forest"),
+    ([-0.2, 0.0, -0.2], "Tundra"),
+    ([-0.2, -0.2, 0.0], "Desert"),
+    ([-0.2, -0.2, -0.2], "Desert"),
+    ([0.2, 0.2, -0.2], "Plains"),
+    ([0.2, -0.2, 0.2], "Beach"),
+    ([0.2, -0.2, -0.2], "Desert"),
+    ([-0.2, 0.2, 0.2], "Forest"),
+    ([-0.2, 0.2, -0.2], "Tundra"),
+    ([-0.2, -0.2, 0.2], "Plains"),
+];
+
+/// What the soil, plants and trees of a biome look like.
+#[derive(Clone, Debug)]
+pub struct BiomeDefinition {
+    pub name: String,
+    /// The top block of every column above water.
+    pub surface: u32,
+    /// The blocks right under the surface.
+    pub subsurface: u32,
+    pub subsurface_depth: i32,
+    /// Tree types from the tree stage that grow here, with the share of their usual spots
+    /// they actually grow on.
+    pub trees: Vec<(String, f64)>,
+    /// Chance of a column's surface getting a grass plant.
+    pub grass_density: f64,
+    pub flowers: Vec<u32>,
+    /// Chance of a column's surface getting one of the flowers.
+    pub flower_density: f64,
+    /// Height above which the surface turns to snow, as a share of the world's max height.
+    pub snow_line: f64,
+}
+
+impl BiomeDefinition {
+    pub fn new(name: &str, surface: u32, subsurface: u32) -> Self {
+        Self {
+            name: name.to_owned(),
+            surface,
+            subsurface,
+            subsurface_depth: 2,
+            trees: vec![],
+            grass_density: 0.0,
+            flowers: vec![],
+            flower_density: 0.0,
+            snow_line: 0.6,
+        }
+    }
+
+    pub fn subsurface_depth(mut self, depth: i32) -> Self {
+        self.subsurface_depth = depth;
+        self
+    }
+
+    pub fn tree(mut self, tree_type: &str, density: f64) -> Self {
+        self.trees.push((tree_type.to_owned(), density));
+        self
+    }
+
+    pub fn grass(mut self, density: f64) -> Self {
+        self.grass_density = density;
+        self
+    }
+
+    pub fn flowers(mut self, flowers: &[u32], density: f64) -> Self {
+        self.flowers = flowers.to_vec();
+        self.flower_density = density;
+        self
+    }
+
+    pub fn snow_line(mut self, snow_line: f64) -> Self {
+        self.snow_line = snow_line;
+        self
+    }
+
+    /// How densely a tree type grows here, 0 if it doesn't.
+    pub fn tree_density(&self, tree_type: &str) -> f64 {
+        self.trees
+            .iter()
+            .find(|(name, _)| name == tree_type)
+            .map(|(_, density)| *density)
+            .unwrap_or(0.0)
+    }
+}
+
+/// The definitions of every biome the terrain can pick, looked up by name.
+#[derive(Clone, Debug)]
+pub struct Biomes {
+    definitions: HashMap<String, BiomeDefinition>,
+    /// Used for a biome name without a definition.
+    fallback: BiomeDefinition,
+}
+
+impl Biomes {
+    pub fn new(fallback: BiomeDefinition) -> Self {
+        Self {
+            definitions: HashMap::new(),
+            fallback,
+        }
+    }
+
+    pub fn with(mut self, definition: BiomeDefinition) -> Self {
+        self.definitions
+            .insert(definition.name.to_owned(), definition);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> &BiomeDefinition {
+        self.definitions.get(name).unwrap_or(&self.fallback)
+    }
+
+    /// The densest a tree type grows in any biome.
+    pub fn max_tree_density(&self, tree_type: &str) -> f64 {
+        self.definitions
+            .values()
+            .chain([&self.fallback])
+            .map(|definition| definition.tree_density(tree_type))
+            .fold(0.0, f64::max)
+    }
+
+    /// The biome of a column, looked up at the height of its surface.
+    pub fn at(&self, terrain: &Terrain, vx: i32, vy: i32, vz: i32) -> &BiomeDefinition {
+        self.get(&terrain.get_biome_at(vx, vy, vz).name)
+    }
+}
+
+/// The biomes of the terrain world.
+pub fn terrain_biomes(registry: &Registry) -> Biomes {
+    let id = |name: &str| registry.get_block_by_name(name).id;
+
+    let plains = BiomeDefinition::new("Plains", id("Grass Block"), id("Dirt"))
+        .subsurface_depth(3)
+        .tree("Oak", 0.15)
+        .tree("Boulder", 0.3)
+        .grass(0.12)
+        .flowers(&[id("Mushroom")], 0.005);
+
+    Biomes::new(plains.to_owned())
+        .with(plains)
+        .with(
+            BiomeDefinition::new("Forest", id("Grass Block"), id("Dirt"))
+                .subsurface_depth(3)
+                .tree("Oak", 1.0)
+                .tree("Mystical", 0.5)
+                .tree("Boulder", 0.2)
+                .grass(0.3)
+                .flowers(&[id("Mushroom")], 0.02),
+        )
+        .with(
+            BiomeDefinition::new("Desert", id("Sand"), id("Sand"))
+                .subsurface_depth(4)
+                .tree("Boulder", 0.15)
+                .snow_line(1.1),
+        )
+        .with(
+            BiomeDefinition::new("Tundra", id("Snow"), id("Dirt"))
+                .tree("Boulder", 0.5)
+                .tree("Oak", 0.05)
+                .snow_line(0.35),
+        )
+        .with(
+            BiomeDefinition::new("Beach", id("Sand"), id("Sand"))
+                .subsurface_depth(3)
+                .tree("Tiny", 1.0),
+        )
+}
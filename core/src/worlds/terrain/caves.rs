@@ -0,0 +1,152 @@
+use voxelize::{
+    Chunk, ChunkStage, NoiseOptions, Resources, SeededNoise, Space, Terrain, VoxelAccess,
+};
+
+use super::{
+    rivers::{Rivers, RIVER_MAX_DEPTH},
+    surface_height, TERRAIN_MIN_HEIGHT,
+};
+
+/// Hollows out the solid ground under the terrain with two kinds of caves: long winding
+/// tunnels where two noise fields both cross zero ("worms"), and wide open caverns where a
+/// third one runs high ("cheese").
+///
+/// A cave never gets closer than `depth` voxels to the surface of its own column or any
+/// column next to it, so no cave opens up to the sky or sits beside the water of a cliff.
+/// Under the sea floor that distance is `ocean_depth` instead, to keep the sea from leaking
+/// in. With `rivers`, it's kept under the deepest a river's bed gets too, so no channel cut
+/// later opens into a cave. Run it right after the base terrain, while the surface is
+/// still bare.
+pub struct CaveStage {
+    terrain: Terrain,
+    worm_a: SeededNoise,
+    worm_b: SeededNoise,
+    cheese: SeededNoise,
+    /// How close to zero both worm noises have to be, the width of the tunnels.
+    worm_width: f64,
+    /// How high the cheese noise has to be for a cavern.
+    cheese_threshold: f64,
+    depth: i32,
+    ocean_depth: i32,
+    /// Nothing under this height gets carved, so the bottom of the world stays closed.
+    floor: i32,
+    rivers: Option<Rivers>,
+}
+
+impl CaveStage {
+    pub fn new(seed: u32, terrain: Terrain) -> Self {
+        let worm = NoiseOptions::new()
+            .frequency(0.012)
+            .octaves(2)
+            .persistence(0.5)
+            .lacunarity(2.0)
+            .build();
+        let cheese = NoiseOptions::new()
+            .frequency(0.02)
+            .octaves(3)
+            .persistence(0.5)
+            .lacunarity(2.0)
+            .build();
+
+        Self {
+            terrain,
+            worm_a: SeededNoise::new(seed.wrapping_add(7919), &worm),
+            worm_b: SeededNoise::new(seed.wrapping_add(15887), &worm),
+            cheese: SeededNoise::new(seed.wrapping_add(23819), &cheese),
+            worm_width: 0.06,
+            cheese_threshold: 0.6,
+            depth: 8,
+            ocean_depth: 12,
+            floor: TERRAIN_MIN_HEIGHT + 5,
+            rivers: None,
+        }
+    }
+
+    pub fn rivers(mut self, rivers: Rivers) -> Self {
+        self.rivers = Some(rivers);
+        self
+    }
+
+    fn is_cave(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        // Caverns are squashed to be wider than they are tall.
+        if self.cheese.get3d(vx, vy * 2, vz) > self.cheese_threshold {
+            return true;
+        }
+
+        self.worm_a.get3d(vx, vy, vz).abs() < self.worm_width
+            && self.worm_b.get3d(vx, vy, vz).abs() < self.worm_width
+    }
+}
+
+impl ChunkStage for CaveStage {
+    fn name(&self) -> String {
+        "Caves".to_owned()
+    }
+
+    fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
+        let max_height = resources.config.max_height as i32;
+        let water_level = resources.config.water_level as i32;
+
+        let (min_x, min_z) = (chunk.min.0, chunk.min.2);
+        let (max_x, max_z) = (chunk.max.0, chunk.max.2);
+        let stride = max_z - min_z + 2;
+
+        // The highest a cave may reach in every column of the chunk and the ring around it.
+        // The ring lies in other chunks, so its surface comes from the terrain itself.
+        let mut ceilings = vec![0; ((max_x - min_x + 2) * stride) as usize];
+        let course = self.rivers.as_ref().map(|rivers| {
+            rivers.course(
+                (min_x - 1, min_z - 1),
+                (max_x + 1, max_z + 1),
+                max_height,
+                water_level,
+            )
+        });
+
+        for vx in (min_x - 1)..=max_x {
+            for vz in (min_z - 1)..=max_z {
+                let height = if vx >= min_x && vx < max_x && vz >= min_z && vz < max_z {
+                    chunk.get_max_height(vx, vz) as i32
+                } else {
+                    surface_height(&self.terrain, vx, vz, max_height)
+                };
+
+                let depth = if height < water_level {
+                    self.ocean_depth
+                } else {
+                    self.depth
+                };
+
+                let mut ceiling = height - depth;
+
+                if let Some((_, level)) = course.as_ref().and_then(|course| course.at(vx, vz)) {
+                    ceiling = ceiling.min(level.min(height) - RIVER_MAX_DEPTH - depth);
+                }
+
+                ceilings[((vx - min_x + 1) * stride + (vz - min_z + 1)) as usize] = ceiling;
+            }
+        }
+
+        let ceiling_at =
+            |vx: i32, vz: i32| ceilings[((vx - min_x + 1) * stride + (vz - min_z + 1)) as usize];
+
+        for vx in min_x..max_x {
+            for vz in min_z..max_z {
+                let ceiling = (-1..=1)
+                    .flat_map(|dx| (-1..=1).map(move |dz| (dx, dz)))
+                    .map(|(dx, dz)| ceiling_at(vx + dx, vz + dz))
+                    .min()
+                    .unwrap_or(0)
+                    .min(max_height - 1);
+
+                for vy in self.floor..ceiling {
+                    if chunk.get_voxel(vx, vy, vz) != 0 && self.is_cave(vx, vy, vz) {
+                        chunk.set_voxel(vx, vy, vz, 0);
+                    }
+                }
+            }
+        }
+
+        chunk
+    }
+}
@@ -1,4 +1,9 @@
+pub mod biomes;
+mod caves;
+mod ores;
+mod rivers;
 mod soiling;
+mod templates;
 mod tree;
 
 use voxelize::{
@@ -6,13 +11,29 @@ use voxelize::{
     World, WorldConfig,
 };
 
-use crate::constants::get_preload_radius;
+use crate::constants::{get_preload_radius, get_templates_path};
 
-use self::{soiling::SoilingStage, tree::TreeStage};
+use self::{
+    biomes::{terrain_biomes, BIOME_POINTS},
+    caves::CaveStage,
+    ores::terrain_ores,
+    rivers::{RiverStage, Rivers},
+    soiling::SoilingStage,
+    templates::TemplateStage,
+    tree::TreeStage,
+};
 
 use super::shared::{
-    client::setup_client, components::setup_components, entities::setup_entities,
-    methods::setup_methods, stage::LimitedStage, systems::setup_dispatcher,
+    block_entities::setup_block_entities,
+    client::setup_client,
+    components::setup_components,
+    deltas::setup_deltas,
+    entities::setup_entities,
+    history::setup_history,
+    methods::setup_methods,
+    regions::setup_regions,
+    stage::{IslandShape, LimitedStage},
+    systems::{setup_dispatcher, RandomTickConfig},
 };
 
 use std::f64;
@@ -22,90 +43,109 @@ pub const RIVER_HEIGHT: f64 = 0.25;
 pub const PLAINS_HEIGHT: f64 = 0.347;
 pub const RIVER_WIDTH: f64 = 0.36;
 
-pub fn setup_terrain_world(registry: &Registry) -> World {
-    let config = WorldConfig::new()
-        .terrain(
-            &NoiseOptions::new()
-                .frequency(0.005)
-                .octaves(8)
-                .persistence(0.5)
-                .lacunarity(1.8623123)
-                .build(),
-        )
-        .preload(true)
-        .preload_radius(get_preload_radius())
-        .max_chunk([31, 31])
-        .min_chunk([-32, -32])
-        .default_time(1200.0)
-        .time_per_day(2400)
-        .seed(4213)
-        .build();
+/// The version of the terrain world's generation, stamped on the changes it keeps.
+/// Bump it whenever a change to the stages moves the land around.
+pub const TERRAIN_VERSION: u32 = 2;
+
+/// The block the base stage builds the land out of, Stone.
+pub const TERRAIN_BLOCK: u32 = 2;
+/// The lowest voxel the land can reach. The height searches stop here and caves keep a few
+/// voxels above it.
+pub const TERRAIN_MIN_HEIGHT: i32 = 0;
+/// Voxels whose terrain density is above this are solid.
+pub const TERRAIN_THRESHOLD: f64 = 0.0;
+
+/// Seeds of the noise behind each terrain layer.
+pub const CONTINENTALNESS_SEED: u32 = 1231252;
+pub const PEAKS_AND_VALLEYS_SEED: u32 = 51287;
+pub const EROSION_SEED: u32 = 1233;
+
+pub fn continentalness_noise() -> NoiseOptions {
+    NoiseOptions::new()
+        .frequency(0.0005)
+        .octaves(7)
+        .persistence(0.52)
+        .lacunarity(2.3)
+        .seed(CONTINENTALNESS_SEED)
+        .build()
+}
 
-    let mut world = World::new("terrain", &config);
+pub fn peaks_and_valleys_noise() -> NoiseOptions {
+    NoiseOptions::new()
+        .frequency(0.002)
+        .octaves(7)
+        .persistence(0.53)
+        .lacunarity(2.0)
+        .seed(PEAKS_AND_VALLEYS_SEED)
+        .build()
+}
 
-    let mut terrain = Terrain::new(&config);
+pub fn erosion_noise() -> NoiseOptions {
+    NoiseOptions::new()
+        .frequency(0.01)
+        .octaves(7)
+        .persistence(0.5)
+        .lacunarity(1.9)
+        .seed(EROSION_SEED)
+        .build()
+}
+
+/// The noise each layer of the terrain is built on, with its name and seed.
+pub fn terrain_layer_noise() -> Vec<(&'static str, u32, NoiseOptions)> {
+    vec![
+        (
+            "continentalness",
+            CONTINENTALNESS_SEED,
+            continentalness_noise(),
+        ),
+        (
+            "peaks_and_valleys",
+            PEAKS_AND_VALLEYS_SEED,
+            peaks_and_valleys_noise(),
+        ),
+        ("erosion", EROSION_SEED, erosion_noise()),
+    ]
+}
+
+/// The terrain of the terrain world, built once for its base stage and again for every
+/// stage that needs to know the shape of the land beyond the chunk it's working on.
+pub fn build_terrain(config: &WorldConfig) -> Terrain {
+    let mut terrain = Terrain::new(config);
 
     // The base shape of the terrain:
     // The more extreme (far from 0) the value, the more mountainous the terrain will be.
     // The closer to 0, the more plains-like the terrain will be.
-    let continentalness = TerrainLayer::new(
-        "continentalness",
-        &NoiseOptions::new()
-            .frequency(0.0005)
-            .octaves(7)
-            .persistence(0.52)
-            .lacunarity(2.3)
-            .seed(1231252)
-            .build(),
-    )
-    .add_bias_points(&[[-1.0, 3.5], [0.0, 3.0], [0.4, 5.0], [1.0, 8.5]])
-    .add_offset_points(&[
-        [-2.9, MOUNTAIN_HEIGHT],
-        [-0.5, PLAINS_HEIGHT + 0.01],
-        [0.0, PLAINS_HEIGHT],
-        // [RIVER_WIDTH, PLAINS_HEIGHT],
-        // [0.0, PLAINS_HEIGHT],
-        [1.1, RIVER_HEIGHT],
-        [2.8, 0.0],
-        [4.6, MOUNTAIN_HEIGHT], // [5.7, MOUNTAIN_HEIGHT],
-    ]);
+    let continentalness = TerrainLayer::new("continentalness", &continentalness_noise())
+        .add_bias_points(&[[-1.0, 3.5], [0.0, 3.0], [0.4, 5.0], [1.0, 8.5]])
+        .add_offset_points(&[
+            [-2.9, MOUNTAIN_HEIGHT],
+            [-0.5, PLAINS_HEIGHT + 0.01],
+            [0.0, PLAINS_HEIGHT],
+            // [RIVER_WIDTH, PLAINS_HEIGHT],
+            // [0.0, PLAINS_HEIGHT],
+            [1.1, RIVER_HEIGHT],
+            [2.8, 0.0],
+            [4.6, MOUNTAIN_HEIGHT], // [5.7, MOUNTAIN_HEIGHT],
+        ]);
 
     // // The peaks and valleys of the terrain:
     // // The higher the value, the more mountainous the terrain will be.
     // // The lower the value, the more plains-like the terrain will be.
-    let peaks_and_valleys = TerrainLayer::new(
-        "peaks_and_valleys",
-        &NoiseOptions::new()
-            .frequency(0.002)
-            .octaves(7)
-            .persistence(0.53)
-            .lacunarity(2.0)
-            .seed(51287)
-            .build(),
-    )
-    .add_bias_points(&[[-1.0, 3.5], [1.0, 3.5]])
-    .add_offset_points(&[
-        [-3.0, RIVER_HEIGHT],
-        [-2.0, PLAINS_HEIGHT],
-        [-0.4, PLAINS_HEIGHT * 0.9],
-        [0.0, RIVER_HEIGHT],
-        [RIVER_WIDTH / 2.0, RIVER_HEIGHT * 1.05],
-        [2.0, PLAINS_HEIGHT + RIVER_HEIGHT],
-        [5.0, MOUNTAIN_HEIGHT * 2.0],
-    ]);
-
-    let erosion = TerrainLayer::new(
-        "erosion",
-        &NoiseOptions::new()
-            .frequency(0.01)
-            .octaves(7)
-            .persistence(0.5)
-            .lacunarity(1.9)
-            .seed(1233)
-            .build(),
-    )
-    .add_bias_points(&[[-1.0, 3.5], [1.0, 3.5]])
-    .add_offset_points(&[[-1.0, MOUNTAIN_HEIGHT], [1.0, RIVER_HEIGHT / 2.0]]);
+    let peaks_and_valleys = TerrainLayer::new("peaks_and_valleys", &peaks_and_valleys_noise())
+        .add_bias_points(&[[-1.0, 3.5], [1.0, 3.5]])
+        .add_offset_points(&[
+            [-3.0, RIVER_HEIGHT],
+            [-2.0, PLAINS_HEIGHT],
+            [-0.4, PLAINS_HEIGHT * 0.9],
+            [0.0, RIVER_HEIGHT],
+            [RIVER_WIDTH / 2.0, RIVER_HEIGHT * 1.05],
+            [2.0, PLAINS_HEIGHT + RIVER_HEIGHT],
+            [5.0, MOUNTAIN_HEIGHT * 2.0],
+        ]);
+
+    let erosion = TerrainLayer::new("erosion", &erosion_noise())
+        .add_bias_points(&[[-1.0, 3.5], [1.0, 3.5]])
+        .add_offset_points(&[[-1.0, MOUNTAIN_HEIGHT], [1.0, RIVER_HEIGHT / 2.0]]);
 
     terrain.add_layer(&continentalness, 1.0);
     terrain.add_layer(&peaks_and_valleys, 0.5);
@@ -124,46 +164,135 @@ pub fn setup_terrain_world(registry: &Registry) -> World {
     //  ●	0.0: Land, mountainous
     //  ●	-1.0: Mountain peaks
 
-    let cap = 0.2;
-
-    terrain.add_biome(&[0.0, 0.0, 0.0], Biome::new("Biome 0", "Biome Test 0"));
-
-    terrain.add_biome(&[0.0, 0.0, cap], Biome::new("Biome 1", "Biome Test 1"));
-    terrain.add_biome(&[0.0, cap, 0.0], Biome::new("Biome 2", "Biome Test 2"));
-    terrain.add_biome(&[0.0, cap, cap], Biome::new("Biome 3", "Biome Test 3"));
-    terrain.add_biome(&[cap, 0.0, 0.0], Biome::new("Biome 4", "Biome Test 4"));
-    terrain.add_biome(&[cap, 0.0, cap], Biome::new("Biome 5", "Biome Test 5"));
-    terrain.add_biome(&[cap, cap, 0.0], Biome::new("Biome 6", "Biome Test 6"));
-    terrain.add_biome(&[cap, cap, cap], Biome::new("Biome 7", "Biome Test 7"));
-
-    terrain.add_biome(&[0.0, 0.0, -cap], Biome::new("Biome 8", "Biome Test 8"));
-    terrain.add_biome(&[0.0, -cap, 0.0], Biome::new("Biome 9", "Biome Test 9"));
-    terrain.add_biome(&[0.0, -cap, -cap], Biome::new("Biome 10", "Biome Test 10"));
-    terrain.add_biome(&[-cap, 0.0, 0.0], Biome::new("Biome 11", "Biome Test 11"));
-    terrain.add_biome(&[-cap, 0.0, -cap], Biome::new("Biome 12", "Biome Test 12"));
-    terrain.add_biome(&[-cap, -cap, 0.0], Biome::new("Biome 13", "Biome Test 13"));
-    terrain.add_biome(&[-cap, -cap, -cap], Biome::new("Biome 14", "Biome Test 14"));
-
-    terrain.add_biome(&[cap, cap, -cap], Biome::new("Biome 15", "Biome Test 15"));
-    terrain.add_biome(&[cap, -cap, cap], Biome::new("Biome 16", "Biome Test 16"));
-    terrain.add_biome(&[cap, -cap, -cap], Biome::new("Biome 17", "Biome Test 17"));
-    terrain.add_biome(&[-cap, cap, cap], Biome::new("Biome 18", "Biome Test 18"));
-    terrain.add_biome(&[-cap, cap, -cap], Biome::new("Biome 19", "Biome Test 19"));
-    terrain.add_biome(&[-cap, -cap, cap], Biome::new("Biome 20", "Biome Test 20"));
+    for (point, name) in BIOME_POINTS {
+        terrain.add_biome(&point, Biome::new(name, name));
+    }
+
+    terrain
+}
+
+/// A stable hash of a column and a salt, for choices that have to come out the same no
+/// matter which chunk makes them.
+pub fn hash2d(seed: u32, x: i32, z: i32, salt: u64) -> u64 {
+    let mut h = (seed as u64)
+        ^ ((x as u32 as u64) << 32 | z as u32 as u64).wrapping_mul(0x9e3779b97f4a7c15)
+        ^ salt.wrapping_mul(0xbf58476d1ce4e5b9);
+
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// `hash2d` as a number from 0 up to 1.
+pub fn chance2d(seed: u32, x: i32, z: i32, salt: u64) -> f64 {
+    (hash2d(seed, x, z, salt) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The terrain's density at a voxel, worked out the same way `BaseTerrainStage` does.
+pub fn density_at(terrain: &Terrain, vx: i32, vy: i32, vz: i32) -> f64 {
+    let (bias, offset) = terrain.get_bias_offset(vx, vy, vz);
+    terrain.get_density_from_bias_offset(bias, offset, vy)
+}
+
+/// The y of the highest solid voxel the terrain has in a column, the same one
+/// `BaseTerrainStage` ends up with before any other stage runs.
+pub fn surface_height(terrain: &Terrain, vx: i32, vz: i32, max_height: i32) -> i32 {
+    (TERRAIN_MIN_HEIGHT..max_height)
+        .rev()
+        .find(|&vy| density_at(terrain, vx, vy, vz) > TERRAIN_THRESHOLD)
+        .unwrap_or(TERRAIN_MIN_HEIGHT)
+}
+
+/// `surface_height`, found faster by stepping down the column `step` voxels at a time and
+/// then climbing back up to the exact top. Overhangs thinner than `step` can be missed.
+pub fn rough_surface_height(
+    terrain: &Terrain,
+    vx: i32,
+    vz: i32,
+    max_height: i32,
+    step: i32,
+) -> i32 {
+    let solid = |vy: i32| density_at(terrain, vx, vy, vz) > TERRAIN_THRESHOLD;
+
+    let mut vy = max_height - 1;
+
+    while vy > TERRAIN_MIN_HEIGHT && !solid(vy) {
+        vy = (vy - step).max(TERRAIN_MIN_HEIGHT);
+    }
+
+    while vy + 1 < max_height && solid(vy + 1) {
+        vy += 1;
+    }
+
+    vy
+}
+
+/// The config of the terrain world, for the server and the tools that generate its land
+/// without it.
+pub fn terrain_config() -> WorldConfig {
+    WorldConfig::new()
+        .terrain(
+            &NoiseOptions::new()
+                .frequency(0.005)
+                .octaves(8)
+                .persistence(0.5)
+                .lacunarity(1.8623123)
+                .build(),
+        )
+        .preload(true)
+        .preload_radius(get_preload_radius())
+        .max_chunk([31, 31])
+        .min_chunk([-32, -32])
+        .default_time(1200.0)
+        .time_per_day(2400)
+        .seed(4213)
+        // For the world's block entities, history and regions. Its chunks aren't saved,
+        // only the changes made to them, see `setup_deltas`.
+        .saving(true)
+        .save_dir("data/worlds/terrain")
+        .build()
+}
+
+pub fn setup_terrain_world(registry: &Registry) -> World {
+    let config = terrain_config();
+
+    let mut world = World::new("terrain", &config);
 
     {
         let mut pipeline = world.pipeline_mut();
 
-        let mut terrain_stage = BaseTerrainStage::new(terrain);
-        terrain_stage.set_base(2);
-        terrain_stage.set_threshold(0.0);
+        let mut terrain_stage = BaseTerrainStage::new(build_terrain(&config));
+        terrain_stage.set_base(TERRAIN_BLOCK);
+        terrain_stage.set_threshold(TERRAIN_THRESHOLD);
 
         pipeline.add_stage(terrain_stage);
 
-        pipeline.add_stage(SoilingStage::new(
+        // Traced once for the stages carving them and the ones keeping clear of them.
+        let rivers = Rivers::new(config.seed, build_terrain(&config));
+
+        pipeline.add_stage(
+            CaveStage::new(config.seed, build_terrain(&config)).rivers(rivers.to_owned()),
+        );
+
+        let biomes = terrain_biomes(registry);
+
+        let soiling = SoilingStage::new(
             config.seed,
             &NoiseOptions::new().frequency(0.04).lacunarity(1.6).build(),
-        ));
+            build_terrain(&config),
+            biomes.to_owned(),
+        );
+        let soil = soiling.soil();
+
+        pipeline.add_stage(soiling);
+
+        pipeline.add_stage(
+            RiverStage::new(rivers.to_owned())
+                .bank(registry.get_block_by_name("Sand").id)
+                .bank(registry.get_block_by_name("Gravel").id),
+        );
+
+        pipeline.add_stage(terrain_ores(config.seed, registry));
 
         let mut tiny_trees = Trees::new(
             config.seed,
@@ -281,7 +410,9 @@ pub fn setup_terrain_world(registry: &Registry) -> World {
             .build();
         mystical_trees.register("Mystical", mystical);
 
-        let tree_stage = TreeStage::new()
+        let tree_stage = TreeStage::new(config.seed, build_terrain(&config), biomes)
+            .soil(soil)
+            .rivers(rivers)
             .with(oak_trees, "Oak")
             .with(tiny_trees, "Tiny")
             .with(boulder_trees, "Boulder")
@@ -289,18 +420,32 @@ pub fn setup_terrain_world(registry: &Registry) -> World {
         // .with(ancient_trees, "Ancient");
 
         pipeline.add_stage(tree_stage);
+
+        pipeline.add_stage(
+            TemplateStage::new(config.seed, build_terrain(&config))
+                .threshold(0.3)
+                .load(&get_templates_path()),
+        );
+    }
+
+    {
+        let mut pipeline = world.pipeline_mut();
+        pipeline.add_stage(LimitedStage::new(registry, config.seed).shape(IslandShape::Circle))
     }
 
+    // Only the changes made to its chunks are saved, the rest is generated again from the seed.
+    setup_deltas(&mut world, TERRAIN_VERSION);
+
     setup_components(&mut world);
     setup_entities(&mut world);
     setup_dispatcher(&mut world);
     setup_methods(&mut world);
     setup_client(&mut world);
+    setup_block_entities(&mut world);
+    setup_regions(&mut world, vec![]);
+    setup_history(&mut world);
 
-    {
-        let mut pipeline = world.pipeline_mut();
-        pipeline.add_stage(LimitedStage)
-    }
+    world.ecs_mut().insert(RandomTickConfig::new(6));
 
     world
 }
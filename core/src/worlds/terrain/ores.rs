@@ -0,0 +1,489 @@
+use voxelize::{
+    Chunk, ChunkStage, NoiseOptions, Registry, Resources, SeededNoise, Space, VoxelAccess,
+};
+
+use super::{chance2d, hash2d};
+
+/// Width of the square cells veins are scattered over, each cell getting its own veins.
+pub const ORE_CELL_SIZE: i32 = 16;
+
+/// The most voxels one vein can have. A vein never wanders further than this from where it
+/// starts, so a chunk only has to look at the cells this close to it.
+pub const MAX_VEIN_SIZE: usize = 16;
+
+/// How far up and down the strata bend with the noise.
+pub const STRATA_WARP: f64 = 6.0;
+
+/// Where and how often a block shows up in veins under the ground.
+#[derive(Clone, Debug)]
+pub struct OreDefinition {
+    pub block: u32,
+    /// Veins start between these heights, the top one not included.
+    pub min_height: i32,
+    pub max_height: i32,
+    /// How many voxels a vein has, at most `MAX_VEIN_SIZE`.
+    pub vein_size: usize,
+    /// How many veins a cell gets on average, fractions being a chance of one more.
+    pub frequency: f64,
+    /// The blocks a vein may replace. Anything else it runs into stays as it is.
+    pub hosts: Vec<u32>,
+}
+
+impl OreDefinition {
+    pub fn new(block: u32, min_height: i32, max_height: i32) -> Self {
+        Self {
+            block,
+            min_height,
+            max_height,
+            vein_size: 8,
+            frequency: 1.0,
+            hosts: vec![],
+        }
+    }
+
+    pub fn vein_size(mut self, size: usize) -> Self {
+        self.vein_size = size.min(MAX_VEIN_SIZE);
+        self
+    }
+
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn hosts(mut self, hosts: &[u32]) -> Self {
+        self.hosts = hosts.to_vec();
+        self
+    }
+}
+
+/// Turns the plain stone under the terrain into layers of other rocks, then grows veins of
+/// ores and rare rocks through them. Every vein comes from the cell it starts in and the
+/// seed alone, so it's the same in every chunk it runs through.
+pub struct OreStage {
+    seed: u32,
+    noise: SeededNoise,
+    /// The rock of each stratum and the height it reaches up to, from the bottom up. Stone
+    /// above the last one is left alone.
+    strata: Vec<(u32, i32)>,
+    ores: Vec<OreDefinition>,
+}
+
+impl OreStage {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            noise: SeededNoise::new(
+                seed,
+                &NoiseOptions::new()
+                    .frequency(0.01)
+                    .octaves(3)
+                    .persistence(0.5)
+                    .lacunarity(2.0)
+                    .seed(68111)
+                    .build(),
+            ),
+            strata: vec![],
+            ores: vec![],
+        }
+    }
+
+    pub fn stratum(mut self, block: u32, top: i32) -> Self {
+        self.strata.push((block, top));
+        self
+    }
+
+    pub fn ore(mut self, ore: OreDefinition) -> Self {
+        self.ores.push(ore);
+        self
+    }
+
+    /// The rock stone turns into at a voxel, if any.
+    fn stratum_at(&self, vx: i32, vy: i32, vz: i32) -> Option<u32> {
+        let vy = vy as f64 + self.noise.get2d(vx, vz) * STRATA_WARP;
+
+        self.strata
+            .iter()
+            .find(|(_, top)| vy < *top as f64)
+            .map(|(block, _)| *block)
+    }
+
+    /// Every voxel of the veins of one ore that start in a cell.
+    fn veins(&self, index: usize, ore: &OreDefinition, cx: i32, cz: i32) -> Vec<(i32, i32, i32)> {
+        if ore.max_height <= ore.min_height {
+            return vec![];
+        }
+
+        let salt = |vein: u64, step: u64| (1 << 40) | (index as u64) << 24 | vein << 12 | step;
+
+        let extra = chance2d(self.seed, cx, cz, salt(0xfff, 0)) < ore.frequency.fract();
+        let count = ore.frequency.floor() as u64 + extra as u64;
+
+        let mut voxels = vec![];
+
+        for vein in 0..count {
+            let start = hash2d(self.seed, cx, cz, salt(vein, 0));
+            let mut x = cx * ORE_CELL_SIZE + (start % ORE_CELL_SIZE as u64) as i32;
+            let mut z = cz * ORE_CELL_SIZE + ((start >> 8) % ORE_CELL_SIZE as u64) as i32;
+            let mut y =
+                ore.min_height + ((start >> 16) % (ore.max_height - ore.min_height) as u64) as i32;
+
+            for step in 0..ore.vein_size {
+                voxels.push((x, y, z));
+
+                // Wander one voxel along a random axis and direction.
+                let turn = hash2d(self.seed, cx, cz, salt(vein, step as u64 + 1));
+                let delta = if turn & 1 == 0 { 1 } else { -1 };
+
+                match (turn >> 1) % 3 {
+                    0 => x += delta,
+                    1 => y += delta,
+                    _ => z += delta,
+                }
+            }
+        }
+
+        voxels
+    }
+}
+
+impl ChunkStage for OreStage {
+    fn name(&self) -> String {
+        "Ores".to_owned()
+    }
+
+    fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
+        let stone = resources.registry.get_block_by_name("Stone").id;
+        let max_height = resources.config.max_height as i32;
+
+        let (min_x, min_z) = (chunk.min.0, chunk.min.2);
+        let (max_x, max_z) = (chunk.max.0, chunk.max.2);
+
+        if !self.strata.is_empty() {
+            for vx in min_x..max_x {
+                for vz in min_z..max_z {
+                    let height = (chunk.get_max_height(vx, vz) as i32).min(max_height - 1);
+
+                    for vy in 0..=height {
+                        if chunk.get_voxel(vx, vy, vz) != stone {
+                            continue;
+                        }
+
+                        if let Some(block) = self.stratum_at(vx, vy, vz) {
+                            chunk.set_voxel(vx, vy, vz, block);
+                        }
+                    }
+                }
+            }
+        }
+
+        let reach = MAX_VEIN_SIZE as i32;
+
+        for (index, ore) in self.ores.iter().enumerate() {
+            for cx in (min_x - reach).div_euclid(ORE_CELL_SIZE)
+                ..=(max_x - 1 + reach).div_euclid(ORE_CELL_SIZE)
+            {
+                for cz in (min_z - reach).div_euclid(ORE_CELL_SIZE)
+                    ..=(max_z - 1 + reach).div_euclid(ORE_CELL_SIZE)
+                {
+                    for (vx, vy, vz) in self.veins(index, ore, cx, cz) {
+                        if vx < min_x
+                            || vx >= max_x
+                            || vz < min_z
+                            || vz >= max_z
+                            || vy < ore.min_height
+                            || vy >= ore.max_height.min(max_height)
+                        {
+                            continue;
+                        }
+
+                        if ore.hosts.contains(&chunk.get_voxel(vx, vy, vz)) {
+                            chunk.set_voxel(vx, vy, vz, ore.block);
+                        }
+                    }
+                }
+            }
+        }
+
+        chunk
+    }
+}
+
+/// The rock layers and veins of the terrain world.
+pub fn terrain_ores(seed: u32, registry: &Registry) -> OreStage {
+    let id = |name: &str| registry.get_block_by_name(name).id;
+
+    let (stone, basalt, gabbro, granite, diorite, andersite, limestone) = (
+        id("Stone"),
+        id("Basalt"),
+        id("Gabbro"),
+        id("Granite"),
+        id("Diorite"),
+        id("Andersite"),
+        id("Limestone"),
+    );
+
+    OreStage::new(seed)
+        .stratum(basalt, 14)
+        .stratum(gabbro, 26)
+        .stratum(granite, 42)
+        .stratum(diorite, 54)
+        .stratum(andersite, 66)
+        .stratum(limestone, 78)
+        // Pockets of other rocks
+        .ore(
+            OreDefinition::new(id("Marble"), 30, 80)
+                .vein_size(16)
+                .frequency(0.6)
+                .hosts(&[limestone, andersite, stone]),
+        )
+        .ore(
+            OreDefinition::new(id("Obsidian"), 3, 16)
+                .vein_size(12)
+                .frequency(0.4)
+                .hosts(&[basalt]),
+        )
+        .ore(
+            OreDefinition::new(id("Chalk"), 60, 100)
+                .vein_size(14)
+                .frequency(0.5)
+                .hosts(&[limestone, stone]),
+        )
+        // Common minerals
+        .ore(
+            OreDefinition::new(id("Flint"), 40, 110)
+                .vein_size(8)
+                .frequency(3.0)
+                .hosts(&[limestone, id("Chalk"), stone]),
+        )
+        .ore(
+            OreDefinition::new(id("Pyrite"), 10, 70)
+                .vein_size(6)
+                .frequency(1.5)
+                .hosts(&[granite, diorite, andersite, stone]),
+        )
+        .ore(
+            OreDefinition::new(id("Hematite"), 5, 60)
+                .vein_size(8)
+                .frequency(1.2)
+                .hosts(&[basalt, gabbro, granite]),
+        )
+        .ore(
+            OreDefinition::new(id("Malachite"), 40, 90)
+                .vein_size(6)
+                .frequency(0.8)
+                .hosts(&[limestone, id("Marble")]),
+        )
+        .ore(
+            OreDefinition::new(id("Azurite"), 40, 90)
+                .vein_size(5)
+                .frequency(0.5)
+                .hosts(&[limestone, id("Marble")]),
+        )
+        // Rare agates
+        .ore(
+            OreDefinition::new(id("Blue Lace Agate"), 10, 40)
+                .vein_size(5)
+                .frequency(0.3)
+                .hosts(&[basalt, gabbro]),
+        )
+        .ore(
+            OreDefinition::new(id("Condor Agate"), 5, 30)
+                .vein_size(4)
+                .frequency(0.2)
+                .hosts(&[basalt]),
+        )
+        .ore(
+            OreDefinition::new(id("Moss Agate"), 45, 80)
+                .vein_size(5)
+                .frequency(0.3)
+                .hosts(&[limestone, andersite]),
+        )
+        .ore(
+            OreDefinition::new(id("Crazy Lace Agate"), 30, 60)
+                .vein_size(4)
+                .frequency(0.2)
+                .hosts(&[diorite, andersite]),
+        )
+        // Gems
+        .ore(
+            OreDefinition::new(id("Jade"), 20, 55)
+                .vein_size(4)
+                .frequency(0.25)
+                .hosts(&[granite, diorite, id("Marble")]),
+        )
+        .ore(
+            OreDefinition::new(id("Emerald"), 8, 36)
+                .vein_size(3)
+                .frequency(0.15)
+                .hosts(&[granite, id("Marble")]),
+        )
+        .ore(
+            OreDefinition::new(id("Sunstone"), 5, 26)
+                .vein_size(3)
+                .frequency(0.1)
+                .hosts(&[gabbro, granite]),
+        )
+        .ore(
+            OreDefinition::new(id("Iolite"), 3, 20)
+                .vein_size(3)
+                .frequency(0.1)
+                .hosts(&[basalt, gabbro]),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use voxelize::{Chunk, ChunkOptions, WorldConfig};
+
+    use crate::registry::test_registry;
+
+    use super::*;
+
+    const DIRT: u32 = 1;
+    const STONE: u32 = 2;
+    const BASALT: u32 = 55;
+    const GABBRO: u32 = 57;
+    const MARBLE: u32 = 64;
+
+    #[test]
+    fn strata_follow_their_heights() {
+        let stage = OreStage::new(7).stratum(BASALT, 20).stratum(GABBRO, 40);
+
+        for vx in (0..2000).step_by(50) {
+            for vz in (0..2000).step_by(50) {
+                assert_eq!(stage.stratum_at(vx, 0, vz), Some(BASALT));
+                assert_eq!(stage.stratum_at(vx, 5, vz), Some(BASALT));
+                assert_eq!(stage.stratum_at(vx, 30, vz), Some(GABBRO));
+                assert_eq!(stage.stratum_at(vx, 60, vz), None);
+            }
+        }
+    }
+
+    #[test]
+    fn strata_bend_with_the_noise() {
+        let stage = OreStage::new(7).stratum(BASALT, 20).stratum(GABBRO, 40);
+
+        let tops = (0..2000)
+            .step_by(50)
+            .map(|vx| {
+                (0..40)
+                    .take_while(|&vy| stage.stratum_at(vx, vy, 0) == Some(BASALT))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+
+        // The noise only roughly stays within -1 and 1, so a stratum can bend a voxel past
+        // `STRATA_WARP`.
+        assert!(tops.iter().all(|top| (13..=27).contains(top)));
+        assert!(tops.iter().any(|top| *top != tops[0]));
+    }
+
+    #[test]
+    fn veins_come_as_often_as_their_frequency() {
+        let ore = OreDefinition::new(MARBLE, 10, 30)
+            .vein_size(6)
+            .frequency(1.5);
+        let stage = OreStage::new(7).ore(ore.to_owned());
+
+        let mut total = 0;
+
+        for cx in 0..40 {
+            for cz in 0..40 {
+                let veins = stage.veins(0, &ore, cx, cz).len() / 6;
+                assert!(veins == 1 || veins == 2);
+                total += veins;
+            }
+        }
+
+        let average = total as f64 / 1600.0;
+        assert!((1.4..1.6).contains(&average), "{} veins a cell", average);
+    }
+
+    #[test]
+    fn veins_start_in_their_cell_and_heights() {
+        let ore = OreDefinition::new(MARBLE, 10, 30)
+            .vein_size(8)
+            .frequency(2.0);
+        let stage = OreStage::new(7).ore(ore.to_owned());
+
+        for cx in -10..10 {
+            for cz in -10..10 {
+                for vein in stage.veins(0, &ore, cx, cz).chunks(8) {
+                    let (sx, sy, sz) = vein[0];
+
+                    assert_eq!(sx.div_euclid(ORE_CELL_SIZE), cx);
+                    assert_eq!(sz.div_euclid(ORE_CELL_SIZE), cz);
+                    assert!((10..30).contains(&sy));
+
+                    // One step at a time, so never further than the vein is long.
+                    for (x, y, z) in vein {
+                        assert!((x - sx).abs() + (y - sy).abs() + (z - sz).abs() < 8);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ores_only_replace_their_hosts_within_their_heights() {
+        let mut chunk = Chunk::new(
+            "test",
+            0,
+            0,
+            &ChunkOptions {
+                size: 16,
+                max_height: 64,
+                sub_chunks: 4,
+            },
+        );
+
+        for vx in 0..16 {
+            for vz in 0..16 {
+                for vy in 0..48 {
+                    let id = if (20..24).contains(&vy) { DIRT } else { STONE };
+                    chunk.set_voxel(vx, vy, vz, id);
+                }
+            }
+        }
+
+        let stage = OreStage::new(7).ore(
+            OreDefinition::new(MARBLE, 10, 30)
+                .vein_size(16)
+                .frequency(4.0)
+                .hosts(&[STONE]),
+        );
+        let config = WorldConfig::new().build();
+        let chunk = stage.process(
+            chunk,
+            Resources {
+                registry: test_registry(),
+                config: &config,
+            },
+            None,
+        );
+
+        let mut marble = 0;
+
+        for vx in 0..16 {
+            for vz in 0..16 {
+                for vy in 0..64 {
+                    match chunk.get_voxel(vx, vy, vz) {
+                        MARBLE => {
+                            assert!((10..30).contains(&vy));
+                            marble += 1;
+                        }
+                        DIRT => assert!((20..24).contains(&vy)),
+                        _ => {}
+                    }
+                }
+
+                for vy in 20..24 {
+                    assert_eq!(chunk.get_voxel(vx, vy, vz), DIRT);
+                }
+            }
+        }
+
+        assert!(marble > 0);
+    }
+}
@@ -0,0 +1,407 @@
+use std::sync::{Arc, Mutex};
+
+use hashbrown::{HashMap, HashSet};
+use voxelize::{Chunk, ChunkStage, Resources, SeededNoise, Space, Terrain, VoxelAccess};
+
+use super::{chance2d, continentalness_noise, hash2d, rough_surface_height, CONTINENTALNESS_SEED};
+
+/// Width of the square cells the world is split into for rivers, each holding at most one
+/// river source.
+pub const RIVER_CELL_SIZE: i32 = 128;
+
+/// How far a river moves on every step of its trace.
+pub const RIVER_STEP: i32 = 8;
+
+/// The most steps a river takes before it gives up on finding the sea.
+pub const MAX_RIVER_STEPS: usize = 64;
+
+/// How much higher than its water a river may cut through the land in its way before it
+/// ends in a pond instead.
+pub const MAX_RIVER_CUT: i32 = 12;
+
+/// A river only starts where the continentalness is at most this, well inland. Land is on
+/// the negative side of the continentalness here, see `build_terrain`.
+pub const RIVER_SOURCE_CONTINENTALNESS: f64 = -0.3;
+
+/// How far above sea level a river has to start.
+pub const RIVER_SOURCE_HEIGHT: i32 = 16;
+
+/// The half width of a river at its source and at its mouth.
+pub const RIVER_MIN_RADIUS: f64 = 2.0;
+pub const RIVER_MAX_RADIUS: f64 = 5.0;
+
+/// How far past the water the banks reach.
+pub const RIVER_BANK_WIDTH: f64 = 2.0;
+
+/// The deepest a river bed gets below its water level, in the middle of its course.
+pub const RIVER_MAX_DEPTH: i32 = 4;
+
+/// How far below its bed a river is lined with sand.
+const RIVER_LINING: i32 = 2;
+
+/// How far the work of a river can reach from the cell it starts in.
+const RIVER_REACH: i32 = RIVER_STEP * MAX_RIVER_STEPS as i32 + RIVER_MAX_RADIUS as i32 + 3;
+
+/// The ways a river can flow from each point of its course.
+const DIRECTIONS: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// One point of a river's course.
+#[derive(Clone, Debug)]
+struct RiverPoint {
+    x: i32,
+    z: i32,
+    /// The height of the top of the water here.
+    level: i32,
+    radius: f64,
+}
+
+/// The river starting in each cell, or `None` for a cell without one.
+type Traced = HashMap<(i32, i32), Option<Arc<River>>>;
+
+#[derive(Clone, Debug)]
+struct River {
+    points: Vec<RiverPoint>,
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+/// The rivers of a world, traced from high ground inland down to the sea from the terrain
+/// alone. Each river is kept once traced, so every chunk it runs through sees the same
+/// course. Clones share what's been traced, so the stages that carve the rivers and the
+/// ones that keep clear of them can use one each.
+#[derive(Clone)]
+pub struct Rivers {
+    seed: u32,
+    /// Chance of a cell having a river source.
+    chance: f64,
+    terrain: Arc<Terrain>,
+    continentalness: Arc<SeededNoise>,
+    traced: Arc<Mutex<Traced>>,
+}
+
+impl Rivers {
+    pub fn new(seed: u32, terrain: Terrain) -> Self {
+        Self {
+            seed,
+            chance: 0.5,
+            terrain: Arc::new(terrain),
+            continentalness: Arc::new(SeededNoise::new(
+                CONTINENTALNESS_SEED,
+                &continentalness_noise(),
+            )),
+            traced: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn height(&self, vx: i32, vz: i32, max_height: i32) -> i32 {
+        rough_surface_height(&self.terrain, vx, vz, max_height, 4)
+    }
+
+    /// The river starting in a cell, traced the first time it's asked for.
+    fn river(&self, cx: i32, cz: i32, max_height: i32, water_level: i32) -> Option<Arc<River>> {
+        if let Some(river) = self.traced.lock().unwrap().get(&(cx, cz)) {
+            return river.to_owned();
+        }
+
+        let river = self.trace(cx, cz, max_height, water_level).map(Arc::new);
+
+        self.traced
+            .lock()
+            .unwrap()
+            .insert((cx, cz), river.to_owned());
+
+        river
+    }
+
+    fn trace(&self, cx: i32, cz: i32, max_height: i32, water_level: i32) -> Option<River> {
+        if chance2d(self.seed, cx, cz, 200) >= self.chance {
+            return None;
+        }
+
+        let start = hash2d(self.seed, cx, cz, 201);
+        let mut x = cx * RIVER_CELL_SIZE + (start % RIVER_CELL_SIZE as u64) as i32;
+        let mut z = cz * RIVER_CELL_SIZE + ((start >> 16) % RIVER_CELL_SIZE as u64) as i32;
+
+        if self.continentalness.get2d(x, z) > RIVER_SOURCE_CONTINENTALNESS {
+            return None;
+        }
+
+        let mut level = self.height(x, z, max_height);
+
+        if level < water_level + RIVER_SOURCE_HEIGHT {
+            return None;
+        }
+
+        let mut points = vec![RiverPoint {
+            x,
+            z,
+            level,
+            radius: RIVER_MIN_RADIUS,
+        }];
+        let mut visited = HashSet::new();
+        let mut heading = (0, 0);
+        visited.insert((x, z));
+
+        for step in 1..=MAX_RIVER_STEPS {
+            // Flow to the lowest of the 8 spots around, favoring the way the river already
+            // goes so it doesn't zigzag.
+            let next = DIRECTIONS
+                .into_iter()
+                .map(|(dx, dz)| {
+                    let (nx, nz) = (x + dx * RIVER_STEP, z + dz * RIVER_STEP);
+                    let height = self.height(nx, nz, max_height);
+                    let bend = if (dx, dz) == heading { 0 } else { 1 };
+
+                    ((height, bend), (dx, dz), nx, nz)
+                })
+                .filter(|(_, _, nx, nz)| !visited.contains(&(*nx, *nz)))
+                .min_by_key(|(score, ..)| *score);
+
+            let ((height, _), direction, nx, nz) = match next {
+                Some(next) => next,
+                None => break,
+            };
+
+            if height > level + MAX_RIVER_CUT {
+                break;
+            }
+
+            (x, z, heading) = (nx, nz, direction);
+            level = level.min(height).max(water_level - 1);
+            visited.insert((x, z));
+
+            points.push(RiverPoint {
+                x,
+                z,
+                level,
+                radius: RIVER_MIN_RADIUS
+                    + (RIVER_MAX_RADIUS - RIVER_MIN_RADIUS) * step as f64 / MAX_RIVER_STEPS as f64,
+            });
+
+            if height < water_level {
+                break;
+            }
+        }
+
+        if points.len() < 2 {
+            return None;
+        }
+
+        let margin = (RIVER_MAX_RADIUS + RIVER_BANK_WIDTH).ceil() as i32;
+        let min = points.iter().fold((i32::MAX, i32::MAX), |(x, z), point| {
+            (x.min(point.x - margin), z.min(point.z - margin))
+        });
+        let max = points.iter().fold((i32::MIN, i32::MIN), |(x, z), point| {
+            (x.max(point.x + margin), z.max(point.z + margin))
+        });
+
+        Some(River { points, min, max })
+    }
+
+    /// The rivers whose water or banks reach the columns from `min` up to `max`, exclusive.
+    pub fn course(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+        max_height: i32,
+        water_level: i32,
+    ) -> RiverCourse {
+        let mut rivers = vec![];
+
+        for cx in (min.0 - RIVER_REACH).div_euclid(RIVER_CELL_SIZE)
+            ..=(max.0 - 1 + RIVER_REACH).div_euclid(RIVER_CELL_SIZE)
+        {
+            for cz in (min.1 - RIVER_REACH).div_euclid(RIVER_CELL_SIZE)
+                ..=(max.1 - 1 + RIVER_REACH).div_euclid(RIVER_CELL_SIZE)
+            {
+                if let Some(river) = self.river(cx, cz, max_height, water_level) {
+                    if river.min.0 < max.0
+                        && river.max.0 >= min.0
+                        && river.min.1 < max.1
+                        && river.max.1 >= min.1
+                    {
+                        rivers.push(river);
+                    }
+                }
+            }
+        }
+
+        RiverCourse { rivers }
+    }
+}
+
+/// The rivers running through an area, see `Rivers::course`.
+pub struct RiverCourse {
+    rivers: Vec<Arc<River>>,
+}
+
+impl RiverCourse {
+    pub fn is_empty(&self) -> bool {
+        self.rivers.is_empty()
+    }
+
+    /// Where a column sits against the closest river, if it's in its water or banks. The
+    /// ratio goes from 0 in the middle of the water to 1 at its edge and on to 2 at the
+    /// outer edge of the banks, next to the level of the river's water there.
+    pub fn at(&self, vx: i32, vz: i32) -> Option<(f64, i32)> {
+        self.rivers
+            .iter()
+            .filter_map(|river| closest(river, vx, vz))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .filter(|(ratio, _)| *ratio <= 2.0)
+    }
+}
+
+/// Where a column sits against the closest river, as the distance to its course over its
+/// radius there and the level of its water.
+fn closest(river: &River, vx: i32, vz: i32) -> Option<(f64, i32)> {
+    let (px, pz) = (vx as f64, vz as f64);
+
+    river
+        .points
+        .windows(2)
+        .filter_map(|pair| {
+            let (a, b) = (&pair[0], &pair[1]);
+            let margin = (RIVER_MAX_RADIUS + RIVER_BANK_WIDTH).ceil() as i32;
+
+            if vx < a.x.min(b.x) - margin
+                || vx > a.x.max(b.x) + margin
+                || vz < a.z.min(b.z) - margin
+                || vz > a.z.max(b.z) + margin
+            {
+                return None;
+            }
+
+            let (ax, az) = (a.x as f64, a.z as f64);
+            let (dx, dz) = (b.x as f64 - ax, b.z as f64 - az);
+            let t = (((px - ax) * dx + (pz - az) * dz) / (dx * dx + dz * dz)).clamp(0.0, 1.0);
+            let distance = ((px - ax - dx * t).powi(2) + (pz - az - dz * t).powi(2)).sqrt();
+            let radius = a.radius + (b.radius - a.radius) * t;
+            let level = (a.level as f64 + (b.level - a.level) as f64 * t).round() as i32;
+
+            Some((distance / radius, level, distance - radius))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(ratio, level, past)| {
+            // Past the water, the ratio counts banks from 1 up to 2 at their outer edge.
+            if ratio <= 1.0 {
+                (ratio, level)
+            } else {
+                (1.0 + past / RIVER_BANK_WIDTH, level)
+            }
+        })
+}
+
+/// Carves the channels of the rivers, fills them with water and lines them with banks.
+pub struct RiverStage {
+    rivers: Rivers,
+    /// Blocks the banks are made of, picked per column.
+    banks: Vec<u32>,
+}
+
+impl RiverStage {
+    pub fn new(rivers: Rivers) -> Self {
+        Self {
+            rivers,
+            banks: vec![],
+        }
+    }
+
+    pub fn bank(mut self, block: u32) -> Self {
+        self.banks.push(block);
+        self
+    }
+}
+
+impl ChunkStage for RiverStage {
+    fn name(&self) -> String {
+        "Rivers".to_owned()
+    }
+
+    fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
+        let registry = resources.registry;
+        let max_height = resources.config.max_height as i32;
+        let water_level = resources.config.water_level as i32;
+
+        let water = registry.get_block_by_name("Water").id;
+        let sand = registry.get_block_by_name("Sand").id;
+
+        let (min_x, min_z) = (chunk.min.0, chunk.min.2);
+        let (max_x, max_z) = (chunk.max.0, chunk.max.2);
+
+        let course = self
+            .rivers
+            .course((min_x, min_z), (max_x, max_z), max_height, water_level);
+
+        if course.is_empty() {
+            return chunk;
+        }
+
+        for vx in min_x..max_x {
+            for vz in min_z..max_z {
+                let (ratio, level) = match course.at(vx, vz) {
+                    Some(closest) => closest,
+                    None => continue,
+                };
+
+                let height = chunk.get_max_height(vx, vz) as i32;
+
+                // The sea already has its water.
+                if height < water_level {
+                    continue;
+                }
+
+                let level = level.min(height).min(max_height - 1);
+
+                if ratio > 1.0 {
+                    // Banks, only where the ground is close to the water.
+                    if height <= level + 2 && !self.banks.is_empty() {
+                        let index = (chance2d(self.rivers.seed, vx, vz, 202)
+                            * self.banks.len() as f64) as usize;
+
+                        for vy in (level - 1).max(0)..=height {
+                            if chunk.get_voxel(vx, vy, vz) != 0 {
+                                chunk.set_voxel(vx, vy, vz, self.banks[index]);
+                            }
+                        }
+                    }
+
+                    continue;
+                }
+
+                // Deepest in the middle of the river.
+                let depth = 1 + (2.5 * (1.0 - ratio * ratio)).round() as i32;
+                let bed = (level - depth).max(1);
+
+                for vy in (level + 1)..=height {
+                    chunk.set_voxel(vx, vy, vz, 0);
+                }
+
+                for vy in (bed + 1)..=level {
+                    chunk.set_voxel(vx, vy, vz, water);
+                }
+
+                // Line the bed, so the water can't run off into whatever hollow is below.
+                // Caves keep clear of rivers, see `CaveStage::rivers`.
+                for vy in (bed - RIVER_LINING).max(0)..=bed {
+                    if chunk.get_voxel(vx, vy, vz) == 0 {
+                        chunk.set_voxel(vx, vy, vz, sand);
+                    }
+                }
+
+                chunk.set_voxel(vx, bed, vz, sand);
+            }
+        }
+
+        chunk
+    }
+}
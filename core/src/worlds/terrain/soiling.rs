@@ -1,19 +1,81 @@
-use voxelize::{Chunk, ChunkStage, NoiseOptions, Resources, SeededNoise, Space, VoxelAccess};
+use std::sync::Arc;
+
+use voxelize::{
+    Chunk, ChunkStage, NoiseOptions, Resources, SeededNoise, Space, Terrain, VoxelAccess,
+};
+
+use super::{
+    biomes::{BiomeDefinition, Biomes},
+    chance2d,
+};
 
 pub const VARIANCE: f64 = 3.0;
-pub const SNOW_HEIGHT: f64 = 0.6;
 pub const STONE_HEIGHT: f64 = 0.5;
 
+/// Where the snow and bare stone on top of the land start, from the same noise the soiling
+/// stage uses. Clones share the noise, for stages that have to know what tops a column
+/// without its chunk.
+#[derive(Clone)]
+pub struct Soil {
+    noise: Arc<SeededNoise>,
+}
+
+impl Soil {
+    pub fn new(seed: u32, options: &NoiseOptions) -> Self {
+        Self {
+            noise: Arc::new(SeededNoise::new(seed, options)),
+        }
+    }
+
+    /// The height above which a column turns to snow.
+    pub fn snow_height(&self, biome: &BiomeDefinition, vx: i32, vz: i32, max_height: i32) -> i32 {
+        (biome.snow_line * max_height as f64) as i32 + (self.noise.get2d(vx, vz) * VARIANCE) as i32
+    }
+
+    /// The height above which a column turns to bare stone.
+    pub fn stone_height(&self, vx: i32, vz: i32, max_height: i32) -> i32 {
+        (STONE_HEIGHT * max_height as f64) as i32 + (self.noise.get2d(vx, vz) * VARIANCE) as i32
+    }
+
+    /// Whether a column with its surface at `height` is topped with its biome's surface,
+    /// rather than the sea floor, snow or stone.
+    pub fn has_surface(
+        &self,
+        biome: &BiomeDefinition,
+        vx: i32,
+        height: i32,
+        vz: i32,
+        max_height: i32,
+        water_level: i32,
+    ) -> bool {
+        height > water_level
+            && height <= self.snow_height(biome, vx, vz, max_height)
+            && height <= self.stone_height(vx, vz, max_height)
+    }
+}
+
+/// Covers the bare terrain with the soil of each column's biome, fills in the water and
+/// plants grass and flowers.
 pub struct SoilingStage {
-    noise: SeededNoise,
+    seed: u32,
+    soil: Soil,
+    terrain: Terrain,
+    biomes: Biomes,
 }
 
 impl SoilingStage {
-    pub fn new(seed: u32, options: &NoiseOptions) -> Self {
+    pub fn new(seed: u32, options: &NoiseOptions, terrain: Terrain, biomes: Biomes) -> Self {
         Self {
-            noise: SeededNoise::new(seed, options),
+            seed,
+            soil: Soil::new(seed, options),
+            terrain,
+            biomes,
         }
     }
+
+    pub fn soil(&self) -> Soil {
+        self.soil.to_owned()
+    }
 }
 
 impl ChunkStage for SoilingStage {
@@ -29,28 +91,26 @@ impl ChunkStage for SoilingStage {
 
         let water = registry.get_block_by_name("Water");
         let sand = registry.get_block_by_name("Sand");
-        let dirt = registry.get_block_by_name("Dirt");
         let stone = registry.get_block_by_name("Stone");
-        let grass_block = registry.get_block_by_name("Grass Block");
         let snow = registry.get_block_by_name("Snow");
         let grass = registry.get_block_by_name("Grass");
 
         for vx in chunk.min.0..chunk.max.0 {
             for vz in chunk.min.2..chunk.max.2 {
                 let height = chunk.get_max_height(vx, vz) as i32;
+                let biome = self.biomes.at(&self.terrain, vx, height, vz);
 
-                let snow_height = (SNOW_HEIGHT * config.max_height as f64) as i32
-                    + (self.noise.get2d(vx, vz) * VARIANCE) as i32;
-                let stone_height = (STONE_HEIGHT * config.max_height as f64) as i32
-                    + (self.noise.get2d(vx, vz) * VARIANCE) as i32;
+                let max_height = config.max_height as i32;
+                let snow_height = self.soil.snow_height(biome, vx, vz, max_height);
+                let stone_height = self.soil.stone_height(vx, vz, max_height);
 
                 for vy in 0..=(height.max(water_level)) {
-                    let depth = 2;
+                    let depth = biome.subsurface_depth;
 
-                    // Fill in the water
+                    // Fill in the water, only above the surface so caves stay dry
                     let id = chunk.get_voxel(vx, vy, vz);
 
-                    if registry.is_air(id) && vy < water_level {
+                    if registry.is_air(id) && vy > height && vy < water_level {
                         chunk.set_voxel(vx, vy, vz, water.id);
                         continue;
                     }
@@ -63,17 +123,22 @@ impl ChunkStage for SoilingStage {
                                 chunk.set_voxel(vx, vy, vz, stone.id);
                             } else {
                                 if vy == height {
-                                    chunk.set_voxel(vx, vy, vz, grass_block.id);
+                                    chunk.set_voxel(vx, vy, vz, biome.surface);
                                 } else {
-                                    chunk.set_voxel(vx, vy, vz, dirt.id);
+                                    chunk.set_voxel(vx, vy, vz, biome.subsurface);
                                 }
                             }
                         }
 
-                        if vy == height {
-                            if self.noise.get3d(vx, vy, vz) > 2.5
-                                && chunk.get_voxel(vx, vy, vz) == grass_block.id
-                            {
+                        if vy == height && chunk.get_voxel(vx, vy, vz) == biome.surface {
+                            let roll = chance2d(self.seed, vx, vz, 0);
+
+                            if roll < biome.flower_density && !biome.flowers.is_empty() {
+                                let index = (chance2d(self.seed, vx, vz, 1)
+                                    * biome.flowers.len() as f64)
+                                    as usize;
+                                chunk.set_voxel(vx, vy + 1, vz, biome.flowers[index]);
+                            } else if roll < biome.flower_density + biome.grass_density {
                                 chunk.set_voxel(vx, vy + 1, vz, grass.id);
                             }
                         }
@@ -81,7 +146,7 @@ impl ChunkStage for SoilingStage {
                         && vy <= height
                         && vy >= height - depth
                     {
-                        if self.noise.get3d(vx, vy, vz) > 2.0 {
+                        if self.soil.noise.get3d(vx, vy, vz) > 2.0 {
                             chunk.set_voxel(vx, vy, vz, stone.id);
                         } else {
                             chunk.set_voxel(vx, vy, vz, sand.id);
@@ -0,0 +1,278 @@
+use std::{fs::File, path::Path};
+
+use log::warn;
+use serde::Deserialize;
+use voxelize::{
+    Chunk, ChunkStage, NoiseOptions, Resources, SeededNoise, Space, Terrain, Vec3, VoxelAccess,
+};
+
+use crate::worlds::shared::structures::{Structure, StructureTransform};
+
+use super::{hash2d, surface_height};
+
+/// Width of the square cells the world is split into for templates. Every cell gets at
+/// most one template, kept entirely inside it, so a chunk only has to look at the cells it
+/// overlaps to know everything that reaches into it.
+pub const TEMPLATE_CELL_SIZE: i32 = 64;
+
+/// When a template may be placed, read from the templates manifest.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateRules {
+    /// Structure file, relative to the templates folder.
+    pub file: String,
+    /// Biomes the template shows up in, any biome if empty.
+    #[serde(default)]
+    pub biomes: Vec<String>,
+    /// The most the ground under the template may rise and fall.
+    #[serde(default = "default_max_slope")]
+    pub max_slope: i32,
+    /// How much more likely this template is picked than the others.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// How far below the ground the template's lowest layer goes.
+    #[serde(default)]
+    pub sink: i32,
+    /// Whether the terrain inside the template's box is hollowed out first.
+    #[serde(default = "default_clear")]
+    pub clear: bool,
+}
+
+fn default_max_slope() -> i32 {
+    3
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_clear() -> bool {
+    true
+}
+
+struct Template {
+    rules: TemplateRules,
+    structure: Structure,
+}
+
+/// Where a template ended up in one cell.
+struct Placement<'a> {
+    template: &'a Template,
+    origin: Vec3<i32>,
+    transform: StructureTransform,
+}
+
+/// Scatters template buildings from a folder of structure files over the terrain. Whether a
+/// cell gets one depends on a noise map and the template's rules, worked out from the
+/// terrain itself rather than the chunk, so it comes out the same in every chunk it spans.
+pub struct TemplateStage {
+    seed: u32,
+    terrain: Terrain,
+    noise: SeededNoise,
+    /// How high the noise has to be at a cell for it to get a template.
+    threshold: f64,
+    templates: Vec<Template>,
+}
+
+impl TemplateStage {
+    pub fn new(seed: u32, terrain: Terrain) -> Self {
+        Self {
+            seed,
+            terrain,
+            noise: SeededNoise::new(seed, &NoiseOptions::new().frequency(0.02).build()),
+            threshold: 0.0,
+            templates: vec![],
+        }
+    }
+
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Load every template listed in `templates.json` inside `folder`.
+    pub fn load(mut self, folder: &Path) -> Self {
+        let manifest = folder.join("templates.json");
+
+        if !manifest.exists() {
+            return self;
+        }
+
+        let rules = File::open(&manifest)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                serde_json::from_reader::<_, Vec<TemplateRules>>(file).map_err(|e| e.to_string())
+            });
+
+        let rules = match rules {
+            Ok(rules) => rules,
+            Err(e) => {
+                warn!("Failed to load templates from {:?}: {}", manifest, e);
+                return self;
+            }
+        };
+
+        for rules in rules {
+            let structure = match Structure::load(&folder.join(&rules.file)) {
+                Ok(structure) => structure,
+                Err(e) => {
+                    warn!("Failed to load template {}: {}", rules.file, e);
+                    continue;
+                }
+            };
+
+            let Vec3(sx, _, sz) = structure.size;
+
+            if sx.max(sz) >= TEMPLATE_CELL_SIZE {
+                warn!(
+                    "Template {} is too wide, it has to fit in {} voxels.",
+                    rules.file,
+                    TEMPLATE_CELL_SIZE - 1
+                );
+                continue;
+            }
+
+            self.templates.push(Template { rules, structure });
+        }
+
+        self
+    }
+
+    /// The template a cell holds, if any.
+    fn placement(
+        &self,
+        cx: i32,
+        cz: i32,
+        max_height: i32,
+        water_level: i32,
+    ) -> Option<Placement<'_>> {
+        let total = self
+            .templates
+            .iter()
+            .map(|template| template.rules.weight as u64)
+            .sum::<u64>();
+
+        if total == 0 {
+            return None;
+        }
+
+        let center_x = cx * TEMPLATE_CELL_SIZE + TEMPLATE_CELL_SIZE / 2;
+        let center_z = cz * TEMPLATE_CELL_SIZE + TEMPLATE_CELL_SIZE / 2;
+
+        if self.noise.get2d(center_x, center_z) < self.threshold {
+            return None;
+        }
+
+        let mut pick = hash2d(self.seed, cx, cz, 0) % total;
+        let template = self.templates.iter().find(|template| {
+            let weight = template.rules.weight as u64;
+
+            if pick < weight {
+                return true;
+            }
+
+            pick -= weight;
+            false
+        })?;
+
+        let shape = hash2d(self.seed, cx, cz, 1);
+        let transform = StructureTransform {
+            turns: (shape % 4) as u32,
+            mirror: (shape >> 2) % 2 == 1,
+        };
+        let Vec3(sx, sy, sz) = transform.size(&template.structure.size);
+
+        let x = cx * TEMPLATE_CELL_SIZE
+            + (hash2d(self.seed, cx, cz, 2) % (TEMPLATE_CELL_SIZE - sx) as u64) as i32;
+        let z = cz * TEMPLATE_CELL_SIZE
+            + (hash2d(self.seed, cx, cz, 3) % (TEMPLATE_CELL_SIZE - sz) as u64) as i32;
+
+        let heights = [
+            (x, z),
+            (x + sx - 1, z),
+            (x, z + sz - 1),
+            (x + sx - 1, z + sz - 1),
+            (x + sx / 2, z + sz / 2),
+        ]
+        .map(|(vx, vz)| surface_height(&self.terrain, vx, vz, max_height));
+
+        let lowest = *heights.iter().min()?;
+        let highest = *heights.iter().max()?;
+
+        if highest - lowest > template.rules.max_slope || lowest < water_level {
+            return None;
+        }
+
+        if lowest + 1 - template.rules.sink + sy > max_height {
+            return None;
+        }
+
+        if !template.rules.biomes.is_empty() {
+            let biome = self
+                .terrain
+                .get_biome_at(x + sx / 2, heights[4], z + sz / 2);
+
+            if !template.rules.biomes.contains(&biome.name) {
+                return None;
+            }
+        }
+
+        Some(Placement {
+            template,
+            origin: Vec3(x, lowest + 1 - template.rules.sink, z),
+            transform,
+        })
+    }
+}
+
+impl ChunkStage for TemplateStage {
+    fn name(&self) -> String {
+        "Templates".to_owned()
+    }
+
+    fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
+        let max_height = resources.config.max_height as i32;
+        let water_level = resources.config.water_level as i32;
+
+        let Vec3(min_x, _, min_z) = chunk.min;
+        let Vec3(max_x, _, max_z) = chunk.max;
+
+        for cx in min_x.div_euclid(TEMPLATE_CELL_SIZE)..=(max_x - 1).div_euclid(TEMPLATE_CELL_SIZE)
+        {
+            for cz in
+                min_z.div_euclid(TEMPLATE_CELL_SIZE)..=(max_z - 1).div_euclid(TEMPLATE_CELL_SIZE)
+            {
+                let placement = match self.placement(cx, cz, max_height, water_level) {
+                    Some(placement) => placement,
+                    None => continue,
+                };
+
+                let Placement {
+                    template,
+                    origin,
+                    transform,
+                } = placement;
+                let Vec3(sx, sy, sz) = transform.size(&template.structure.size);
+
+                if template.rules.clear {
+                    for vx in origin.0.max(min_x)..(origin.0 + sx).min(max_x) {
+                        for vz in origin.2.max(min_z)..(origin.2 + sz).min(max_z) {
+                            for vy in (origin.1 + template.rules.sink)..(origin.1 + sy) {
+                                chunk.set_voxel(vx, vy, vz, 0);
+                            }
+                        }
+                    }
+                }
+
+                template.structure.place_in_chunk(
+                    &mut chunk,
+                    resources.registry,
+                    &origin,
+                    &transform,
+                );
+            }
+        }
+
+        chunk
+    }
+}
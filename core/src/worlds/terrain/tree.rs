@@ -1,19 +1,166 @@
-use voxelize::{Chunk, ChunkStage, Resources, Space, Trees, Vec3, VoxelAccess};
+use std::sync::{Arc, Mutex};
 
+use hashbrown::HashMap;
+use voxelize::{Chunk, ChunkStage, Resources, Space, Terrain, Trees, Vec3, VoxelAccess};
+
+use super::{biomes::Biomes, chance2d, rivers::Rivers, soiling::Soil, surface_height};
+
+/// How far from its root a tree grows by default. Whatever a tree would grow past this is
+/// left out.
+pub const TREE_REACH: i32 = 16;
+
+/// Width of the square cells tree roots are found and kept in.
+pub const TREE_CELL_SIZE: i32 = 16;
+
+/// The trees rooted in a cell, as the tree type's index and the voxel of the root.
+type Roots = Arc<Vec<(usize, Vec3<i32>)>>;
+
+/// Plants every tree type that grows in a column's biome, as densely as the biome says.
+///
+/// Whether a column gets a tree is worked out from the seed alone: its surface from the
+/// terrain, what tops it from `soil` and whether it's in the way of `rivers`. So every
+/// chunk finds the trees rooted up to `TREE_REACH` voxels outside it too and grows the
+/// parts of them that fall inside it, and trees come out whole across chunk borders no
+/// matter which chunk generates first. Handing the parts outside a chunk to its neighbors
+/// instead would land them after the later stages, like templates and deltas, already ran
+/// on those.
+///
+/// Finding a root means finding the surface of its column, so the roots are kept per cell
+/// once found, and each column is only looked at once however many chunks a tree reaches.
 pub struct TreeStage {
+    seed: u32,
+    terrain: Terrain,
+    biomes: Biomes,
+    soil: Option<Soil>,
+    rivers: Option<Rivers>,
     // trees + tree type
     all_trees: Vec<(Trees, String)>,
+    found: Arc<Mutex<HashMap<(i32, i32), Roots>>>,
 }
 
 impl TreeStage {
-    pub fn new() -> Self {
-        Self { all_trees: vec![] }
+    pub fn new(seed: u32, terrain: Terrain, biomes: Biomes) -> Self {
+        Self {
+            seed,
+            terrain,
+            biomes,
+            soil: None,
+            rivers: None,
+            all_trees: vec![],
+            found: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Keep trees off snow and bare stone.
+    pub fn soil(mut self, soil: Soil) -> Self {
+        self.soil = Some(soil);
+        self
+    }
+
+    /// Keep trees out of rivers and off their banks.
+    pub fn rivers(mut self, rivers: Rivers) -> Self {
+        self.rivers = Some(rivers);
+        self
     }
 
     pub fn with(mut self, trees: Trees, tree_type: &str) -> Self {
         self.all_trees.push((trees, tree_type.to_string()));
         self
     }
+
+    /// The trees rooted in the columns from `min` up to `max`, exclusive, as the tree type's
+    /// index and the voxel of the root. Roots come in the same order whatever the range.
+    pub fn roots(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+        max_height: i32,
+        water_level: i32,
+    ) -> Vec<(usize, Vec3<i32>)> {
+        let course = self
+            .rivers
+            .as_ref()
+            .map(|rivers| rivers.course(min, max, max_height, water_level));
+        let densest = self
+            .all_trees
+            .iter()
+            .map(|(_, tree_type)| self.biomes.max_tree_density(tree_type))
+            .collect::<Vec<_>>();
+
+        let mut roots = vec![];
+
+        for vx in min.0..max.0 {
+            for vz in min.1..max.1 {
+                // Most columns can't get a tree in any biome, so they're skipped before the
+                // surface is looked for.
+                let rolls = (0..self.all_trees.len())
+                    .map(|index| chance2d(self.seed, vx, vz, 100 + index as u64))
+                    .collect::<Vec<_>>();
+
+                if rolls
+                    .iter()
+                    .zip(densest.iter())
+                    .all(|(roll, densest)| roll >= densest)
+                {
+                    continue;
+                }
+
+                let height = surface_height(&self.terrain, vx, vz, max_height);
+
+                if height <= water_level {
+                    continue;
+                }
+
+                let biome = self.biomes.at(&self.terrain, vx, height, vz);
+
+                if let Some(soil) = &self.soil {
+                    if !soil.has_surface(biome, vx, height, vz, max_height, water_level) {
+                        continue;
+                    }
+                }
+
+                if let Some(course) = &course {
+                    if course.at(vx, vz).is_some() {
+                        continue;
+                    }
+                }
+
+                for (index, (trees, tree_type)) in self.all_trees.iter().enumerate() {
+                    let density = biome.tree_density(tree_type);
+
+                    if density <= 0.0 || rolls[index] >= density {
+                        continue;
+                    }
+
+                    let root = Vec3(vx, height, vz);
+
+                    if trees.should_plant(&root) {
+                        roots.push((index, root));
+                    }
+                }
+            }
+        }
+
+        roots
+    }
+
+    /// The roots in a cell, found the first time they're asked for.
+    fn roots_in_cell(&self, cx: i32, cz: i32, max_height: i32, water_level: i32) -> Roots {
+        if let Some(roots) = self.found.lock().unwrap().get(&(cx, cz)) {
+            return roots.to_owned();
+        }
+
+        let min = (cx * TREE_CELL_SIZE, cz * TREE_CELL_SIZE);
+        let max = (min.0 + TREE_CELL_SIZE, min.1 + TREE_CELL_SIZE);
+        let roots = Arc::new(self.roots(min, max, max_height, water_level));
+
+        self.found
+            .lock()
+            .unwrap()
+            .insert((cx, cz), roots.to_owned());
+
+        roots
+    }
 }
 
 impl ChunkStage for TreeStage {
@@ -22,27 +169,49 @@ impl ChunkStage for TreeStage {
     }
 
     fn process(&self, mut chunk: Chunk, resources: Resources, _: Option<Space>) -> Chunk {
-        let dirt = resources.registry.get_block_by_name("Dirt");
-        let grass_block = resources.registry.get_block_by_name("Grass Block");
+        let max_height = resources.config.max_height as i32;
+        let water_level = resources.config.water_level as i32;
 
-        for vx in chunk.min.0..chunk.max.0 {
-            for vz in chunk.min.2..chunk.max.2 {
-                let height = chunk.get_max_height(vx, vz) as i32;
-                let id = chunk.get_voxel(vx, height, vz);
+        let Vec3(min_x, _, min_z) = chunk.min;
+        let Vec3(max_x, _, max_z) = chunk.max;
 
-                if id != dirt.id && id != grass_block.id {
+        let (from_x, from_z) = (min_x - TREE_REACH, min_z - TREE_REACH);
+        let (to_x, to_z) = (max_x + TREE_REACH, max_z + TREE_REACH);
+
+        let cells = (from_x.div_euclid(TREE_CELL_SIZE)..=(to_x - 1).div_euclid(TREE_CELL_SIZE))
+            .flat_map(|cx| {
+                (from_z.div_euclid(TREE_CELL_SIZE)..=(to_z - 1).div_euclid(TREE_CELL_SIZE))
+                    .map(move |cz| (cx, cz))
+            });
+
+        let roots = cells
+            .flat_map(|(cx, cz)| {
+                self.roots_in_cell(cx, cz, max_height, water_level)
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .filter(|(_, Vec3(rx, _, rz))| {
+                (from_x..to_x).contains(rx) && (from_z..to_z).contains(rz)
+            });
+
+        for (index, root) in roots {
+            let (trees, tree_type) = &self.all_trees[index];
+            let Vec3(rx, _, rz) = root;
+
+            for (Vec3(vx, vy, vz), id) in trees.generate(tree_type, &root) {
+                if (vx - rx).abs() > TREE_REACH || (vz - rz).abs() > TREE_REACH {
                     continue;
                 }
 
-                for (trees, tree_type) in self.all_trees.iter() {
-                    if trees.should_plant(&Vec3(vx, height, vz)) {
-                        trees
-                            .generate(&tree_type, &Vec3(vx, height, vz))
-                            .into_iter()
-                            .for_each(|(Vec3(ux, uy, uz), id)| {
-                                chunk.set_voxel(ux, uy, uz, id);
-                            });
-                    }
+                if vx >= min_x
+                    && vx < max_x
+                    && vz >= min_z
+                    && vz < max_z
+                    && vy >= 0
+                    && vy < max_height
+                {
+                    chunk.set_voxel(vx, vy, vz, id);
                 }
             }
         }
@@ -50,3 +219,135 @@ impl ChunkStage for TreeStage {
         chunk
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+
+    use voxelize::{ChunkOptions, LSystem, NoiseOptions, Tree, VoxelAccess};
+
+    use crate::registry::test_registry;
+
+    use super::{
+        super::{biomes::terrain_biomes, build_terrain, soiling::SoilingStage, terrain_config},
+        *,
+    };
+
+    fn tree_stage() -> TreeStage {
+        let config = terrain_config();
+        let biomes = terrain_biomes(test_registry());
+        let soil = SoilingStage::new(
+            config.seed,
+            &NoiseOptions::new().frequency(0.04).lacunarity(1.6).build(),
+            build_terrain(&config),
+            biomes.to_owned(),
+        )
+        .soil();
+
+        let mut trees = Trees::new(
+            config.seed,
+            &NoiseOptions::new()
+                .frequency(0.36)
+                .lacunarity(2.9)
+                .seed(532874)
+                .build(),
+        );
+        trees.register(
+            "Oak",
+            Tree::new(5004, 5003)
+                .leaf_height(3)
+                .leaf_radius(3)
+                .branch_initial_radius(2)
+                .branch_initial_length(6)
+                .branch_dy_angle(f64::consts::PI / 4.0)
+                .branch_drot_angle(f64::consts::PI / 4.0)
+                .system(LSystem::new().axiom("F%[F%]").iterations(1).build())
+                .build(),
+        );
+
+        TreeStage::new(config.seed, build_terrain(&config), biomes)
+            .soil(soil)
+            .rivers(Rivers::new(config.seed, build_terrain(&config)))
+            .with(trees, "Oak")
+    }
+
+    fn chunk(cx: i32, cz: i32, size: usize) -> Chunk {
+        Chunk::new(
+            "test",
+            cx,
+            cz,
+            &ChunkOptions {
+                size,
+                max_height: terrain_config().max_height,
+                sub_chunks: 4,
+            },
+        )
+    }
+
+    #[test]
+    fn roots_do_not_depend_on_the_range() {
+        let stage = tree_stage();
+        let config = terrain_config();
+        let (max_height, water_level) = (config.max_height as i32, config.water_level as i32);
+
+        let inner = stage.roots((64, -32), (128, 32), max_height, water_level);
+        let outer = stage
+            .roots((0, -96), (192, 96), max_height, water_level)
+            .into_iter()
+            .filter(|(_, Vec3(vx, _, vz))| (64..128).contains(vx) && (-32..32).contains(vz))
+            .collect::<Vec<_>>();
+
+        assert_eq!(inner, outer);
+    }
+
+    #[test]
+    fn trees_come_out_whole_across_chunk_borders() {
+        let stage = tree_stage();
+        let config = terrain_config();
+        let (max_height, water_level) = (config.max_height as i32, config.water_level as i32);
+
+        let (_, root) = (-16..16)
+            .flat_map(|cx| (-16..16).map(move |cz| (cx, cz)))
+            .find_map(|(cx, cz)| {
+                stage
+                    .roots_in_cell(cx, cz, max_height, water_level)
+                    .first()
+                    .cloned()
+            })
+            .expect("no trees to test with");
+
+        let resources = || Resources {
+            registry: test_registry(),
+            config: &config,
+        };
+
+        // A chunk twice as wide around the first tree has to come out the same as the four
+        // chunks it covers, each grown on its own.
+        let Vec3(rx, _, rz) = root;
+        let (cx, cz) = (rx.div_euclid(32), rz.div_euclid(32));
+
+        let whole = stage.process(chunk(cx, cz, 32), resources(), None);
+        let parts = [(0, 0), (0, 1), (1, 0), (1, 1)]
+            .map(|(dx, dz)| stage.process(chunk(cx * 2 + dx, cz * 2 + dz, 16), resources(), None));
+
+        let mut grown = 0;
+
+        for vx in cx * 32..cx * 32 + 32 {
+            for vz in cz * 32..cz * 32 + 32 {
+                let part = &parts[(vx.div_euclid(16) - cx * 2) as usize * 2
+                    + (vz.div_euclid(16) - cz * 2) as usize];
+
+                for vy in 0..max_height {
+                    let id = whole.get_voxel(vx, vy, vz);
+                    assert_eq!(id, part.get_voxel(vx, vy, vz), "at {} {} {}", vx, vy, vz);
+
+                    if id != 0 {
+                        grown += 1;
+                    }
+                }
+            }
+        }
+
+        assert!(grown > 0);
+    }
+}
@@ -1,6 +1,6 @@
-import fetch from 'node-fetch';
+import fs from 'fs';
 
-import { transport } from '@/server/transport';
+import fetch from 'node-fetch';
 
 export async function getGithubContributions() {
   const token = process.env.GITHUB_TOKEN;
@@ -39,56 +39,14 @@ export async function getGithubContributions() {
   return data.data.user.contributionsCollection.contributionCalendar;
 }
 
+// The core server builds the contribution wall in the main world out of this file.
 export async function buildContributionBlocks() {
   const data = await getGithubContributions();
-  const colors = data.colors;
-  const getContributionLevel = (color: string) => {
-    const index = colors.indexOf(color);
-    return index < 0 ? 0 : index + 1;
-  };
-  const weeksData = data.weeks.splice(data.weeks.length - 53, 53);
-  const updates: any[] = [];
-  let column = 0;
-  for (const weekData of weeksData) {
-    const { contributionDays } = weekData;
-
-    let row = 47;
-
-    for (const contributionDay of contributionDays) {
-      const { color } = contributionDay;
-      const contributionLevel = getContributionLevel(color);
-      updates.push({
-        voxel: 3000 + contributionLevel,
-        vx: column + 26,
-        vy: row,
-        vz: 68,
-      });
+  const path = `${__dirname}/../core/data/contributions.json`;
 
-      row--;
-    }
-
-    // If i'm on the last column, check if contributionDays is less than 7
-    // If so, fill the rest with 0s
-    if (column === weeksData.length - 1 && contributionDays.length < 7) {
-      for (let i = contributionDays.length; i < 7; i++) {
-        updates.push({
-          voxel: 0,
-          vx: column + 26,
-          vy: row,
-          vz: 68,
-        });
-        row--;
-      }
-    }
-
-    column--;
-  }
-
-  transport.send({
-    type: 'UPDATE',
-    text: 'main',
-    updates,
-  });
+  fs.writeFileSync(path, JSON.stringify(data));
 
-  console.log(`Updating ${updates.length} blocks for Github contributions`);
+  console.log(
+    `Saved ${data.weeks.length} weeks of Github contributions to ${path}`,
+  );
 }
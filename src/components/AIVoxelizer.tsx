@@ -11,7 +11,7 @@ import { isAdmin } from '../utils/isAdmin';
 import { getServerUrl } from '../utils/urls';
 
 export function AIVoxelizer() {
-  const { world, gui, rigidControls } = useVoxelize();
+  const { world, method, gui, rigidControls } = useVoxelize();
 
   const [prompt, setPrompt] = useState('');
   const [width, setWidth] = useState(64);
@@ -106,7 +106,7 @@ export function AIVoxelizer() {
                   (!prompt || isLoading) && 'half-disabled',
                 )}
                 onClick={async () => {
-                  if (!world || !rigidControls) {
+                  if (!world || !method || !rigidControls) {
                     return;
                   }
 
@@ -130,6 +130,7 @@ export function AIVoxelizer() {
                   ImageVoxelizer.build(
                     b64_json,
                     world,
+                    method,
                     new Vector3(...rigidControls.voxel),
                     {
                       width,
@@ -11,6 +11,7 @@ import { isAdmin } from '../utils/isAdmin';
 const maxRadius = 5;
 const minRadius = 1;
 const worldsToPlace = ['flat'];
+const signBlocks = ['Wall Sign', 'Standing Sign'];
 
 export function Editing() {
   const {
@@ -24,6 +25,7 @@ export function Editing() {
     updateHooks,
     setChatItems,
     blockEntities,
+    method,
   } = useVoxelize();
 
   const { playAudio } = useAudio();
@@ -83,6 +85,12 @@ export function Editing() {
       ]);
     };
 
+    // Plots and protected regions are checked on the server, which only takes edits
+    // through a method so it knows who made them.
+    const applyUpdates = (updates: BlockUpdate[]) => {
+      method?.call('edit-voxels', { updates });
+    };
+
     const getAdminCheck = (target: Coords3) => {
       const [vx, vy, vz] = target;
 
@@ -135,7 +143,7 @@ export function Editing() {
         }
       }
 
-      if (updates.length) world.updateVoxels(updates);
+      if (updates.length) applyUpdates(updates);
     };
 
     const bulkPlace = () => {
@@ -155,6 +163,20 @@ export function Editing() {
       const updates: BlockUpdate[] = [];
       const block = world.getBlockById(itemSlots.getFocused().content);
 
+      // Signs go through their own method, so the server knows who placed them.
+      if (method && signBlocks.includes(block.name)) {
+        method.call('place-sign', {
+          vx,
+          vy,
+          vz,
+          type: block.id,
+          rotation,
+          yRotation,
+          text: '',
+        });
+        return;
+      }
+
       for (let x = -radius; x <= radius; x++) {
         for (let y = -radius; y <= radius; y++) {
           for (let z = -radius; z <= radius; z++) {
@@ -173,7 +195,7 @@ export function Editing() {
         }
       }
 
-      if (updates.length) world.updateVoxels(updates);
+      if (updates.length) applyUpdates(updates);
     };
 
     const maxCols = itemSlots.options.horizontalCount;
@@ -203,14 +225,12 @@ export function Editing() {
       'Twitter',
       'Mail',
       'BuyMeACoffee',
-      'Trophy (mc.js)',
-      'Trophy (voxelize)',
-      'Trophy (modern-graphql-tutorial)',
-      'Trophy (mine.js)',
-      'Trophy (rust-typescript-template)',
-      'Trophy (mc.js-legacy)',
     ];
 
+    // Every project's trophy is named "Trophy (<project>)".
+    const shouldSkip = (name = '') =>
+      blocksToSkip.includes(name) || name.startsWith('Trophy (');
+
     inputs.click('right', () => {
       if (!voxelInteract.potential) return;
 
@@ -222,7 +242,7 @@ export function Editing() {
       if (voxelInteract.target) {
         const [tvx, tvy, tvz] = voxelInteract.target || [0, 0, 0];
         const block = world.getBlockAt(tvx, tvy, tvz);
-        if (blocksToSkip.includes(block?.name || '')) return;
+        if (shouldSkip(block?.name)) return;
 
         // handle entity right click
         if (block.isEntity) {
@@ -270,6 +290,7 @@ export function Editing() {
     voxelInteract,
     world,
     worldName,
+    method,
   ]);
 
   return null;
@@ -7,10 +7,10 @@ import { useVoxelize } from '../hooks/useVoxelize';
 import { isAdmin } from '../utils/isAdmin';
 
 export const IV = () => {
-  const { chat, world, rigidControls } = useVoxelize();
+  const { chat, world, method, rigidControls } = useVoxelize();
 
   useEffect(() => {
-    if (!chat || !world || !rigidControls) return;
+    if (!chat || !world || !method || !rigidControls) return;
     if (!isAdmin()) return;
 
     chat.addCommand('iv', (rest) => {
@@ -19,11 +19,12 @@ export const IV = () => {
       ImageVoxelizer.build(
         url,
         world,
+        method,
         new Vector3(...rigidControls.voxel),
         options,
       );
     });
-  }, [chat, world, rigidControls]);
+  }, [chat, world, method, rigidControls]);
 
   return <></>;
 };
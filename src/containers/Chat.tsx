@@ -128,7 +128,7 @@ export function Chat() {
           updates.push({ vx: vx + x, vy: vy + y, vz: vz + z, type: block.id });
         }
 
-        world.updateVoxels(updates);
+        method.call('edit-voxels', { updates });
       });
 
       const placeTextAt = (text: string, coords: Coords3) => {
@@ -260,6 +260,10 @@ export function Chat() {
       });
     }
 
+    chat.addCommand('claim', () => {
+      method.call('claim-plot', { voxel: rigidControls.voxel });
+    });
+
     chat.onChat = (chat: ChatItem) => {
       setChatItems((prev) => [...prev, chat]);
       showChatList();
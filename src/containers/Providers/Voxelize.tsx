@@ -49,6 +49,7 @@ import Amethyst from '../../assets/images/blocks/amethyst.png';
 import BlueLaceAgate from '../../assets/images/blocks/blue_lace_agate.png';
 import Diorite from '../../assets/images/blocks/diorite_block.png';
 import MossAgate from '../../assets/images/blocks/moss_agate.png';
+import { listenToBlockData } from '../../core/block-data';
 import { BreakParticles } from '../../core/particles';
 import { makeRegistry } from '../../core/registry';
 
@@ -65,6 +66,7 @@ import { BlockEntities } from '@/src/core/block-entities';
 import { Trigger, Triggers } from '@/src/core/trigger';
 import type { ChatItem } from '@/src/types';
 import { isAdmin } from '@/src/utils/isAdmin';
+import { getPlayerKey } from '@/src/utils/playerKey';
 import { getCoreUrl } from '@/src/utils/urls';
 
 ColorText.SPLITTER = '$';
@@ -89,10 +91,15 @@ export type PeerRole = 'OWNER' | 'GUEST';
 export type PeersData = {
   direction: number[];
   position: number[];
-  role: PeerRole;
+  // Handed out by the server, clients don't get to say what they are.
+  role?: PeerRole;
+  // Sent by admins so the server gives them the owner role.
+  adminKey?: string;
+  // Sent by everyone so the server can tell players apart across visits.
+  playerKey?: string;
 };
 
-function paintCharacterByRole(character: Character, role: PeerRole) {
+function paintCharacterByRole(character: Character, role?: PeerRole) {
   if (role === 'GUEST' || !role) {
     // ...copied from voxelize
     character.head.paint('all', new THREE.Color('#96baff'));
@@ -453,7 +460,10 @@ export function VoxelizeProvider({
         metadata: {
           position: [px, py, pz],
           direction: [dx, dy, dz],
-          role: isUserAdmin ? 'OWNER' : 'GUEST',
+          adminKey: isUserAdmin
+            ? localStorage.getItem('shaoruu.io-admin') ?? undefined
+            : undefined,
+          playerKey: getPlayerKey(),
         },
       };
     };
@@ -738,6 +748,18 @@ export function VoxelizeProvider({
     network.register(chat);
     network.register(events);
 
+    // The server has already sent the voxels back as they are, so this only lets the
+    // player know why their edit didn't go through.
+    events.on('edit-rejected', ({ voxels }: { voxels: Coords3[] }) => {
+      chat.onChat?.({
+        type: 'chat',
+        sender: '[SYSTEM]',
+        body: `$gray$You can't edit ${
+          voxels.length === 1 ? 'that block' : `${voxels.length} of those blocks`
+        } here.`,
+      });
+    });
+
     chatRef.current = chat;
 
     if (worldName === 'main') {
@@ -851,6 +873,7 @@ export function VoxelizeProvider({
       await world.loader.load();
       await world.initialize();
       await makeRegistry(world);
+      await listenToBlockData(world, events, method);
 
       /* -------------------------------------------------------------------------- */
       /*                         POST-INTIALIZE PREPARATIONS                        */
@@ -0,0 +1,156 @@
+import type { Coords3, Events, Method, World } from '@voxelize/core';
+import { CanvasTexture, NearestFilter, SRGBColorSpace } from 'three';
+
+import Obsidian from '../assets/images/blocks/obsidian_block.png';
+
+type BlockDataPayload = {
+  voxel: Coords3;
+  id: number;
+  data: any;
+};
+
+const width = 400;
+const height = (width / 0.8) * 0.3;
+const border = width * 0.025;
+
+function drawBackground(ctx: CanvasRenderingContext2D) {
+  ctx.fillStyle = '#240A34';
+  ctx.fillRect(0, 0, width, height);
+  ctx.fillStyle = '#222831';
+  ctx.fillRect(border, border, width - border * 2, height - border * 2);
+}
+
+// A big value with smaller lines of labels under it.
+function drawText(canvas: HTMLCanvasElement, [value, ...labels]: string[]) {
+  const ctx = canvas.getContext('2d');
+  if (!ctx) return;
+
+  drawBackground(ctx);
+
+  ctx.textAlign = 'center';
+  ctx.fillStyle = '#FFFFFF';
+  ctx.font = `${width * 0.14}px ConnectionSerif-d20X`;
+  ctx.fillText(value, width / 2, height / 2 + width * 0.03);
+
+  ctx.fillStyle = '#aaa';
+  ctx.font = `${width * 0.05}px ConnectionSerif-d20X`;
+  labels.forEach((label, index) => {
+    ctx.fillText(label, width / 2, height / 2 + width * (0.1 + index * 0.06));
+  });
+}
+
+// A bar filled up to `share`, with a caption under it.
+function drawProgress(
+  canvas: HTMLCanvasElement,
+  share: number,
+  caption: string,
+) {
+  const ctx = canvas.getContext('2d');
+  if (!ctx) return;
+
+  drawBackground(ctx);
+
+  const padding = width * 0.05;
+  const barWidth = width - padding * 2;
+  const barHeight = height - padding - width * 0.1;
+
+  ctx.fillStyle = '#F7EEDD';
+  ctx.beginPath();
+  ctx.roundRect(padding, padding, barWidth, barHeight, 4);
+  ctx.fill();
+
+  ctx.fillStyle = '#4CCD99';
+  ctx.beginPath();
+  ctx.roundRect(
+    padding,
+    padding,
+    barWidth * Math.min(Math.max(share, 0), 1),
+    barHeight,
+    [4, 0, 0, 4],
+  );
+  ctx.fill();
+
+  ctx.textAlign = 'center';
+  ctx.fillStyle = '#FFFFFF';
+  ctx.font = `${width * 0.04}px ConnectionSerif-d20X`;
+  ctx.fillText(caption, width / 2, height - padding);
+}
+
+// How each display block draws the value the server computed for it. Everything it shows
+// comes from the server, so every player sees the same time in the server's timezone.
+const displays: Record<
+  string,
+  (canvas: HTMLCanvasElement, data: any) => void
+> = {
+  'Current Time': (canvas, { time, date, timezone }) =>
+    drawText(canvas, [time, date, timezone]),
+  'Year Percentage': (canvas, { year, percentage }) =>
+    drawProgress(
+      canvas,
+      percentage,
+      `${Math.round(percentage * 100)}% of ${year} passed`,
+    ),
+  'Visitor Counter': (canvas, { count }) =>
+    drawText(canvas, [`${count}`, count === 1 ? 'visitor' : 'visitors']),
+  Uptime: (canvas, { seconds }) => {
+    const hours = Math.floor(seconds / 3600);
+    const minutes = Math.floor((seconds % 3600) / 60);
+    drawText(canvas, [
+      `${hours}h ${minutes.toString().padStart(2, '0')}m`,
+      'uptime',
+    ]);
+  },
+};
+
+// Draws the values the server sends in `block-data` events onto the front of the display
+// blocks. Every voxel of a block shows the same value, so each block has one texture. The
+// server only pushes them every minute, so the current ones are asked for right away.
+export async function listenToBlockData(
+  world: World,
+  events: Events,
+  method: Method,
+) {
+  const canvases: Record<number, [HTMLCanvasElement, CanvasTexture]> = {};
+
+  for (const name of Object.keys(displays)) {
+    const block = world.getBlockByName(name);
+    if (!block) continue;
+
+    const canvas = document.createElement('canvas');
+    canvas.width = width;
+    canvas.height = height;
+
+    const texture = new CanvasTexture(canvas);
+    texture.minFilter = NearestFilter;
+    texture.magFilter = NearestFilter;
+    texture.colorSpace = SRGBColorSpace;
+
+    drawText(canvas, ['...']);
+
+    await world.applyBlockTexture(
+      name,
+      ['px', 'nx', 'py', 'ny', 'pz', 'nz'],
+      Obsidian,
+    );
+    await world.applyBlockTexture(name, 'pz', texture);
+
+    canvases[block.id] = [canvas, texture];
+  }
+
+  const draw = (payload: BlockDataPayload) => {
+    const display = canvases[payload.id];
+    const block = world.getBlockById(payload.id);
+    if (!display || !block) return;
+
+    const [canvas, texture] = display;
+    displays[block.name](canvas, payload.data);
+    texture.needsUpdate = true;
+  };
+
+  events.on('block-data', draw);
+  events.on('block-data-all', (payloads: BlockDataPayload[]) => {
+    payloads.forEach(draw);
+  });
+
+  method.call('block-data');
+}
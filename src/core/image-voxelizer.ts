@@ -1,4 +1,4 @@
-import type { AtlasTexture, BlockUpdate, World } from '@voxelize/core';
+import type { AtlasTexture, BlockUpdate, Method, World } from '@voxelize/core';
 import type { Vector3 } from 'three';
 
 /**
@@ -42,6 +42,7 @@ const defaultOptions: ImageVoxelizerOptions = {
  * ImageVoxelizer.build(
  *   "https://i.imgur.com/0Z0Z0Z0.png",
  *   world,
+ *   method,
  *   new THREE.Vector3(0, 0, 0),
  *   {
  *     width: 64,
@@ -116,7 +117,8 @@ export class ImageVoxelizer {
    * Build a list of block updates that corresponds to a mosaic of the given image using the textures registered in the given world's registry.
    *
    * @param imgURL The URL of the image to be voxelized. This will be used to create an `Image` object.
-   * @param world The world to be updated.
+   * @param world The world whose registry the image is matched against.
+   * @param method The method to send the updates through, so the server checks them like any other edit.
    * @param position The position to start voxelizing the image. This will be the bottom middle of the voxelized image.
    * @param options The extra options to process the image voxelization.
    * @returns A list of block updates that corresponds to a mosaic of the given image.
@@ -124,6 +126,7 @@ export class ImageVoxelizer {
   static build = async (
     imgURL: string,
     world: World,
+    method: Method,
     position: Vector3,
     partialOptions: Partial<ImageVoxelizerOptions>,
   ) => {
@@ -311,7 +314,7 @@ export class ImageVoxelizer {
       return false;
     }
 
-    world.updateVoxels(updates);
+    method.call('edit-voxels', { updates });
 
     return true;
   };
@@ -1,5 +1,5 @@
 import { customShaders, type World } from '@voxelize/core';
-import { CanvasTexture, Color, NearestFilter, SRGBColorSpace } from 'three';
+import { Color } from 'three';
 
 import Amethyst from '../assets/images/blocks/amethyst.png';
 import Andersite from '../assets/images/blocks/andersite_block.png';
@@ -75,6 +75,7 @@ import OakLeaves from '../assets/voxelize/pixel-perfection/leaves_oak.png';
 import OakLogSide from '../assets/voxelize/pixel-perfection/log_oak_side.png';
 import OakLogTop from '../assets/voxelize/pixel-perfection/log_oak_top.png';
 import Stone from '../assets/voxelize/pixel-perfection/stone.png';
+import Gravel from '../assets/voxelize/vibes/gravel_stone.png';
 
 export async function makeRegistry(world: World) {
   const all = ['px', 'nx', 'py', 'ny', 'pz', 'nz'];
@@ -154,6 +155,7 @@ export async function makeRegistry(world: World) {
       'voxelize',
       'mc.js-legacy',
       'rust-typescript-template',
+      'lunar-lander-ai',
     ]) {
       const trophyName = `Trophy (${project})`;
 
@@ -189,212 +191,6 @@ export async function makeRegistry(world: World) {
     }
   }
 
-  await world.applyBlockTexture('Year Percentage', all, Obsidian);
-
-  const createYearProgressCanvas = () => {
-    const canvas = document.createElement('canvas');
-    const ctx = canvas.getContext('2d');
-    if (!ctx) {
-      throw new Error('Failed to get canvas context');
-    }
-    const width = 400;
-    const height = (width / 0.8) * 0.3;
-
-    canvas.width = width;
-    canvas.height = height;
-
-    // Padding adjustments
-    const padding = width * 0.05; // 5% padding
-    const border = padding * 0.5;
-    const bottomTextPadding = width * 0.1; // Additional padding for text at the bottom
-    const paddedWidth = width - padding * 2;
-    const paddedHeight = height - padding - bottomTextPadding; // Adjusted for bottom text
-
-    // Background
-    ctx.fillStyle = '#240A34'; // Purple border
-    ctx.fillRect(0, 0, width, height);
-    ctx.fillStyle = '#222831'; // Black background
-    ctx.fillRect(border, border, width - border * 2, height - border * 2);
-
-    // Calculate year progress
-    const now = new Date();
-    const start = new Date(now.getFullYear(), 0, 0);
-    const end = new Date(now.getFullYear() + 1, 0, 0);
-    const yearPassed =
-      (now.getTime() - start.getTime()) / (end.getTime() - start.getTime());
-
-    // Function to draw a rectangle with optional rounded right border radius
-    function drawRoundedRect(
-      ctx: CanvasRenderingContext2D,
-      x: number,
-      y: number,
-      width: number,
-      height: number,
-      radius: number,
-      roundedRight: boolean = true, // Added parameter to control right border radius rounding
-    ): void {
-      ctx.beginPath();
-      ctx.moveTo(x + radius, y);
-      ctx.lineTo(x + width - (roundedRight ? radius : 0), y); // Conditional radius based on roundedRight
-      if (roundedRight) {
-        ctx.quadraticCurveTo(x + width, y, x + width, y + radius);
-        ctx.lineTo(x + width, y + height - radius);
-        ctx.quadraticCurveTo(
-          x + width,
-          y + height,
-          x + width - radius,
-          y + height,
-        );
-      } else {
-        ctx.lineTo(x + width, y); // Straight line if not rounded
-        ctx.lineTo(x + width, y + height);
-        ctx.lineTo(x + width - radius, y + height); // Straight line to start left rounding
-      }
-      ctx.lineTo(x + radius, y + height);
-      ctx.quadraticCurveTo(x, y + height, x, y + height - radius);
-      ctx.lineTo(x, y + radius);
-      ctx.quadraticCurveTo(x, y, x + radius, y);
-      ctx.closePath();
-      ctx.fill();
-    }
-
-    // Progress bar background with border radius
-    ctx.fillStyle = '#F7EEDD'; // Background color for the progress bar
-    drawRoundedRect(ctx, padding, padding, paddedWidth, paddedHeight, 4, true); // Keep right border rounded for background
-
-    // Progress bar with optional rounded right border radius
-    ctx.fillStyle = '#4CCD99'; // Green bar
-    const progressBarWidth: number = paddedWidth * yearPassed;
-    drawRoundedRect(
-      ctx,
-      padding,
-      padding,
-      progressBarWidth,
-      paddedHeight,
-      4,
-      false,
-    ); // Turn off right border rounding for progress bar
-    // Data text
-    ctx.font = `${width * 0.04}px ConnectionSerif-d20X`;
-    ctx.fillStyle = '#FFFFFF'; // White text
-    const text = `${Math.round(yearPassed * 100)}% of ${
-      // current year
-      now.getFullYear()
-    } passed`;
-    ctx.textAlign = 'center'; // Align text to center horizontally
-    ctx.fillText(text, width / 2, height - padding); // Center text
-
-    return canvas;
-  };
-  const yearProgressCanvas = createYearProgressCanvas();
-  const yearProgressTexture = new CanvasTexture(yearProgressCanvas);
-
-  yearProgressTexture.magFilter = NearestFilter;
-  yearProgressTexture.minFilter = NearestFilter;
-  yearProgressTexture.colorSpace = SRGBColorSpace;
-
-  await world.applyBlockTexture('Year Percentage', 'pz', yearProgressTexture);
-
-  await world.applyBlockTexture('Current Time', all, Obsidian);
-
-  const currentTimeWidth = 400;
-  const currentTimeHeight = (currentTimeWidth / 0.8) * 0.3;
-  // Padding adjustments
-  const currentTimePadding = currentTimeWidth * 0.05; // 5% padding
-  const currentTimeBorder = currentTimePadding * 0.5;
-
-  const createCurrentTimeCanvas = () => {
-    const canvas = document.createElement('canvas');
-    canvas.style.imageRendering = 'pixelated'; // Ensures no blur on the canvas
-
-    const ctx = canvas.getContext('2d');
-    if (!ctx) {
-      throw new Error('Failed to get canvas context');
-    }
-
-    canvas.width = currentTimeWidth;
-    canvas.height = currentTimeHeight;
-
-    return canvas;
-  };
-
-  const currentTimeCanvas = createCurrentTimeCanvas();
-  const currentTimeTexture = new CanvasTexture(currentTimeCanvas);
-
-  // Function to update time every second with a more readable format and display the current date
-  const updateTime = () => {
-    const ctx = currentTimeCanvas.getContext('2d');
-
-    if (!ctx) return;
-
-    const now = new Date();
-    const minutes = now.getMinutes().toString().padStart(2, '0');
-    const seconds = now.getSeconds().toString().padStart(2, '0');
-    // Use a more readable time format: HH:MM:SS AM/PM
-    const amPm = now.getHours() >= 12 ? 'PM' : 'AM';
-    const formattedHours = now.getHours() % 12 || 12; // Converts 24h to 12h format and treats 0 as 12
-    const timeString = `${formattedHours
-      .toString()
-      .padStart(2, '0')}:${minutes}:${seconds} ${amPm}`;
-    const dateString = now.toLocaleDateString(); // Get current date in local format
-
-    // Clear previous time and date display
-    // Background
-    ctx.fillStyle = '#240A34'; // Purple border
-    ctx.fillRect(0, 0, currentTimeWidth, currentTimeHeight);
-    ctx.fillStyle = '#222831'; // Black background
-    ctx.fillRect(
-      currentTimeBorder,
-      currentTimeBorder,
-      currentTimeWidth - currentTimeBorder * 2,
-      currentTimeHeight - currentTimeBorder * 2,
-    );
-
-    // Display current time in a more readable format
-    ctx.font = `${currentTimeWidth * 0.14}px ConnectionSerif-d20X`; // Adjust font size for the new format
-    ctx.fillStyle = '#FFFFFF'; // White text for better contrast
-    ctx.textAlign = 'center'; // Center text horizontally
-    // Adjust position for centering the text with the new format, considering additional date display
-    const textYPosition =
-      currentTimeHeight / 2 + currentTimePadding - currentTimeWidth * 0.05;
-    ctx.fillText(timeString, currentTimeWidth / 2, textYPosition);
-
-    // Display current date below the time
-    ctx.font = `${currentTimeWidth * 0.055}px ConnectionSerif-d20X`; // Slightly smaller font for the date
-    ctx.fillText(
-      dateString,
-      currentTimeWidth / 2,
-      textYPosition + currentTimeWidth * 0.08, // Position the date below the time
-    );
-    ctx.font = `${currentTimeWidth * 0.035}px ConnectionSerif-d20X`; // Slightly smaller font for the date
-    ctx.fillStyle = '#aaa';
-    ctx.fillText(
-      `${Intl.DateTimeFormat().resolvedOptions().timeZone}`,
-      currentTimeWidth / 2,
-      textYPosition + currentTimeWidth * 0.12, // Position the timezone slightly below the date
-    );
-
-    currentTimeTexture.needsUpdate = true;
-  };
-
-  // Initial time update
-  updateTime();
-
-  // Update time every second
-  setInterval(updateTime, 1000);
-
-  currentTimeTexture.minFilter = NearestFilter;
-  currentTimeTexture.magFilter = NearestFilter;
-  currentTimeTexture.colorSpace = SRGBColorSpace;
-
-  await world.applyBlockTexture('Current Time', 'pz', currentTimeTexture);
-
-  console.log(
-    world
-      .getBlockByName('Year Percentage')
-      .faces.find((face) => face.name === 'pz'),
-  );
-
   await world.applyBlockTexture('Trophy (mc.js)', 'cuppz', MCJS);
   await world.applyBlockTexture('Trophy (mine.js)', 'cuppz', MineJS);
   await world.applyBlockTexture(
@@ -488,6 +284,7 @@ export async function makeRegistry(world: World) {
   await world.applyBlockTexture('Black Concrete', all, BlackConcrete);
   await world.applyBlockTexture('Blue Concrete', all, BlueConcrete);
   await world.applyBlockTexture('Glass', all, Glass);
+  await world.applyBlockTexture('Gravel', all, Gravel);
   await world.applyBlockTexture(
     'Birch Log',
     ['px', 'nx', 'pz', 'nz'],
@@ -0,0 +1,14 @@
+const PLAYER_KEY = 'shaoruu.io-player';
+
+// A random key this browser keeps, so the server knows it's the same player every time
+// they join, and their plots and signs stay theirs.
+export function getPlayerKey() {
+  let key = localStorage.getItem(PLAYER_KEY);
+
+  if (!key) {
+    key = crypto.randomUUID();
+    localStorage.setItem(PLAYER_KEY, key);
+  }
+
+  return key;
+}